@@ -0,0 +1,179 @@
+//! Derive macros for `phoenix_channels_client`, which re-exports them with its `derive` feature.
+#![warn(missing_docs)]
+
+use proc_macro::TokenStream;
+use proc_macro2::{Ident, TokenStream as TokenStream2};
+use quote::quote;
+use syn::spanned::Spanned;
+use syn::{parse_macro_input, Data, DeriveInput, Fields, LitStr, Variant};
+
+/// Derives `phoenix_channels_client::ChannelEvents` for an enum with a variant per event.
+///
+/// * Unit variants are events without a payload.  They are sent with an empty JSON object and
+///   decoded regardless of the payload.
+/// * Newtype variants are events whose payload is the field serialized with `serde`.
+///
+/// The event name is the variant's name in `snake_case`, unless renamed with
+/// `#[channel_event(rename = "...")]`.
+#[proc_macro_derive(ChannelEvents, attributes(channel_event))]
+pub fn derive_channel_events(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let data = match input.data {
+        Data::Enum(data) => data,
+        Data::Struct(data) => {
+            return Err(syn::Error::new(
+                data.struct_token.span,
+                "ChannelEvents can only be derived for enums",
+            ))
+        }
+        Data::Union(data) => {
+            return Err(syn::Error::new(
+                data.union_token.span,
+                "ChannelEvents can only be derived for enums",
+            ))
+        }
+    };
+
+    let mut encode_arms = Vec::with_capacity(data.variants.len());
+    let mut decode_arms = Vec::with_capacity(data.variants.len());
+
+    for variant in &data.variants {
+        let ident = &variant.ident;
+        let name = event_name(variant)?;
+
+        match &variant.fields {
+            Fields::Unit => {
+                encode_arms.push(quote! {
+                    Self::#ident => ::phoenix_channels_client::__private::encode_empty(#name)
+                });
+                decode_arms.push(quote! {
+                    ::core::option::Option::Some(#name) => ::core::result::Result::Ok(Self::#ident)
+                });
+            }
+            Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+                encode_arms.push(quote! {
+                    Self::#ident(payload) => ::phoenix_channels_client::__private::encode(#name, &payload)
+                });
+                decode_arms.push(quote! {
+                    ::core::option::Option::Some(#name) => {
+                        ::phoenix_channels_client::__private::decode(event, payload).map(Self::#ident)
+                    }
+                });
+            }
+            fields => {
+                return Err(syn::Error::new(
+                    fields.span(),
+                    "ChannelEvents variants must be unit variants or newtype variants with the payload type",
+                ))
+            }
+        }
+    }
+
+    let ident = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::phoenix_channels_client::ChannelEvents for #ident #type_generics #where_clause {
+            fn into_event_payload(
+                self,
+            ) -> ::core::result::Result<
+                (::phoenix_channels_client::Event, ::phoenix_channels_client::Payload),
+                ::phoenix_channels_client::ChannelEventsError,
+            > {
+                match self {
+                    #(#encode_arms,)*
+                }
+            }
+
+            fn from_event_payload(
+                event_payload: ::phoenix_channels_client::EventPayload,
+            ) -> ::core::result::Result<Self, ::phoenix_channels_client::ChannelEventsError> {
+                let ::phoenix_channels_client::EventPayload { event, payload } = event_payload;
+
+                match ::phoenix_channels_client::__private::user_event_name(&event) {
+                    #(#decode_arms,)*
+                    _ => ::core::result::Result::Err(
+                        ::phoenix_channels_client::ChannelEventsError::UnknownEvent { event },
+                    ),
+                }
+            }
+        }
+    })
+}
+
+/// `#[channel_event(rename = "...")]` or the `snake_case` name of the variant.
+fn event_name(variant: &Variant) -> syn::Result<String> {
+    let mut rename = None;
+
+    for attr in &variant.attrs {
+        if attr.path().is_ident("channel_event") {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename") {
+                    rename = Some(meta.value()?.parse::<LitStr>()?.value());
+
+                    Ok(())
+                } else {
+                    Err(meta.error("unsupported channel_event attribute, expected `rename`"))
+                }
+            })?;
+        }
+    }
+
+    Ok(rename.unwrap_or_else(|| snake_case(&variant.ident)))
+}
+
+/// `NewMessage` to `new_message` and `HTTPError` to `http_error`.
+fn snake_case(ident: &Ident) -> String {
+    let chars: Vec<char> = ident.to_string().chars().collect();
+    let mut snake_case = String::with_capacity(chars.len() + 4);
+
+    for (i, c) in chars.iter().enumerate() {
+        if c.is_uppercase() {
+            let previous = i.checked_sub(1).map(|i| chars[i]);
+            let next = chars.get(i + 1);
+            let starts_word = match previous {
+                None => false,
+                Some(previous) if previous.is_lowercase() || previous.is_numeric() => true,
+                Some(previous) => {
+                    previous.is_uppercase() && next.map_or(false, |next| next.is_lowercase())
+                }
+            };
+
+            if starts_word {
+                snake_case.push('_');
+            }
+
+            snake_case.extend(c.to_lowercase());
+        } else {
+            snake_case.push(*c);
+        }
+    }
+
+    snake_case
+}
+
+#[cfg(test)]
+mod tests {
+    use proc_macro2::Span;
+
+    use super::*;
+
+    #[test]
+    fn snake_case_splits_words() {
+        for (ident, expected) in [
+            ("Typing", "typing"),
+            ("NewMessage", "new_message"),
+            ("HTTPError", "http_error"),
+            ("Ping2Pong", "ping2_pong"),
+        ] {
+            assert_eq!(snake_case(&Ident::new(ident, Span::call_site())), expected);
+        }
+    }
+}
@@ -0,0 +1,527 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use futures::StreamExt;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, BufReader};
+use tokio::sync::broadcast;
+use url::Url;
+
+use phoenix_channels_client::{Channel, Event, EventPayload, Payload, Socket, Topic};
+
+const USAGE: &str = "\
+Usage: phx-cli [--timeout <SECONDS>] [--script <FILE>] <URL> [--join <TOPIC> [<PAYLOAD>]]...
+
+Connects to the Phoenix socket at URL, such as ws://localhost:4000/socket/websocket, joins each
+TOPIC with the JSON PAYLOAD and runs commands read from standard input, or from FILE with
+--script, one per line.  Events and status changes are printed as they are received, prefixed
+with the UTC time.
+
+Options:
+  --timeout <SECONDS>  How long to wait to connect, join, call and wait [default: 5]
+  --script <FILE>      Run the commands in FILE instead, exiting with an error at the first that
+                       fails
+  --join <TOPIC>       Join TOPIC after connecting, with PAYLOAD if given
+  --help               Print this help";
+
+const COMMANDS: &str = "\
+Commands:
+  join <TOPIC> [<PAYLOAD>]            Join TOPIC with the JSON PAYLOAD
+  leave <TOPIC>                       Leave TOPIC
+  cast <TOPIC> <EVENT> [<PAYLOAD>]    Cast EVENT with the JSON PAYLOAD to TOPIC
+  call <TOPIC> <EVENT> [<PAYLOAD>]    Call EVENT with the JSON PAYLOAD on TOPIC and print the reply
+  wait <TOPIC> <EVENT>                Wait for EVENT on TOPIC, unless it was received since the
+                                      last wait
+  sleep <MILLISECONDS>                Wait for MILLISECONDS
+  help                                Print the commands
+  quit                                Leave all topics and disconnect
+
+Blank lines and lines starting with # are ignored.";
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let args = match Args::parse(std::env::args().skip(1)) {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            println!("{}\n\n{}", USAGE, COMMANDS);
+
+            return ExitCode::SUCCESS;
+        }
+        Err(error) => {
+            eprintln!("{}\n\n{}", error, USAGE);
+
+            return ExitCode::from(2);
+        }
+    };
+
+    match run(args).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            print_line(format!("error: {}", error));
+
+            ExitCode::FAILURE
+        }
+    }
+}
+
+struct Args {
+    url: Url,
+    timeout: Duration,
+    script: Option<PathBuf>,
+    joins: Vec<(String, Option<String>)>,
+}
+impl Args {
+    /// The [Args], or [None] if `--help` was passed.
+    fn parse(args: impl Iterator<Item = String>) -> Result<Option<Self>, String> {
+        let mut args = args.peekable();
+        let mut url = None;
+        let mut timeout = Duration::from_secs(5);
+        let mut script = None;
+        let mut joins = Vec::new();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--help" | "-h" => return Ok(None),
+                "--timeout" => {
+                    let seconds = args.next().ok_or("--timeout needs <SECONDS>")?;
+                    let seconds = seconds
+                        .parse()
+                        .ok()
+                        .filter(|seconds: &f64| seconds.is_finite() && *seconds >= 0.0)
+                        .ok_or_else(|| format!("invalid --timeout {}", seconds))?;
+                    timeout = Duration::from_secs_f64(seconds);
+                }
+                "--script" => {
+                    script = Some(args.next().ok_or("--script needs <FILE>")?.into());
+                }
+                "--join" => {
+                    let topic = args.next().ok_or("--join needs <TOPIC>")?;
+                    let payload = match args.peek() {
+                        Some(next) if !next.starts_with("--") => args.next(),
+                        _ => None,
+                    };
+                    joins.push((topic, payload));
+                }
+                _ if arg.starts_with("--") => return Err(format!("unknown option {}", arg)),
+                _ if url.is_none() => {
+                    let parsed =
+                        Url::parse(&arg).map_err(|error| format!("invalid URL: {}", error))?;
+                    url = Some(parsed);
+                }
+                _ => return Err(format!("unexpected argument {}", arg)),
+            }
+        }
+
+        Ok(Some(Self {
+            url: url.ok_or("missing <URL>")?,
+            timeout,
+            script,
+            joins,
+        }))
+    }
+}
+
+async fn run(args: Args) -> Result<(), String> {
+    let Args {
+        url,
+        timeout,
+        script,
+        joins,
+    } = args;
+    let socket = Socket::spawn(url).map_err(|error| error.to_string())?;
+    let mut statuses = socket.statuses().into_stream();
+    tokio::spawn(async move {
+        while let Some(result) = statuses.next().await {
+            match result {
+                Ok(Ok(status)) => print_line(format!("socket {:?}", status)),
+                Ok(Err(error)) => print_line(format!("socket error {}", error)),
+                Err(error) => print_line(format!("socket {}", error)),
+            }
+        }
+    });
+    socket
+        .connect(timeout)
+        .await
+        .map_err(|error| error.to_string())?;
+
+    let (event_tx, event_rx) = broadcast::channel(100);
+    let mut cli = Cli {
+        socket: socket.clone(),
+        timeout,
+        channels: HashMap::new(),
+        event_tx,
+        event_rx,
+    };
+
+    for (topic, payload) in joins {
+        cli.join(&topic, payload.as_deref()).await?;
+    }
+
+    let result = match script {
+        Some(script) => {
+            let file = tokio::fs::File::open(&script)
+                .await
+                .map_err(|error| format!("{}: {}", script.display(), error))?;
+
+            cli.run_lines(BufReader::new(file), true).await
+        }
+        None => {
+            cli.run_lines(BufReader::new(tokio::io::stdin()), false)
+                .await
+        }
+    };
+
+    cli.leave_all().await;
+    socket.shutdown().await.ok();
+
+    result
+}
+
+/// Whether to keep reading commands.
+enum Flow {
+    Continue,
+    Quit,
+}
+
+struct Cli {
+    socket: Arc<Socket>,
+    timeout: Duration,
+    channels: HashMap<String, Arc<Channel>>,
+    /// Every [EventPayload] received on a joined topic, for `wait`.
+    event_tx: broadcast::Sender<(String, EventPayload)>,
+    /// The [EventPayload]s `wait` hasn't looked at yet, so an event received before `wait` still
+    /// counts.
+    event_rx: broadcast::Receiver<(String, EventPayload)>,
+}
+impl Cli {
+    /// Runs each command in `reader`.  When `script` is set, commands are echoed and the first
+    /// that fails is returned, otherwise failures are printed and the next command is read.
+    async fn run_lines<R: AsyncBufRead + Unpin>(
+        &mut self,
+        reader: R,
+        script: bool,
+    ) -> Result<(), String> {
+        let mut lines = reader.lines();
+
+        while let Some(line) = lines.next_line().await.map_err(|error| error.to_string())? {
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if script {
+                print_line(format!("> {}", line));
+            }
+
+            match self.run_command(line).await {
+                Ok(Flow::Continue) => (),
+                Ok(Flow::Quit) => break,
+                Err(error) if script => return Err(format!("{}: {}", line, error)),
+                Err(error) => print_line(format!("error: {}", error)),
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn run_command(&mut self, line: &str) -> Result<Flow, String> {
+        let (command, rest) = split_word(line);
+
+        match command {
+            "join" => {
+                let (topic, payload) = split_word(rest);
+                self.join(required(topic, "<TOPIC>")?, optional(payload))
+                    .await?;
+            }
+            "leave" => {
+                let topic = required(rest, "<TOPIC>")?;
+                let channel = self
+                    .channels
+                    .remove(topic)
+                    .ok_or_else(|| format!("{} is not joined", topic))?;
+                channel.leave().await.map_err(|error| error.to_string())?;
+                channel
+                    .shutdown()
+                    .await
+                    .map_err(|error| error.to_string())?;
+            }
+            "cast" => {
+                let (channel, event, payload) = self.event_payload(rest)?;
+                channel
+                    .cast(event, payload)
+                    .await
+                    .map_err(|error| error.to_string())?;
+            }
+            "call" => {
+                let (topic, _) = split_word(rest);
+                let (channel, event, payload) = self.event_payload(rest)?;
+                let reply = channel
+                    .call(event, payload, self.timeout)
+                    .await
+                    .map_err(|error| error.to_string())?;
+                print_line(format!("{} reply {}", topic, reply));
+            }
+            "wait" => {
+                let (topic, event) = split_word(rest);
+                let topic = required(topic, "<TOPIC>")?;
+                let event = Event::from_string(required(event, "<EVENT>")?.to_string());
+                let event_rx = &mut self.event_rx;
+
+                tokio::time::timeout(self.timeout, async {
+                    loop {
+                        match event_rx.recv().await {
+                            Ok((received_topic, event_payload))
+                                if received_topic == topic && event_payload.event == event =>
+                            {
+                                break
+                            }
+                            Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => (),
+                            Err(broadcast::error::RecvError::Closed) => break,
+                        }
+                    }
+                })
+                .await
+                .map_err(|_| format!("timeout waiting for {} on {}", event, topic))?;
+            }
+            "sleep" => {
+                let milliseconds = required(rest, "<MILLISECONDS>")?;
+                let milliseconds: u64 = milliseconds
+                    .parse()
+                    .map_err(|_| format!("invalid milliseconds {}", milliseconds))?;
+                tokio::time::sleep(Duration::from_millis(milliseconds)).await;
+            }
+            "help" => print_line(COMMANDS.to_string()),
+            "quit" | "exit" => return Ok(Flow::Quit),
+            _ => return Err(format!("unknown command {}; try help", command)),
+        }
+
+        Ok(Flow::Continue)
+    }
+
+    /// Creates a channel for `topic`, prints its events and statuses, and joins it.
+    async fn join(&mut self, topic: &str, payload: Option<&str>) -> Result<(), String> {
+        if self.channels.contains_key(topic) {
+            return Err(format!("{} is already joined", topic));
+        }
+
+        let payload = payload.map(parse_payload).transpose()?;
+        let channel = self
+            .socket
+            .channel(Topic::from_string(topic.to_string()), payload)
+            .await
+            .map_err(|error| error.to_string())?;
+
+        let mut events = channel.events().into_stream();
+        let event_tx = self.event_tx.clone();
+        let event_topic = topic.to_string();
+        tokio::spawn(async move {
+            while let Some(result) = events.next().await {
+                match result {
+                    Ok(event_payload) => {
+                        print_line(format!(
+                            "{} {} {}",
+                            event_topic, event_payload.event, event_payload.payload
+                        ));
+                        event_tx.send((event_topic.clone(), event_payload)).ok();
+                    }
+                    Err(error) => print_line(format!("{} {}", event_topic, error)),
+                }
+            }
+        });
+
+        let mut statuses = channel.statuses().into_stream();
+        let status_topic = topic.to_string();
+        tokio::spawn(async move {
+            while let Some(result) = statuses.next().await {
+                match result {
+                    Ok(Ok(status)) => print_line(format!("{} {:?}", status_topic, status)),
+                    Ok(Err(error)) => print_line(format!("{} join error {}", status_topic, error)),
+                    Err(error) => print_line(format!("{} {}", status_topic, error)),
+                }
+            }
+        });
+
+        if let Err(error) = channel.join(self.timeout).await {
+            channel.shutdown().await.ok();
+
+            return Err(error.to_string());
+        }
+
+        self.channels.insert(topic.to_string(), channel);
+
+        Ok(())
+    }
+
+    async fn leave_all(&mut self) {
+        for (_, channel) in self.channels.drain() {
+            channel.leave().await.ok();
+            channel.shutdown().await.ok();
+        }
+    }
+
+    /// The joined channel, [Event] and [Payload] in `<TOPIC> <EVENT> [<PAYLOAD>]`.
+    fn event_payload(&self, rest: &str) -> Result<(Arc<Channel>, Event, Payload), String> {
+        let (topic, rest) = split_word(rest);
+        let (event, payload) = split_word(rest);
+        let topic = required(topic, "<TOPIC>")?;
+        let channel = self
+            .channels
+            .get(topic)
+            .ok_or_else(|| format!("{} is not joined", topic))?
+            .clone();
+        let event = Event::from_string(required(event, "<EVENT>")?.to_string());
+        let payload = parse_payload(optional(payload).unwrap_or("{}"))?;
+
+        Ok((channel, event, payload))
+    }
+}
+
+/// The first whitespace-separated word in `line` and the rest of it.
+fn split_word(line: &str) -> (&str, &str) {
+    let line = line.trim_start();
+
+    match line.find(char::is_whitespace) {
+        Some(index) => (&line[..index], line[index..].trim_start()),
+        None => (line, ""),
+    }
+}
+
+fn required<'a>(word: &'a str, name: &str) -> Result<&'a str, String> {
+    if word.is_empty() {
+        Err(format!("missing {}", name))
+    } else {
+        Ok(word)
+    }
+}
+
+fn optional(word: &str) -> Option<&str> {
+    if word.is_empty() {
+        None
+    } else {
+        Some(word)
+    }
+}
+
+fn parse_payload(json: &str) -> Result<Payload, String> {
+    Payload::json_from_serialized(json.to_string())
+        .map_err(|error| format!("invalid payload {}: {}", json, error))
+}
+
+/// Prints `line` prefixed with the time of day in UTC.
+fn print_line(line: String) {
+    let since_epoch = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let seconds = since_epoch.as_secs();
+
+    println!(
+        "[{:02}:{:02}:{:02}.{:03}] {}",
+        seconds / 3600 % 24,
+        seconds / 60 % 60,
+        seconds % 60,
+        since_epoch.subsec_millis(),
+        line
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Option<Args>, String> {
+        Args::parse(args.iter().map(ToString::to_string))
+    }
+
+    #[test]
+    fn args_parse_url_options_and_joins() {
+        let args = parse(&[
+            "--timeout",
+            "0.5",
+            "--script",
+            "demo.phx",
+            "ws://localhost:4000/socket/websocket",
+            "--join",
+            "room:lobby",
+            r#"{"name":"demo"}"#,
+            "--join",
+            "room:other",
+        ])
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(args.url.as_str(), "ws://localhost:4000/socket/websocket");
+        assert_eq!(args.timeout, Duration::from_millis(500));
+        assert_eq!(args.script, Some(PathBuf::from("demo.phx")));
+        assert_eq!(
+            args.joins,
+            vec![
+                (
+                    "room:lobby".to_string(),
+                    Some(r#"{"name":"demo"}"#.to_string())
+                ),
+                ("room:other".to_string(), None)
+            ]
+        );
+    }
+
+    #[test]
+    fn args_parse_help() {
+        assert!(parse(&["ws://localhost:4000/socket/websocket", "--help"])
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn args_parse_rejects_invalid_timeouts() {
+        for timeout in ["-1", "NaN", "inf", "soon"] {
+            assert_eq!(
+                parse(&["--timeout", timeout, "ws://localhost:4000/socket/websocket"]).err(),
+                Some(format!("invalid --timeout {}", timeout))
+            );
+        }
+    }
+
+    #[test]
+    fn args_parse_rejects_missing_url() {
+        assert_eq!(
+            parse(&["--join", "room:lobby"]).err(),
+            Some("missing <URL>".to_string())
+        );
+    }
+
+    #[test]
+    fn split_word_splits_on_first_whitespace() {
+        assert_eq!(
+            split_word("  cast  room:lobby new_msg {}"),
+            ("cast", "room:lobby new_msg {}")
+        );
+        assert_eq!(split_word("quit"), ("quit", ""));
+        assert_eq!(split_word(""), ("", ""));
+    }
+
+    #[tokio::test]
+    async fn script_stops_at_first_failure() {
+        let socket =
+            Socket::spawn(Url::parse("ws://localhost:4000/socket/websocket").unwrap()).unwrap();
+        let (event_tx, event_rx) = broadcast::channel(1);
+        let mut cli = Cli {
+            socket: socket.clone(),
+            timeout: Duration::from_millis(10),
+            channels: HashMap::new(),
+            event_tx,
+            event_rx,
+        };
+        let lines = b"# comment\nsleep 1\nleave room:lobby\nleave room:other\n";
+
+        assert_eq!(
+            cli.run_lines(&lines[..], true).await,
+            Err("leave room:lobby: room:lobby is not joined".to_string())
+        );
+        assert_eq!(cli.run_lines(&lines[..], false).await, Ok(()));
+
+        socket.shutdown().await.unwrap();
+    }
+}
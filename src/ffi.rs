@@ -2,12 +2,18 @@
 //!
 //! [uniffi] should only be used in code under this namespace.
 
+pub mod absinthe;
+pub mod backoff;
 pub mod channel;
 mod http;
 pub mod io;
 pub mod json;
+pub mod listeners;
+pub mod live_view;
 pub mod message;
 pub mod observable_status;
+pub mod presence;
+pub mod serializer;
 pub mod socket;
 pub mod topic;
 pub mod web_socket;
@@ -0,0 +1,615 @@
+//! A client for GraphQL subscriptions served by `Absinthe.Phoenix`: sends each subscription
+//! document over the `__absinthe__:control` channel and receives its results, which Absinthe
+//! broadcasts on a topic of their own that is never joined.
+//!
+//! ```no_run
+//! # use std::collections::HashMap;
+//! # use std::sync::Arc;
+//! # use std::time::Duration;
+//! #
+//! # use phoenix_channels_client::{Socket, JSON};
+//! #
+//! async fn watch_comments(socket: Arc<Socket>, post_id: String) {
+//!     let absinthe = socket.absinthe().await.unwrap();
+//!     absinthe.join(Duration::from_secs(5)).await.unwrap();
+//!
+//!     let subscription = absinthe
+//!         .subscribe(
+//!             "subscription ($postId: ID!) { commentAdded(postId: $postId) { body } }".to_string(),
+//!             HashMap::from([("postId".to_string(), JSON::String { string: post_id })]),
+//!             Duration::from_secs(5),
+//!         )
+//!         .await
+//!         .unwrap();
+//!
+//!     while let Ok(result) = subscription.result().await {
+//!         println!("{:?}", result);
+//!     }
+//! }
+//! ```
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use futures::stream::{self, BoxStream};
+use futures::StreamExt;
+use log::error;
+use serde_json::{json, Value};
+use tokio::sync::{broadcast, mpsc};
+use tokio::task::JoinHandle;
+
+use crate::ffi::channel::statuses::ChannelStatuses;
+use crate::ffi::channel::{CallError, Channel, ChannelJoinError, ChannelStatus};
+use crate::ffi::json::JSON;
+use crate::ffi::message::{Event, Payload};
+use crate::ffi::observable_status::StatusesError;
+use crate::ffi::socket::Socket;
+use crate::rust;
+use crate::rust::message::Broadcast;
+
+/// The topic of the channel subscription documents are sent over.
+pub(crate) const CONTROL_TOPIC: &str = "__absinthe__:control";
+const DOC_EVENT: &str = "doc";
+const UNSUBSCRIBE_EVENT: &str = "unsubscribe";
+const SUBSCRIPTION_DATA_EVENT: &str = "subscription:data";
+
+/// GraphQL subscriptions over the `__absinthe__:control` [Channel], created with
+/// [Socket::absinthe](crate::Socket::absinthe).
+///
+/// When the [Channel] rejoins, the server has forgotten the subscriptions made over the previous
+/// join, so each [AbsintheSubscription] is resubscribed with the same document.  Dropping this
+/// does not leave the [Channel] and ends all of its [AbsintheSubscription]s.
+#[cfg_attr(
+    feature = "uniffi",
+    derive(uniffi::Object)
+)]
+pub struct Absinthe {
+    channel: Arc<Channel>,
+    shared: Arc<Shared>,
+    join_handle: JoinHandle<()>,
+}
+#[cfg_attr(
+    feature = "uniffi",
+    uniffi::export
+)]
+impl Absinthe {
+    /// The `__absinthe__:control` [Channel].
+    pub fn channel(&self) -> Arc<Channel> {
+        self.channel.clone()
+    }
+
+    /// Joins the `__absinthe__:control` [Channel] within `timeout`.
+    pub async fn join(&self, timeout: Duration) -> Result<(), ChannelJoinError> {
+        self.channel.join(timeout).await
+    }
+
+    /// Subscribes to the GraphQL subscription in `query` with `variables` within `timeout`, which
+    /// is also used for each resubscribe after the [Channel] rejoins.
+    ///
+    /// Queries and mutations are run by the server too, but their result is returned in
+    /// [AbsintheError::NotASubscription].
+    pub async fn subscribe(
+        &self,
+        query: String,
+        variables: HashMap<String, JSON>,
+        timeout: Duration,
+    ) -> Result<Arc<AbsintheSubscription>, AbsintheError> {
+        let variables: serde_json::Map<String, Value> = variables
+            .into_iter()
+            .map(|(key, value)| (key, value.into()))
+            .collect();
+        let document = json!({ "query": query, "variables": variables });
+        // Read before sending the document, so a rejoin while it is sent leaves this stale.
+        let join_reply = self.channel.join_payload.reply();
+        let subscription_id = subscription_id(&self.channel, &document, timeout).await?;
+        let (result_tx, result_rx) = mpsc::unbounded_channel();
+        let key = self.shared.next_key.fetch_add(1, Ordering::SeqCst);
+
+        self.shared.subscriptions.lock().unwrap().insert(
+            key,
+            Subscription {
+                document,
+                timeout,
+                subscription_id,
+                join_reply: join_reply.clone(),
+                result_tx,
+            },
+        );
+
+        // A resubscribe for the rejoin may have run before this was inserted.
+        let rejoined = match (join_reply, self.channel.join_payload.reply()) {
+            (Some(join_reply), Some(current_join_reply)) => {
+                !Arc::ptr_eq(&join_reply, &current_join_reply)
+            }
+            (None, None) => false,
+            _ => true,
+        };
+
+        if rejoined {
+            self.shared.resubscribe(&self.channel).await;
+        }
+
+        Ok(Arc::new(AbsintheSubscription {
+            channel: self.channel.clone(),
+            shared: self.shared.clone(),
+            key,
+            result_rx: tokio::sync::Mutex::new(result_rx),
+        }))
+    }
+}
+impl Absinthe {
+    pub(crate) fn new(socket: &Socket, channel: Arc<Channel>) -> Arc<Self> {
+        let shared = Arc::new(Shared {
+            next_key: AtomicU64::new(0),
+            subscriptions: Default::default(),
+            resubscribing: Default::default(),
+        });
+        let join_handle = tokio::spawn(shared.clone().listen(
+            channel.clone(),
+            socket.unrouted_broadcast_tx.subscribe(),
+            channel.statuses(),
+        ));
+
+        Arc::new(Self {
+            channel,
+            shared,
+            join_handle,
+        })
+    }
+}
+impl Drop for Absinthe {
+    fn drop(&mut self) {
+        self.join_handle.abort();
+        self.shared.subscriptions.lock().unwrap().clear();
+    }
+}
+
+/// Sends the subscription `document`, returning the ID of the topic its results are broadcast on.
+async fn subscription_id(
+    channel: &Channel,
+    document: &Value,
+    timeout: Duration,
+) -> Result<String, AbsintheError> {
+    let reply = channel
+        .call(
+            Event::from_string(DOC_EVENT.to_string()),
+            rust::message::Payload::from(document.clone()).into(),
+            timeout,
+        )
+        .await?;
+
+    let subscription_id = match rust::message::Payload::from(reply.clone()) {
+        rust::message::Payload::Value(value) => value
+            .get("subscriptionId")
+            .and_then(Value::as_str)
+            .map(ToString::to_string),
+        rust::message::Payload::Binary(_) => None,
+    };
+
+    subscription_id.ok_or(AbsintheError::NotASubscription { result: reply })
+}
+
+struct Subscription {
+    document: Value,
+    timeout: Duration,
+    /// The topic the results of the current subscription are broadcast on.
+    subscription_id: String,
+    /// The reply to the join the subscription was made over, to resubscribe after rejoins.
+    join_reply: Option<Arc<rust::message::Payload>>,
+    result_tx: mpsc::UnboundedSender<JSON>,
+}
+
+struct Shared {
+    next_key: AtomicU64,
+    subscriptions: Mutex<HashMap<u64, Subscription>>,
+    /// Held while resubscribing, so a subscription isn't resubscribed twice for one rejoin.
+    resubscribing: tokio::sync::Mutex<()>,
+}
+impl Shared {
+    async fn listen(
+        self: Arc<Self>,
+        channel: Arc<Channel>,
+        mut broadcasts: broadcast::Receiver<Broadcast>,
+        statuses: Arc<ChannelStatuses>,
+    ) {
+        loop {
+            tokio::select! {
+                result = broadcasts.recv() => match result {
+                    Ok(broadcast) => self.broadcast_received(broadcast),
+                    Err(broadcast::error::RecvError::Lagged(missed_broadcast_count)) => error!(
+                        "Absinthe missed {} broadcasts, so subscriptions may miss results",
+                        missed_broadcast_count
+                    ),
+                    Err(broadcast::error::RecvError::Closed) => break,
+                },
+                result = statuses.status() => match result {
+                    // Spawned, so results keep being delivered while the documents are sent.
+                    Ok(Ok(ChannelStatus::Joined)) | Err(StatusesError::MissedStatuses { .. }) => {
+                        let shared = self.clone();
+                        let channel = channel.clone();
+                        tokio::spawn(async move { shared.resubscribe(&channel).await });
+                    }
+                    Ok(_) => (),
+                    Err(StatusesError::NoMoreStatuses) => break,
+                }
+            }
+        }
+
+        self.subscriptions.lock().unwrap().clear();
+    }
+
+    fn broadcast_received(&self, broadcast: Broadcast) {
+        let rust::message::EventPayload { event, payload } = broadcast.event_payload;
+
+        match event {
+            rust::message::Event::User(user) if user == SUBSCRIPTION_DATA_EVENT => (),
+            _ => return,
+        }
+
+        let topic = broadcast.topic.to_string();
+        let subscriptions = self.subscriptions.lock().unwrap();
+        let subscription = match subscriptions
+            .values()
+            .find(|subscription| subscription.subscription_id == topic)
+        {
+            Some(subscription) => subscription,
+            None => return,
+        };
+        let result = match &payload {
+            rust::message::Payload::Value(value) => value.get("result").cloned(),
+            rust::message::Payload::Binary(_) => None,
+        };
+
+        match result {
+            Some(result) => {
+                subscription.result_tx.send(result.into()).ok();
+            }
+            None => error!(
+                "Absinthe ignored {} payload without `result` on {}",
+                SUBSCRIPTION_DATA_EVENT, topic
+            ),
+        }
+    }
+
+    /// Sends the document of each subscription made over an earlier join again, ending those the
+    /// server no longer accepts.
+    async fn resubscribe(&self, channel: &Channel) {
+        let _resubscribing = self.resubscribing.lock().await;
+        let join_reply = match channel.join_payload.reply() {
+            Some(join_reply) => join_reply,
+            None => return,
+        };
+        let stale: Vec<(u64, Value, Duration)> = self
+            .subscriptions
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, subscription)| match &subscription.join_reply {
+                Some(subscription_join_reply) => !Arc::ptr_eq(subscription_join_reply, &join_reply),
+                None => true,
+            })
+            .map(|(key, subscription)| (*key, subscription.document.clone(), subscription.timeout))
+            .collect();
+
+        for (key, document, timeout) in stale {
+            let result = subscription_id(channel, &document, timeout).await;
+            let mut subscriptions = self.subscriptions.lock().unwrap();
+
+            match result {
+                Ok(subscription_id) => {
+                    if let Some(subscription) = subscriptions.get_mut(&key) {
+                        subscription.subscription_id = subscription_id;
+                        subscription.join_reply = Some(join_reply.clone());
+                    }
+                }
+                Err(error) => {
+                    error!(
+                        "Absinthe ended subscription that failed to resubscribe: {}",
+                        error
+                    );
+                    subscriptions.remove(&key);
+                }
+            }
+        }
+    }
+}
+
+/// A GraphQL subscription made with [Absinthe::subscribe].
+///
+/// Dropping this stops delivering its results, but does not unsubscribe on the server, so use
+/// [AbsintheSubscription::unsubscribe] first.
+#[cfg_attr(
+    feature = "uniffi",
+    derive(uniffi::Object)
+)]
+pub struct AbsintheSubscription {
+    channel: Arc<Channel>,
+    shared: Arc<Shared>,
+    key: u64,
+    result_rx: tokio::sync::Mutex<mpsc::UnboundedReceiver<JSON>>,
+}
+#[cfg_attr(
+    feature = "uniffi",
+    uniffi::export
+)]
+impl AbsintheSubscription {
+    /// The ID of the topic the results are broadcast on, which changes with each resubscribe.
+    ///
+    /// * [None] - the subscription ended.
+    pub fn subscription_id(&self) -> Option<String> {
+        self.shared
+            .subscriptions
+            .lock()
+            .unwrap()
+            .get(&self.key)
+            .map(|subscription| subscription.subscription_id.clone())
+    }
+
+    /// Waits for the next result, such as `{"data": {...}}`.
+    pub async fn result(&self) -> Result<JSON, AbsintheSubscriptionError> {
+        self.result_rx
+            .lock()
+            .await
+            .recv()
+            .await
+            .ok_or(AbsintheSubscriptionError::NoMoreResults)
+    }
+
+    /// Unsubscribes on the server within `timeout`, after which [AbsintheSubscription::result]
+    /// only returns the results that were already received.
+    pub async fn unsubscribe(&self, timeout: Duration) -> Result<(), AbsintheError> {
+        let subscription = self.shared.subscriptions.lock().unwrap().remove(&self.key);
+
+        if let Some(subscription) = subscription {
+            self.channel
+                .call(
+                    Event::from_string(UNSUBSCRIBE_EVENT.to_string()),
+                    rust::message::Payload::from(
+                        json!({ "subscriptionId": subscription.subscription_id }),
+                    )
+                    .into(),
+                    timeout,
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+}
+// Rust-only, as streams can't be exported with `uniffi`
+impl AbsintheSubscription {
+    /// Converts into a [Stream](futures::Stream) of [AbsintheSubscription::result]s for use with
+    /// [StreamExt] and `select!`.
+    ///
+    /// The stream ends where [AbsintheSubscription::result] would return
+    /// [AbsintheSubscriptionError::NoMoreResults].
+    pub fn into_stream(self: Arc<Self>) -> BoxStream<'static, JSON> {
+        stream::unfold(self, |subscription| async move {
+            match subscription.result().await {
+                Ok(result) => Some((result, subscription)),
+                Err(AbsintheSubscriptionError::NoMoreResults) => None,
+            }
+        })
+        .boxed()
+    }
+}
+impl Drop for AbsintheSubscription {
+    fn drop(&mut self) {
+        self.shared.subscriptions.lock().unwrap().remove(&self.key);
+    }
+}
+
+/// Errors when calling [AbsintheSubscription::result].
+#[derive(Debug, thiserror::Error)]
+#[cfg_attr(
+    feature = "uniffi",
+    derive(uniffi::Error)
+)]
+pub enum AbsintheSubscriptionError {
+    /// There are no more results because the subscription was unsubscribed, failed to resubscribe
+    /// after a rejoin, or its [Absinthe] was dropped or [Channel] shutdown.
+    #[error("No more Absinthe subscription results left")]
+    NoMoreResults,
+}
+
+/// Errors when calling [Absinthe::subscribe] or [AbsintheSubscription::unsubscribe].
+#[derive(Debug, thiserror::Error)]
+#[cfg_attr(
+    feature = "uniffi",
+    derive(uniffi::Error)
+)]
+pub enum AbsintheError {
+    /// Calling the [Channel] failed, including when the server replied with GraphQL `errors`.
+    #[error("call error: {call_error}")]
+    Call {
+        /// The error from [Channel::call].
+        call_error: CallError,
+    },
+    /// The document was run, but not as a subscription, as it was a query or mutation.
+    #[error("not a subscription: {result:?}")]
+    NotASubscription {
+        /// The result of the query or mutation.
+        result: Payload,
+    },
+}
+impl From<CallError> for AbsintheError {
+    fn from(call_error: CallError) -> Self {
+        Self::Call { call_error }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{json_payload, FakeServer, ScriptedReply, TIMEOUT};
+
+    const QUERY: &str = "subscription { commentAdded { body } }";
+
+    fn data(server: &FakeServer, subscription_id: &str, body: &str) {
+        server.fastlane(
+            subscription_id,
+            SUBSCRIPTION_DATA_EVENT,
+            json_payload(json!({
+                "result": { "data": { "commentAdded": { "body": body } } },
+                "subscriptionId": subscription_id
+            })),
+        );
+    }
+
+    fn body(result: JSON) -> Value {
+        Value::from(result)["data"]["commentAdded"]["body"].clone()
+    }
+
+    #[tokio::test]
+    async fn subscription_receives_results_and_resubscribes_after_rejoin() {
+        let server = FakeServer::new();
+        server.reply_to_call(
+            CONTROL_TOPIC,
+            DOC_EVENT,
+            ScriptedReply::Ok(json_payload(
+                json!({ "subscriptionId": "__absinthe__:doc:1" }),
+            )),
+        );
+        server.reply_to_call(
+            CONTROL_TOPIC,
+            DOC_EVENT,
+            ScriptedReply::Ok(json_payload(
+                json!({ "subscriptionId": "__absinthe__:doc:2" }),
+            )),
+        );
+        let socket = server.connected_socket().await;
+        let absinthe = socket.absinthe().await.unwrap();
+        absinthe.join(TIMEOUT).await.unwrap();
+
+        let subscription = absinthe
+            .subscribe(QUERY.to_string(), HashMap::new(), TIMEOUT)
+            .await
+            .unwrap();
+
+        let doc = loop {
+            let received = server.next_received().await;
+
+            if received.event == crate::Event::from_string(DOC_EVENT.to_string()) {
+                break received;
+            }
+        };
+        assert_eq!(
+            rust::message::Payload::from(doc.payload),
+            rust::message::Payload::from(json!({ "query": QUERY, "variables": {} }))
+        );
+
+        data(&server, "__absinthe__:doc:1", "first");
+        assert_eq!(body(subscription.result().await.unwrap()), json!("first"));
+
+        server.close(CONTROL_TOPIC);
+
+        tokio::time::timeout(TIMEOUT, async {
+            while subscription.subscription_id() != Some("__absinthe__:doc:2".to_string()) {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+
+        data(&server, "__absinthe__:doc:1", "stale");
+        data(&server, "__absinthe__:doc:2", "second");
+        assert_eq!(body(subscription.result().await.unwrap()), json!("second"));
+
+        server.reply_to_call(
+            CONTROL_TOPIC,
+            UNSUBSCRIBE_EVENT,
+            ScriptedReply::Ok(json_payload(json!({}))),
+        );
+        subscription.unsubscribe(TIMEOUT).await.unwrap();
+
+        assert!(matches!(
+            subscription.result().await,
+            Err(AbsintheSubscriptionError::NoMoreResults)
+        ));
+
+        socket.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn results_are_delivered_while_resubscribing() {
+        let server = FakeServer::new();
+        server.reply_to_call(
+            CONTROL_TOPIC,
+            DOC_EVENT,
+            ScriptedReply::Ok(json_payload(
+                json!({ "subscriptionId": "__absinthe__:doc:1" }),
+            )),
+        );
+        server.reply_to_call(CONTROL_TOPIC, DOC_EVENT, ScriptedReply::NoReply);
+        server.reply_to_call(
+            CONTROL_TOPIC,
+            DOC_EVENT,
+            ScriptedReply::Ok(json_payload(
+                json!({ "subscriptionId": "__absinthe__:doc:3" }),
+            )),
+        );
+        let socket = server.connected_socket().await;
+        let absinthe = socket.absinthe().await.unwrap();
+        absinthe.join(TIMEOUT).await.unwrap();
+
+        let _resubscribing = absinthe
+            .subscribe(QUERY.to_string(), HashMap::new(), TIMEOUT)
+            .await
+            .unwrap();
+
+        server.close(CONTROL_TOPIC);
+
+        // Wait for the resubscribe that is never replied to
+        let mut docs = 0;
+
+        while docs < 2 {
+            let received = server.next_received().await;
+
+            if received.event == crate::Event::from_string(DOC_EVENT.to_string()) {
+                docs += 1;
+            }
+        }
+
+        let subscription = absinthe
+            .subscribe(QUERY.to_string(), HashMap::new(), TIMEOUT)
+            .await
+            .unwrap();
+
+        data(&server, "__absinthe__:doc:3", "during resubscribe");
+        let result = tokio::time::timeout(Duration::from_secs(1), subscription.result())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(body(result), json!("during resubscribe"));
+
+        socket.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn query_is_not_a_subscription() {
+        let server = FakeServer::new();
+        server.reply_to_call(
+            CONTROL_TOPIC,
+            DOC_EVENT,
+            ScriptedReply::Ok(json_payload(json!({ "data": { "posts": [] } }))),
+        );
+        let socket = server.connected_socket().await;
+        let absinthe = socket.absinthe().await.unwrap();
+        absinthe.join(TIMEOUT).await.unwrap();
+
+        match absinthe
+            .subscribe("{ posts { title } }".to_string(), HashMap::new(), TIMEOUT)
+            .await
+        {
+            Err(AbsintheError::NotASubscription { result }) => assert_eq!(
+                rust::message::Payload::from(result),
+                rust::message::Payload::from(json!({ "data": { "posts": [] } }))
+            ),
+            other => panic!("expected NotASubscription, got {:?}", other.map(|_| ())),
+        }
+
+        socket.shutdown().await.unwrap();
+    }
+}
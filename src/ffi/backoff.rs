@@ -0,0 +1,203 @@
+//! How long a [Socket](crate::Socket) waits before automatically reconnecting and a
+//! [Channel](crate::Channel) waits before automatically rejoining.
+//!
+//! The built-in [BackoffStrategy]s are configured with
+//! [SocketOptions::reconnect_backoff](crate::SocketOptions::reconnect_backoff) and
+//! [SocketOptions::rejoin_backoff](crate::SocketOptions::rejoin_backoff).  Any other schedule can
+//! implement [Backoff] and be passed to
+//! [Socket::spawn_with_backoffs](crate::Socket::spawn_with_backoffs).
+//!
+//! ```
+//! # use std::time::Duration;
+//! #
+//! # use phoenix_channels_client::{BackoffStrategy, SocketOptions};
+//! #
+//! // Spread reconnects from many clients out, so they don't all hit a restarted server at once.
+//! let options = SocketOptions::default()
+//!     .with_reconnect_backoff(BackoffStrategy::ExponentialFullJitter {
+//!         base: Duration::from_millis(500),
+//!         max: Duration::from_secs(60),
+//!     })
+//!     .with_max_reconnect_attempts(20);
+//! ```
+
+use std::time::Duration;
+
+/// Decides how long to sleep before each automatic reconnect or rejoin attempt.
+#[cfg_attr(feature = "uniffi", uniffi::export(callback_interface))]
+pub trait Backoff: Send + Sync {
+    /// How long to sleep before attempt number `attempt`, starting at `0` for the first attempt
+    /// after the connection was lost or the join failed.
+    ///
+    /// * `timeout` - the timeout for connecting or joining.
+    /// * `previous_sleep` - the value returned for the previous attempt, or [Duration::ZERO] for
+    ///   the first attempt.
+    fn sleep_duration(&self, attempt: u32, timeout: Duration, previous_sleep: Duration)
+        -> Duration;
+}
+
+/// The built-in [Backoff]s.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "uniffi",
+    derive(uniffi::Enum)
+)]
+pub enum BackoffStrategy {
+    /// Sleeps for the connect or join timeout times the multiplier for the attempt.  The last
+    /// multiplier is reused once all the others have been used.
+    Multipliers {
+        /// The multiplier for each attempt.
+        multipliers: Vec<u32>,
+    },
+    /// Sleeps the same duration before every attempt.
+    Fixed {
+        /// How long to sleep before each attempt.
+        sleep: Duration,
+    },
+    /// Sleeps a random duration between zero and `base * 2^attempt`, capped at `max`.
+    ExponentialFullJitter {
+        /// The cap on the sleep for the first attempt.
+        base: Duration,
+        /// The cap on the sleep for any attempt.
+        max: Duration,
+    },
+    /// Sleeps a random duration between `base` and three times the previous sleep, capped at
+    /// `max`.
+    DecorrelatedJitter {
+        /// The shortest sleep.
+        base: Duration,
+        /// The longest sleep.
+        max: Duration,
+    },
+}
+impl BackoffStrategy {
+    pub(crate) fn validate(&self) -> Result<(), &'static str> {
+        match self {
+            BackoffStrategy::Multipliers { multipliers } if multipliers.is_empty() => {
+                Err("must have at least one multiplier")
+            }
+            BackoffStrategy::ExponentialFullJitter { base, max }
+            | BackoffStrategy::DecorrelatedJitter { base, max }
+                if base > max =>
+            {
+                Err("base must not be greater than max")
+            }
+            _ => Ok(()),
+        }
+    }
+}
+impl Default for BackoffStrategy {
+    /// Keeps this crate's previous schedule: sleep `[0, 1, 2, 5, 10]` times the timeout.  This is
+    /// not the fixed millisecond schedule of `phoenix.js`'s `reconnectAfterMs` and
+    /// `rejoinAfterMs`.
+    fn default() -> Self {
+        BackoffStrategy::Multipliers {
+            multipliers: vec![0, 1, 2, 5, 10],
+        }
+    }
+}
+impl Backoff for BackoffStrategy {
+    fn sleep_duration(
+        &self,
+        attempt: u32,
+        timeout: Duration,
+        previous_sleep: Duration,
+    ) -> Duration {
+        match self {
+            BackoffStrategy::Multipliers { multipliers } => {
+                let index = (attempt as usize).min(multipliers.len().saturating_sub(1));
+
+                timeout.saturating_mul(multipliers.get(index).copied().unwrap_or(0))
+            }
+            BackoffStrategy::Fixed { sleep } => *sleep,
+            BackoffStrategy::ExponentialFullJitter { base, max } => {
+                let ceiling = base
+                    .saturating_mul(2_u32.saturating_pow(attempt))
+                    .min(*max);
+
+                random_between(Duration::ZERO, ceiling)
+            }
+            BackoffStrategy::DecorrelatedJitter { base, max } => {
+                let ceiling = previous_sleep.saturating_mul(3).max(*base);
+
+                random_between(*base, ceiling).min(*max)
+            }
+        }
+    }
+}
+
+fn random_between(low: Duration, high: Duration) -> Duration {
+    if high <= low {
+        low
+    } else {
+        let low_nanos = low.as_nanos().min(u64::MAX as u128) as u64;
+        let high_nanos = high.as_nanos().min(u64::MAX as u128) as u64;
+
+        Duration::from_nanos(fastrand::u64(low_nanos..=high_nanos))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TIMEOUT: Duration = Duration::from_secs(10);
+
+    #[test]
+    fn default_matches_previous_multipliers() {
+        let backoff = BackoffStrategy::default();
+
+        let sleeps: Vec<Duration> = (0..7)
+            .map(|attempt| backoff.sleep_duration(attempt, TIMEOUT, Duration::ZERO))
+            .collect();
+
+        assert_eq!(
+            sleeps,
+            [0, 10, 20, 50, 100, 100, 100]
+                .into_iter()
+                .map(Duration::from_secs)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn exponential_full_jitter_stays_under_ceiling() {
+        let base = Duration::from_millis(100);
+        let max = Duration::from_secs(5);
+        let backoff = BackoffStrategy::ExponentialFullJitter { base, max };
+
+        for attempt in 0..64 {
+            let sleep = backoff.sleep_duration(attempt, TIMEOUT, Duration::ZERO);
+
+            assert!(sleep <= base.saturating_mul(2_u32.saturating_pow(attempt)));
+            assert!(sleep <= max);
+        }
+    }
+
+    #[test]
+    fn decorrelated_jitter_stays_between_base_and_max() {
+        let base = Duration::from_millis(100);
+        let max = Duration::from_secs(5);
+        let backoff = BackoffStrategy::DecorrelatedJitter { base, max };
+        let mut previous_sleep = Duration::ZERO;
+
+        for attempt in 0..64 {
+            let sleep = backoff.sleep_duration(attempt, TIMEOUT, previous_sleep);
+
+            assert!(base <= sleep && sleep <= max);
+            assert!(sleep <= previous_sleep.saturating_mul(3).max(base));
+
+            previous_sleep = sleep;
+        }
+    }
+
+    #[test]
+    fn base_greater_than_max_is_invalid() {
+        assert!(BackoffStrategy::DecorrelatedJitter {
+            base: Duration::from_secs(2),
+            max: Duration::from_secs(1),
+        }
+        .validate()
+        .is_err());
+    }
+}
@@ -81,8 +81,11 @@
 //!
 //! If the server uses authentication for individual channels it is important to
 //! [monitor the status of the channel](Channel::statuses), to be notified when the
-//! [payload](Channel::payload) is no longer valid to authenticate to the channel and a new
-//! [Channel] with the new authentication payload should be [created](Socket::channel).
+//! [payload](Channel::payload) is no longer valid to authenticate to the channel.  The new
+//! authentication payload can be set with [Channel::set_join_payload] or produced for every
+//! rejoin by a [JoinPayloadProvider](crate::JoinPayloadProvider) passed to
+//! [Channel::set_join_payload_provider], so the [Channel] does not need to be
+//! [created](Socket::channel) again.
 //!
 //! ```
 //! # use std::sync::Arc;
@@ -208,23 +211,35 @@ use std::sync::Arc;
 use std::time::{Duration, SystemTime};
 
 use atomic_take::AtomicTake;
+use futures::stream::{self, BoxStream};
+use futures::StreamExt;
 use log::{debug, error};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
 use tokio::sync::{broadcast, mpsc, oneshot, Mutex};
 use tokio::task::JoinHandle;
 use tokio::time;
 use tokio::time::error::Elapsed;
 use tokio::time::Instant;
 
-use crate::ffi::channel::statuses::ChannelStatuses;
+use crate::ffi::channel::join_payload::{JoinPayload, JoinPayloadProvider};
+use crate::ffi::channel::statuses::{ChannelStatusJoinError, ChannelStatuses};
+use crate::ffi::listeners::{ChannelStatusListener, EventListener, ListenerHandle};
 use crate::ffi::message::{Event, Payload};
+use crate::ffi::observable_status::StatusesError;
 use crate::ffi::socket::SocketShutdownError;
 use crate::ffi::topic::Topic;
 use crate::ffi::web_socket::error::WebSocketError;
 use crate::ffi::{instant_to_system_time, web_socket};
 use crate::rust;
 use crate::rust::channel::listener::{ObservableStatus, SendCommand, StateCommand};
+use crate::rust::channel::subscriptions::{EventReceiver, Filter, Subscriptions};
+use crate::rust::channel::typed::{TypedError, TypedEvents};
 use crate::rust::channel::Call;
+use crate::rust::serializer::Serializer;
 
+pub mod join_payload;
+pub mod options;
 pub mod statuses;
 
 /// Errors returned by [Channel] functions.
@@ -286,13 +301,16 @@ pub enum ChannelError {
 )]
 pub struct Channel {
     pub(crate) topic: Arc<Topic>,
-    pub(crate) payload: crate::rust::message::Payload,
+    pub(crate) join_payload: Arc<JoinPayload>,
     /// The channel status
     pub(crate) status: ObservableStatus,
-    pub(crate) event_payload_tx: broadcast::Sender<crate::rust::message::EventPayload>,
+    /// [Channel::events], [Channel::on] and [Channel::on_matching] subscriptions.
+    pub(crate) subscriptions: Arc<Subscriptions>,
     pub(crate) shutdown_tx: AtomicTake<oneshot::Sender<()>>,
     pub(crate) state_command_tx: mpsc::Sender<StateCommand>,
     pub(crate) send_command_tx: mpsc::Sender<SendCommand>,
+    /// [Serializer] of the [Socket](crate::Socket) this channel is on.
+    pub(crate) serializer: Arc<dyn Serializer>,
     /// The join handle corresponding to the channel listener
     pub(crate) join_handle: AtomicTake<JoinHandle<Result<(), ChannelShutdownError>>>,
 }
@@ -330,7 +348,32 @@ impl Channel {
 
     /// Returns the payload sent to the channel when joined
     pub fn payload(&self) -> Payload {
-        (&self.payload).into()
+        self.join_payload.get().into()
+    }
+
+    /// Sets the payload sent on the next [Channel::join] and automatic rejoins, such as a rotated
+    /// authorization token.  Ignored while a [JoinPayloadProvider] is set with
+    /// [Channel::set_join_payload_provider].
+    pub fn set_join_payload(&self, payload: Payload) {
+        self.join_payload.set(payload.into());
+    }
+
+    /// Sets the [JoinPayloadProvider] called for the payload before each [Channel::join] and
+    /// automatic rejoin, replacing any earlier provider.
+    ///
+    /// * [None] - send the last payload from the provider or [Channel::set_join_payload].
+    pub fn set_join_payload_provider(&self, provider: Option<Box<dyn JoinPayloadProvider>>) {
+        self.join_payload.set_provider(provider);
+    }
+
+    /// Returns the payload the server replied with to the last successful join or rejoin, such
+    /// as the initial state of the topic.
+    ///
+    /// * [None] - the channel has not been joined yet.
+    pub fn join_reply(&self) -> Option<Payload> {
+        self.join_payload
+            .reply()
+            .map(|reply| reply.as_ref().clone().into())
     }
 
     /// The current [ChannelStatus].
@@ -347,31 +390,94 @@ impl Channel {
         Arc::new(self.status.subscribe().into())
     }
 
+    /// Calls `listener` with [Channel::status] changes until the returned [ListenerHandle] is
+    /// removed or this channel shuts down.
+    pub fn add_status_listener(
+        &self,
+        listener: Box<dyn ChannelStatusListener>,
+    ) -> Arc<ListenerHandle> {
+        let status = self.status.clone();
+        let statuses = self.statuses();
+
+        ListenerHandle::spawn(async move {
+            loop {
+                match statuses.status().await {
+                    Ok(Ok(channel_status)) => listener.on_status(channel_status),
+                    Ok(Err(ChannelStatusJoinError::Rejected { response })) => {
+                        listener.on_join_rejected(response)
+                    }
+                    Err(StatusesError::MissedStatuses { .. }) => {
+                        listener.on_status(status.get().into())
+                    }
+                    Err(StatusesError::NoMoreStatuses) => break,
+                }
+            }
+        })
+    }
+
     /// Broadcasts [EventPayload] sent from server.
+    ///
+    /// Each [Events] has its own buffer, delivered as configured with
+    /// [ChannelOptions](crate::ChannelOptions), so use [Channel::on] to only buffer some events.
     pub fn events(&self) -> Arc<Events> {
-        Arc::new(self.event_payload_tx.subscribe().into())
+        Arc::new(self.subscribe(Filter::All))
+    }
+
+    /// Calls `listener` with the [EventPayload]s sent from server until the returned
+    /// [ListenerHandle] is removed or this channel shuts down.
+    ///
+    /// The listener has its own buffer, like [Channel::events].
+    pub fn add_event_listener(&self, listener: Box<dyn EventListener>) -> Arc<ListenerHandle> {
+        let events = self.events();
+
+        ListenerHandle::spawn(async move {
+            loop {
+                match events.event().await {
+                    Ok(event_payload) => listener.on_event(event_payload),
+                    Err(EventsError::MissedEvents { missed_event_count }) => {
+                        listener.on_missed_events(missed_event_count)
+                    }
+                    Err(EventsError::NoMoreEvents) => break,
+                }
+            }
+        })
+    }
+
+    /// Subscribes to the [EventPayload]s for `event` sent from server, like `channel.on(event,
+    /// callback)` in `phoenix.js`.
+    ///
+    /// Each subscription has its own buffer of
+    /// [ChannelOptions::event_buffer_size](crate::ChannelOptions::event_buffer_size)
+    /// [EventPayload]s, so it only misses events when it is slow itself.  Unsubscribe with
+    /// [Channel::off] or by dropping the [Events].
+    pub fn on(&self, event: Event) -> Arc<Events> {
+        Arc::new(self.subscribe(Filter::Event(event.into())))
+    }
+
+    /// Unsubscribes `events` from [Channel::events], [Channel::on] or [Channel::on_matching], so
+    /// that [Events::event] returns the [EventPayload]s already received and then
+    /// [EventsError::NoMoreEvents].  [Events] from another [Channel] are ignored.
+    pub fn off(&self, events: Arc<Events>) {
+        self.subscriptions.unsubscribe(events.subscription_reference);
     }
 
     /// Sends `event` with `payload` to this channel, and returns `Ok` if successful.
     ///
     /// This function does not wait for any reply, if you need the reply, then use `send` or `send_with_timeout`.
+    ///
+    /// Until the channel is joined, it is held as configured by
+    /// [ChannelOptions::push_buffer_size](crate::ChannelOptions::push_buffer_size).
     pub async fn cast(&self, event: Event, payload: Payload) -> Result<(), CastError> {
         debug!(
             "sending event {:?} with payload {:#?}, replies ignored",
             &event, &payload
         );
 
-        match self
-            .send_command_tx
-            .send(SendCommand::Cast(crate::rust::message::EventPayload {
-                event: event.into(),
-                payload: payload.into(),
-            }))
-            .await
-        {
-            Ok(()) => Ok(()),
-            Err(_) => Err(self.listener_shutdown().await.unwrap_err().into()),
+        if !self.supports_payload(&payload) {
+            return Err(CastError::BinaryPayloadUnsupported);
         }
+
+        self.cast_payload(event.into(), payload.into()).await
     }
 
     /// Like `send`, except it takes a configurable `timeout` for awaiting the reply.
@@ -391,29 +497,13 @@ impl Channel {
             &event, &timeout, &payload
         );
 
-        let (reply_tx, reply_rx) = oneshot::channel();
+        if !self.supports_payload(&payload) {
+            return Err(CallError::BinaryPayloadUnsupported);
+        }
 
-        match self
-            .send_command_tx
-            .send(SendCommand::Call(Call {
-                event_payload: crate::rust::message::EventPayload {
-                    event: event.into(),
-                    payload: payload.into(),
-                },
-                reply_tx,
-            }))
+        self.call_payload(event.into(), payload.into(), timeout)
             .await
-        {
-            Ok(()) => {
-                debug!("Waiting for reply for {:?} timeout", &timeout);
-
-                match time::timeout(timeout, reply_rx).await? {
-                    Ok(result) => result.map(From::from).map_err(From::from),
-                    Err(_) => Err(self.listener_shutdown().await.unwrap_err().into()),
-                }
-            }
-            Err(_) => Err(self.listener_shutdown().await.unwrap_err().into()),
-        }
+            .map(From::from)
     }
 
     /// Leaves this channel
@@ -452,6 +542,153 @@ impl Channel {
             None => Err(ChannelShutdownError::AlreadyJoined),
         }
     }
+
+    /// Whether the [Serializer] of the [Socket](crate::Socket) can encode `payload`.
+    fn supports_payload(&self, payload: &Payload) -> bool {
+        match payload {
+            Payload::JSON { .. } => true,
+            Payload::Binary { .. } => self.serializer.supports_binary_payloads(),
+        }
+    }
+}
+// Rust-only, as generic functions can't be exported with `uniffi`
+impl Channel {
+    /// Like [Channel::cast], but serializes `payload` to a JSON [Payload].
+    ///
+    /// ```
+    /// # use serde::Serialize;
+    /// #
+    /// # use phoenix_channels_client::{Channel, Event, TypedError, CastError};
+    /// #
+    /// #[derive(Serialize)]
+    /// struct Message<'a> {
+    ///     body: &'a str,
+    /// }
+    ///
+    /// async fn say(channel: &Channel, body: &str) -> Result<(), TypedError<CastError>> {
+    ///     channel
+    ///         .cast_typed(Event::from_string("say".to_string()), &Message { body })
+    ///         .await
+    /// }
+    /// ```
+    pub async fn cast_typed<T: Serialize + ?Sized>(
+        &self,
+        event: Event,
+        payload: &T,
+    ) -> Result<(), TypedError<CastError>> {
+        let payload = serde_json::to_value(payload).map_err(TypedError::Serialization)?;
+
+        debug!(
+            "sending event {:?} with payload {:#?}, replies ignored",
+            &event, &payload
+        );
+
+        self.cast_payload(event.into(), payload.into())
+            .await
+            .map_err(From::from)
+    }
+
+    /// Like [Channel::call], but serializes `payload` to a JSON [Payload] and deserializes the
+    /// reply's JSON [Payload] to `R`.
+    pub async fn call_typed<T: Serialize + ?Sized, R: DeserializeOwned>(
+        &self,
+        event: Event,
+        payload: &T,
+        timeout: Duration,
+    ) -> Result<R, TypedError<CallError>> {
+        let payload = serde_json::to_value(payload).map_err(TypedError::Serialization)?;
+
+        debug!(
+            "sending event {:?} with timeout {:?} and payload {:#?}",
+            &event, &timeout, &payload
+        );
+
+        self.call_payload(event.into(), payload.into(), timeout)
+            .await?
+            .deserialize()
+            .map_err(TypedError::Deserialization)
+    }
+
+    /// Like [Channel::events], but deserializes each [EventPayload::payload] to `T`.
+    pub fn events_as<T: DeserializeOwned>(&self) -> TypedEvents<T> {
+        let (_, receiver) = self.subscriptions.subscribe(Filter::All);
+
+        receiver.into()
+    }
+
+    /// Like [Channel::on], but subscribes to all [EventPayload]s whose [EventPayload::event]
+    /// matches `predicate`.
+    ///
+    /// `predicate` runs on the [Channel]'s task for every event.  If it panics, the returned
+    /// [Events] are unsubscribed as if by [Channel::off].
+    ///
+    /// ```
+    /// # use std::sync::Arc;
+    /// #
+    /// # use phoenix_channels_client::{Channel, Events};
+    /// #
+    /// fn presence_events(channel: &Channel) -> Arc<Events> {
+    ///     channel.on_matching(|event| event.to_string().starts_with("presence_"))
+    /// }
+    /// ```
+    pub fn on_matching<F>(&self, predicate: F) -> Arc<Events>
+    where
+        F: Fn(&Event) -> bool + Send + Sync + 'static,
+    {
+        Arc::new(self.subscribe(Filter::Matching(Box::new(predicate))))
+    }
+
+    fn subscribe(&self, filter: Filter) -> Events {
+        let (subscription_reference, receiver) = self.subscriptions.subscribe(filter);
+
+        Events {
+            receiver: Mutex::new(receiver),
+            subscription_reference,
+        }
+    }
+
+    async fn cast_payload(
+        &self,
+        event: rust::message::Event,
+        payload: rust::message::Payload,
+    ) -> Result<(), CastError> {
+        match self
+            .send_command_tx
+            .send(SendCommand::Cast(rust::message::EventPayload { event, payload }))
+            .await
+        {
+            Ok(()) => Ok(()),
+            Err(_) => Err(self.listener_shutdown().await.unwrap_err().into()),
+        }
+    }
+
+    async fn call_payload(
+        &self,
+        event: rust::message::Event,
+        payload: rust::message::Payload,
+        timeout: Duration,
+    ) -> Result<rust::message::Payload, CallError> {
+        let (reply_tx, reply_rx) = oneshot::channel();
+
+        match self
+            .send_command_tx
+            .send(SendCommand::Call(Call {
+                event_payload: rust::message::EventPayload { event, payload },
+                reply_tx,
+            }))
+            .await
+        {
+            Ok(()) => {
+                debug!("Waiting for reply for {:?} timeout", &timeout);
+
+                match time::timeout(timeout, reply_rx).await? {
+                    Ok(result) => result.map_err(From::from),
+                    Err(_) => Err(self.listener_shutdown().await.unwrap_err().into()),
+                }
+            }
+            Err(_) => Err(self.listener_shutdown().await.unwrap_err().into()),
+        }
+    }
 }
 
 /// Errors when calling [Channel::join].
@@ -499,6 +736,11 @@ pub enum ChannelJoinError {
         /// Rejection server sent when attempting to join the [Channel].
         rejection: Payload,
     },
+    /// The [Channel::payload] is binary, but the
+    /// [Serializer](crate::serializer::Serializer) of the [Socket](crate::Socket) can't encode
+    /// binary payloads.
+    #[error("binary payloads are not supported by the serializer")]
+    BinaryPayloadUnsupported,
 }
 impl From<rust::channel::listener::JoinError> for ChannelJoinError {
     fn from(rust_join_error: rust::channel::listener::JoinError) -> Self {
@@ -519,6 +761,9 @@ impl From<rust::channel::listener::JoinError> for ChannelJoinError {
             rust::channel::listener::JoinError::Rejected(payload) => Self::Rejected {
                 rejection: payload.as_ref().into(),
             },
+            rust::channel::listener::JoinError::BinaryPayloadUnsupported => {
+                Self::BinaryPayloadUnsupported
+            }
         }
     }
 }
@@ -561,7 +806,11 @@ impl From<rust::message::EventPayload> for EventPayload {
     feature = "uniffi",
     derive(uniffi::Object)
 )]
-pub struct Events(Mutex<broadcast::Receiver<rust::message::EventPayload>>);
+pub struct Events {
+    receiver: Mutex<EventReceiver>,
+    /// The [Subscriptions] reference for [Channel::off].
+    subscription_reference: u64,
+}
 #[cfg_attr(
     feature = "uniffi",
     uniffi::export
@@ -569,18 +818,41 @@ pub struct Events(Mutex<broadcast::Receiver<rust::message::EventPayload>>);
 impl Events {
     /// Wait for next [EventPayload] sent from the server.
     pub async fn event(&self) -> Result<EventPayload, EventsError> {
-        self.0
-            .lock()
-            .await
-            .recv()
-            .await
-            .map(From::from)
-            .map_err(From::from)
+        self.receiver.lock().await.recv().await.map(From::from)
     }
 }
-impl From<broadcast::Receiver<rust::message::EventPayload>> for Events {
-    fn from(inner: broadcast::Receiver<rust::message::EventPayload>) -> Self {
-        Self(Mutex::new(inner))
+// Rust-only, as streams can't be exported with `uniffi`
+impl Events {
+    /// Converts into a [Stream](futures::Stream) of [Events::event] results for use with
+    /// [StreamExt] and `select!`.
+    ///
+    /// The stream ends where [Events::event] would return [EventsError::NoMoreEvents], so it only
+    /// yields [EventsError::MissedEvents], after which it continues with the next [EventPayload].
+    ///
+    /// ```
+    /// # use std::sync::Arc;
+    /// #
+    /// # use futures::StreamExt;
+    /// #
+    /// # use phoenix_channels_client::{EventPayload, Events};
+    /// #
+    /// async fn first_two(events: Arc<Events>) -> Vec<EventPayload> {
+    ///     events
+    ///         .into_stream()
+    ///         .filter_map(|result| async move { result.ok() })
+    ///         .take(2)
+    ///         .collect()
+    ///         .await
+    /// }
+    /// ```
+    pub fn into_stream(self: Arc<Self>) -> BoxStream<'static, Result<EventPayload, EventsError>> {
+        stream::unfold(self, |events| async move {
+            match events.event().await {
+                Err(EventsError::NoMoreEvents) => None,
+                result => Some((result, events)),
+            }
+        })
+        .boxed()
     }
 }
 
@@ -596,8 +868,8 @@ pub enum EventsError {
     #[error("No more events left")]
     NoMoreEvents,
     /// [Events::event] wasn't called often enough and some [EventPayload] won't be sent to not
-    /// block the other receivers or the sender.  Call [Events::event] to catch up and get the next
-    /// [EventPayload].
+    /// block the other receivers or the sender, or the [Socket](crate::Socket) itself couldn't
+    /// keep up with the server.  Call [Events::event] to catch up and get the next [EventPayload].
     #[error("Missed {missed_event_count} events; jumping to next event")]
     MissedEvents {
         /// How many [EventPayload] were missed.
@@ -651,13 +923,27 @@ pub enum CastError {
     /// [tokio_tungstenite::tungstenite::error::UrlError] with the `url` passed to [Socket::spawn].  This can include
     /// incorrect scheme ([tokio_tungstenite::tungstenite::error::UrlError::UnsupportedUrlScheme]).
     #[error("URL error: {url_error}")]
-    Url { url_error: String },
+    Url {
+        /// The [tokio_tungstenite::tungstenite::error::UrlError] as a string.
+        url_error: String,
+    },
     /// HTTP error response from server.
     #[error("HTTP error: {}", response.status_code)]
-    Http { response: super::http::Response },
+    Http {
+        /// HTTP error response from server.
+        response: super::http::Response,
+    },
     /// HTTP format error.
     #[error("HTTP format error: {error}")]
-    HttpFormat { error: super::http::HttpError },
+    HttpFormat {
+        /// HTTP format error.
+        error: super::http::HttpError,
+    },
+    /// [Channel::cast]'s `payload` is binary, but the
+    /// [Serializer](crate::serializer::Serializer) of the [Socket](crate::Socket) can't encode
+    /// binary payloads.
+    #[error("binary payloads are not supported by the serializer")]
+    BinaryPayloadUnsupported,
 }
 impl From<ChannelShutdownError> for CastError {
     fn from(shutdown_error: ChannelShutdownError) -> Self {
@@ -709,6 +995,11 @@ pub enum CallError {
         /// Error response from the server.
         reply: Payload,
     },
+    /// [Channel::call]'s `payload` is binary, but the
+    /// [Serializer](crate::serializer::Serializer) of the [Socket](crate::Socket) can't encode
+    /// binary payloads.
+    #[error("binary payloads are not supported by the serializer")]
+    BinaryPayloadUnsupported,
 }
 impl From<Elapsed> for CallError {
     fn from(_: Elapsed) -> Self {
@@ -833,6 +1124,10 @@ pub enum ChannelStatus {
         /// When the [Channel] will automatically [Channel::join].
         until: SystemTime,
     },
+    /// [Channel::join] was called previously, but all
+    /// [SocketOptions::max_rejoin_attempts](crate::SocketOptions::max_rejoin_attempts) automatic
+    /// rejoins failed, so the [Channel] won't rejoin until [Channel::join] is called.
+    RejoinAttemptsExhausted,
     /// [Channel::join] was called and the server responded that the [Channel::topic] was joined
     /// using [Channel::payload].
     Joined,
@@ -854,6 +1149,7 @@ impl From<rust::channel::Status> for ChannelStatus {
             rust::channel::Status::WaitingToRejoin(until) => Self::WaitingToRejoin {
                 until: instant_to_system_time(until),
             },
+            rust::channel::Status::RejoinAttemptsExhausted => Self::RejoinAttemptsExhausted,
             rust::channel::Status::Joined => Self::Joined,
             rust::channel::Status::Leaving => Self::Leaving,
             rust::channel::Status::Left => Self::Left,
@@ -0,0 +1,144 @@
+use std::sync::Arc;
+
+use arc_swap::{ArcSwap, ArcSwapOption};
+
+use crate::ffi::message::Payload;
+use crate::rust;
+
+/// Produces a fresh join payload before each [Channel::join](crate::Channel::join) and automatic
+/// rejoin, so that per-channel authorization tokens can be rotated without shutting down the
+/// [Channel](crate::Channel).
+#[cfg_attr(feature = "uniffi", uniffi::export(callback_interface))]
+pub trait JoinPayloadProvider: Send + Sync {
+    /// The payload for the next join attempt.
+    fn join_payload(&self) -> Payload;
+}
+
+/// The payload sent when joining, shared between the [Channel](crate::Channel) and its async task.
+pub(crate) struct JoinPayload {
+    /// The payload sent for the last join or the one that will be sent for the next join if there
+    /// is no `provider`.
+    payload: ArcSwap<rust::message::Payload>,
+    /// [Channel::set_join_payload_provider](crate::Channel::set_join_payload_provider)
+    provider: ArcSwapOption<Box<dyn JoinPayloadProvider>>,
+    /// The payload of the `ok` reply to the last successful join or rejoin.
+    reply: ArcSwapOption<rust::message::Payload>,
+}
+impl JoinPayload {
+    pub(crate) fn new(payload: rust::message::Payload) -> Self {
+        Self {
+            payload: ArcSwap::from_pointee(payload),
+            provider: ArcSwapOption::empty(),
+            reply: ArcSwapOption::empty(),
+        }
+    }
+
+    /// The payload sent for the last join or to be sent for the next join.
+    pub(crate) fn get(&self) -> rust::message::Payload {
+        self.payload.load().as_ref().clone()
+    }
+
+    pub(crate) fn set(&self, payload: rust::message::Payload) {
+        self.payload.store(payload.into());
+    }
+
+    pub(crate) fn set_provider(&self, provider: Option<Box<dyn JoinPayloadProvider>>) {
+        self.provider.store(provider.map(Into::into));
+    }
+
+    /// The payload for the next join: from the provider if there is one, otherwise the last
+    /// [JoinPayload::set] payload.
+    pub(crate) async fn next(&self) -> rust::message::Payload {
+        match self.provider.load_full() {
+            // `join_payload` is a blocking foreign callback, so keep it off the async workers
+            Some(provider) => {
+                let payload: rust::message::Payload =
+                    tokio::task::spawn_blocking(move || provider.join_payload())
+                        .await
+                        .unwrap_or_else(|join_error| {
+                            std::panic::resume_unwind(join_error.into_panic())
+                        })
+                        .into();
+                self.set(payload.clone());
+
+                payload
+            }
+            None => self.get(),
+        }
+    }
+
+    /// The reply to the last successful join or rejoin, shared so that callers can tell whether
+    /// it changed.
+    pub(crate) fn reply(&self) -> Option<Arc<rust::message::Payload>> {
+        self.reply.load_full()
+    }
+
+    pub(crate) fn set_reply(&self, reply: rust::message::Payload) {
+        self.reply.store(Some(reply.into()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::time::Duration;
+
+    use super::*;
+
+    struct CountingProvider(AtomicU64);
+    impl JoinPayloadProvider for CountingProvider {
+        fn join_payload(&self) -> Payload {
+            let count = self.0.fetch_add(1, Ordering::SeqCst);
+
+            Payload::binary_from_bytes(count.to_be_bytes().to_vec())
+        }
+    }
+
+    /// Blocks like a provider that refreshes a token over the network.
+    struct SlowProvider;
+    impl JoinPayloadProvider for SlowProvider {
+        fn join_payload(&self) -> Payload {
+            std::thread::sleep(Duration::from_millis(200));
+
+            Payload::binary_from_bytes(vec![1])
+        }
+    }
+
+    #[tokio::test]
+    async fn next_uses_provider_and_remembers_payload() {
+        let join_payload = JoinPayload::new(rust::message::Payload::default());
+        join_payload.set_provider(Some(Box::new(CountingProvider(AtomicU64::new(0)))));
+
+        join_payload.next().await;
+        let second = join_payload.next().await;
+
+        assert_eq!(second, 1_u64.to_be_bytes().to_vec().into());
+        assert_eq!(join_payload.get(), second);
+    }
+
+    #[tokio::test]
+    async fn next_without_provider_uses_set_payload() {
+        let join_payload = JoinPayload::new(rust::message::Payload::default());
+        join_payload.set(vec![1, 2, 3].into());
+
+        assert_eq!(join_payload.next().await, vec![1, 2, 3].into());
+    }
+
+    #[tokio::test]
+    async fn slow_provider_does_not_block_the_runtime() {
+        let join_payload = Arc::new(JoinPayload::new(rust::message::Payload::default()));
+        join_payload.set_provider(Some(Box::new(SlowProvider)));
+        let next = tokio::spawn({
+            let join_payload = join_payload.clone();
+
+            async move { join_payload.next().await }
+        });
+
+        // The test runtime has one thread, so this only gets to run while the provider sleeps if
+        // the provider isn't called on that thread.
+        tokio::task::yield_now().await;
+        assert!(!next.is_finished());
+
+        assert_eq!(next.await.unwrap(), vec![1].into());
+    }
+}
@@ -0,0 +1,152 @@
+use crate::ffi::socket::options::SocketOptions;
+use crate::ffi::socket::SocketChannelError;
+
+/// Tunes a [Channel](crate::Channel) created with
+/// [Socket::channel_with_options](crate::Socket::channel_with_options).
+///
+/// ```
+/// # use phoenix_channels_client::{ChannelOptions, EventDelivery};
+/// #
+/// // Ledger topics can't skip entries, so the channel waits for slow receivers.
+/// let options = ChannelOptions::default()
+///     .with_event_buffer_size(100)
+///     .with_event_delivery(EventDelivery::Backpressure);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "uniffi",
+    derive(uniffi::Record)
+)]
+pub struct ChannelOptions {
+    /// How many [EventPayload](crate::EventPayload)s each [Events](crate::Events) of the
+    /// [Channel](crate::Channel) buffers.
+    ///
+    /// * [None] - [SocketOptions::channel_event_buffer_size].
+    #[cfg_attr(feature = "uniffi", uniffi(default = None))]
+    pub event_buffer_size: Option<u32>,
+    /// What happens when the buffer of an [Events](crate::Events) is full.
+    ///
+    /// * [None] - [EventDelivery::Lossy].
+    #[cfg_attr(feature = "uniffi", uniffi(default = None))]
+    pub event_delivery: Option<EventDelivery>,
+    /// How many [Channel::cast](crate::Channel::cast)s and [Channel::call](crate::Channel::call)s
+    /// made before the [Channel](crate::Channel) is joined, or while it waits to rejoin, are held
+    /// and then sent in order once it is joined.  [Channel::call](crate::Channel::call)s whose
+    /// timeout elapses while held are not sent.  When full, further casts and calls wait for
+    /// space.
+    ///
+    /// * [None] - casts and calls wait in the
+    ///   [SocketOptions::channel_command_queue_depth] command queue until the
+    ///   [Channel](crate::Channel) is joined.
+    #[cfg_attr(feature = "uniffi", uniffi(default = None))]
+    pub push_buffer_size: Option<u32>,
+}
+impl ChannelOptions {
+    /// Sets [ChannelOptions::event_buffer_size].
+    pub fn with_event_buffer_size(mut self, event_buffer_size: u32) -> Self {
+        self.event_buffer_size = Some(event_buffer_size);
+        self
+    }
+
+    /// Sets [ChannelOptions::event_delivery].
+    pub fn with_event_delivery(mut self, event_delivery: EventDelivery) -> Self {
+        self.event_delivery = Some(event_delivery);
+        self
+    }
+
+    /// Sets [ChannelOptions::push_buffer_size].
+    pub fn with_push_buffer_size(mut self, push_buffer_size: u32) -> Self {
+        self.push_buffer_size = Some(push_buffer_size);
+        self
+    }
+
+    pub(crate) fn validate(&self) -> Result<(), SocketChannelError> {
+        for (option, value) in [
+            ("event_buffer_size", self.event_buffer_size),
+            ("push_buffer_size", self.push_buffer_size),
+        ] {
+            if value == Some(0) {
+                return Err(SocketChannelError::InvalidOption {
+                    option: option.to_string(),
+                    reason: "must be greater than 0".to_string(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn event_buffer_size_or_default(&self, socket_options: &SocketOptions) -> usize {
+        self.event_buffer_size
+            .unwrap_or(socket_options.channel_event_buffer_size) as usize
+    }
+
+    pub(crate) fn event_delivery_or_default(&self) -> EventDelivery {
+        self.event_delivery.unwrap_or_default()
+    }
+}
+
+/// How [EventPayload](crate::EventPayload)s are delivered to an [Events](crate::Events) whose
+/// buffer of [ChannelOptions::event_buffer_size] is full.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "uniffi",
+    derive(uniffi::Enum)
+)]
+pub enum EventDelivery {
+    /// The oldest [EventPayload](crate::EventPayload) is dropped and
+    /// [Events::event](crate::Events::event) returns
+    /// [EventsError::MissedEvents](crate::EventsError::MissedEvents) with how many were dropped.
+    #[default]
+    Lossy,
+    /// The [Channel](crate::Channel) waits until there is space, so an [Events](crate::Events)
+    /// that is neither received from nor dropped stalls only its own [Channel](crate::Channel).
+    /// The [Socket](crate::Socket) keeps reading meanwhile: pushes to the channel are queued until
+    /// it catches up, and broadcasts the [Socket](crate::Socket) had to drop because the channel
+    /// fell too far behind are reported as
+    /// [EventsError::MissedEvents](crate::EventsError::MissedEvents).
+    Backpressure,
+    /// The buffer grows without bound, so the [Channel](crate::Channel) never waits and no
+    /// [EventPayload](crate::EventPayload) is dropped from it, but memory use is only limited by
+    /// how far behind the slowest [Events](crate::Events) is.  Broadcasts can still be dropped by
+    /// the [Socket](crate::Socket) if the [Channel](crate::Channel) itself falls too far behind,
+    /// which is reported as [EventsError::MissedEvents](crate::EventsError::MissedEvents).
+    Unbounded,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zero_event_buffer_size_is_invalid() {
+        assert!(matches!(
+            ChannelOptions::default().with_event_buffer_size(0).validate(),
+            Err(SocketChannelError::InvalidOption { option, .. }) if option == "event_buffer_size"
+        ));
+    }
+
+    #[test]
+    fn zero_push_buffer_size_is_invalid() {
+        assert!(matches!(
+            ChannelOptions::default().with_push_buffer_size(0).validate(),
+            Err(SocketChannelError::InvalidOption { option, .. }) if option == "push_buffer_size"
+        ));
+    }
+
+    #[test]
+    fn event_buffer_size_defaults_to_socket_option() {
+        let socket_options = SocketOptions::default().with_channel_event_buffer_size(25);
+
+        assert_eq!(
+            ChannelOptions::default().event_buffer_size_or_default(&socket_options),
+            25
+        );
+        assert_eq!(
+            ChannelOptions::default()
+                .with_event_buffer_size(100)
+                .event_buffer_size_or_default(&socket_options),
+            100
+        );
+    }
+}
@@ -1,5 +1,8 @@
 use std::sync::Arc;
 
+use futures::stream::{self, BoxStream};
+use futures::StreamExt;
+
 use crate::ffi::message::Payload;
 use crate::ffi::observable_status::StatusesError;
 use crate::rust;
@@ -33,6 +36,27 @@ impl ChannelStatuses {
             .map(|result| result.map(From::from).map_err(From::from))
     }
 }
+// Rust-only, as streams can't be exported with `uniffi`
+impl ChannelStatuses {
+    /// Converts into a [Stream](futures::Stream) of [ChannelStatuses::status] results for use with
+    /// [StreamExt] and `select!`.
+    ///
+    /// The stream ends where [ChannelStatuses::status] would return
+    /// [StatusesError::NoMoreStatuses], so it only yields [StatusesError::MissedStatuses], after
+    /// which it continues with the next status.
+    pub fn into_stream(
+        self: Arc<Self>,
+    ) -> BoxStream<'static, Result<Result<ChannelStatus, ChannelStatusJoinError>, StatusesError>>
+    {
+        stream::unfold(self, |statuses| async move {
+            match statuses.status().await {
+                Err(StatusesError::NoMoreStatuses) => None,
+                result => Some((result, statuses)),
+            }
+        })
+        .boxed()
+    }
+}
 impl From<observable_status::Statuses<rust::channel::Status, Arc<rust::message::Payload>>>
     for ChannelStatuses
 {
@@ -0,0 +1,178 @@
+//! Callbacks for foreign code to be told about events and status changes instead of looping on
+//! [Events::event](crate::Events::event), [SocketStatuses::status](crate::SocketStatuses::status)
+//! or [ChannelStatuses::status](crate::ChannelStatuses::status) in its own task.
+//!
+//! Listeners are called from an async task of this library, so they should return quickly and
+//! hand off any slow work.
+
+use std::future::Future;
+use std::sync::Arc;
+
+use atomic_take::AtomicTake;
+use tokio::task::JoinHandle;
+
+use crate::ffi::channel::{ChannelStatus, EventPayload};
+use crate::ffi::message::Payload;
+use crate::ffi::socket::SocketStatus;
+use crate::ffi::web_socket::error::WebSocketError;
+
+/// Called with the [EventPayload]s sent from the server to a [Channel](crate::Channel), as
+/// registered with [Channel::add_event_listener](crate::Channel::add_event_listener).
+#[cfg_attr(feature = "uniffi", uniffi::export(callback_interface))]
+pub trait EventListener: Send + Sync {
+    /// The next [EventPayload] sent from the server.
+    fn on_event(&self, event_payload: EventPayload);
+
+    /// `missed_event_count` [EventPayload]s were dropped because the listener was too slow, as
+    /// with [EventsError::MissedEvents](crate::EventsError::MissedEvents).
+    fn on_missed_events(&self, missed_event_count: u64);
+}
+
+/// Called with [SocketStatus] changes, as registered with
+/// [Socket::add_status_listener](crate::Socket::add_status_listener).
+#[cfg_attr(feature = "uniffi", uniffi::export(callback_interface))]
+pub trait SocketStatusListener: Send + Sync {
+    /// The new [Socket::status](crate::Socket::status).  If the listener was too slow and some
+    /// changes were missed, this is called with the current status instead.
+    fn on_status(&self, status: SocketStatus);
+
+    /// The connection errored.
+    fn on_error(&self, error: WebSocketError);
+}
+
+/// Called with [ChannelStatus] changes, as registered with
+/// [Channel::add_status_listener](crate::Channel::add_status_listener).
+#[cfg_attr(feature = "uniffi", uniffi::export(callback_interface))]
+pub trait ChannelStatusListener: Send + Sync {
+    /// The new [Channel::status](crate::Channel::status).  If the listener was too slow and some
+    /// changes were missed, this is called with the current status instead.
+    fn on_status(&self, status: ChannelStatus);
+
+    /// The server rejected a join or automatic rejoin with `response`.
+    fn on_join_rejected(&self, response: Payload);
+}
+
+/// Returned when a listener is added, to remove it.
+///
+/// Dropping the handle does not remove the listener, which is otherwise called until the
+/// [Socket](crate::Socket) or [Channel](crate::Channel) it was added to shuts down.
+#[cfg_attr(
+    feature = "uniffi",
+    derive(uniffi::Object)
+)]
+pub struct ListenerHandle {
+    join_handle: AtomicTake<JoinHandle<()>>,
+}
+#[cfg_attr(
+    feature = "uniffi",
+    uniffi::export
+)]
+impl ListenerHandle {
+    /// Stops calling the listener.  A call already in progress completes.
+    pub fn remove(&self) {
+        if let Some(join_handle) = self.join_handle.take() {
+            join_handle.abort();
+        }
+    }
+}
+impl ListenerHandle {
+    /// Spawns the task that calls the listener.
+    pub(crate) fn spawn<F>(future: F) -> Arc<Self>
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        Arc::new(Self {
+            join_handle: AtomicTake::new(tokio::spawn(future)),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use tokio::sync::mpsc;
+
+    use super::*;
+    use crate::testing::{json_payload, FakeServer, TIMEOUT};
+    use crate::{Event, Topic};
+
+    struct SendingEventListener(mpsc::UnboundedSender<EventPayload>);
+    impl EventListener for SendingEventListener {
+        fn on_event(&self, event_payload: EventPayload) {
+            self.0.send(event_payload).ok();
+        }
+
+        fn on_missed_events(&self, _missed_event_count: u64) {}
+    }
+
+    struct SendingSocketStatusListener(mpsc::UnboundedSender<SocketStatus>);
+    impl SocketStatusListener for SendingSocketStatusListener {
+        fn on_status(&self, status: SocketStatus) {
+            self.0.send(status).ok();
+        }
+
+        fn on_error(&self, _error: WebSocketError) {}
+    }
+
+    struct SendingChannelStatusListener(mpsc::UnboundedSender<ChannelStatus>);
+    impl ChannelStatusListener for SendingChannelStatusListener {
+        fn on_status(&self, status: ChannelStatus) {
+            self.0.send(status).ok();
+        }
+
+        fn on_join_rejected(&self, _response: Payload) {}
+    }
+
+    #[tokio::test]
+    async fn event_listener_is_called_until_removed() {
+        let server = FakeServer::new();
+        let (socket, channel) = server.joined_channel("room:lobby").await;
+        let (event_payload_tx, mut event_payload_rx) = mpsc::unbounded_channel();
+        let handle = channel.add_event_listener(Box::new(SendingEventListener(event_payload_tx)));
+
+        server.broadcast("room:lobby", "new_msg", json_payload(json!({ "body": "hi" })));
+
+        assert_eq!(
+            event_payload_rx.recv().await.unwrap().event,
+            Event::from_string("new_msg".to_string())
+        );
+
+        handle.remove();
+        server.broadcast("room:lobby", "new_msg", json_payload(json!({ "body": "bye" })));
+
+        // The listener, and so the sender, is dropped with the aborted task.
+        assert!(event_payload_rx.recv().await.is_none());
+
+        socket.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn status_listeners_are_called_with_changes() {
+        let server = FakeServer::new();
+        let socket = server.socket().unwrap();
+        let (socket_status_tx, mut socket_status_rx) = mpsc::unbounded_channel();
+        socket.add_status_listener(Box::new(SendingSocketStatusListener(socket_status_tx)));
+        socket.connect(TIMEOUT).await.unwrap();
+
+        assert_eq!(
+            socket_status_rx.recv().await.unwrap(),
+            SocketStatus::Connected
+        );
+
+        let channel = socket
+            .channel(Topic::from_string("room:lobby".to_string()), None)
+            .await
+            .unwrap();
+        let (channel_status_tx, mut channel_status_rx) = mpsc::unbounded_channel();
+        channel.add_status_listener(Box::new(SendingChannelStatusListener(channel_status_tx)));
+        channel.join(TIMEOUT).await.unwrap();
+
+        loop {
+            if channel_status_rx.recv().await.unwrap() == ChannelStatus::Joined {
+                break;
+            }
+        }
+
+        socket.shutdown().await.unwrap();
+    }
+}
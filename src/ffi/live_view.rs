@@ -0,0 +1,871 @@
+//! A client for `Phoenix.LiveView`: joins a LiveView with the session and static tokens from the
+//! page that rendered it, keeps the rendered tree merged with each diff from the server, and
+//! pushes client events.
+//!
+//! ```no_run
+//! # use std::sync::Arc;
+//! # use std::time::Duration;
+//! #
+//! # use phoenix_channels_client::{LiveViewJoin, LiveViewUpdate, Socket, JSON};
+//! #
+//! async fn click(socket: Arc<Socket>, id: String, url: String, session: String) {
+//!     // From the `id`, `data-phx-session` and `data-phx-static` of the LiveView's container in the
+//!     // page rendered at `url`.
+//!     let live_view = socket
+//!         .live_view(LiveViewJoin::new(id, url, session))
+//!         .await
+//!         .unwrap();
+//!     let updates = live_view.updates();
+//!     live_view.join(Duration::from_secs(5)).await.unwrap();
+//!     println!("{}", live_view.html().unwrap());
+//!
+//!     live_view
+//!         .push_event(
+//!             "click".to_string(),
+//!             "increment".to_string(),
+//!             JSON::Object { object: Default::default() },
+//!             None,
+//!             Duration::from_secs(5),
+//!         )
+//!         .await
+//!         .unwrap();
+//!
+//!     while let Ok(update) = updates.update().await {
+//!         if let LiveViewUpdate::Diff { .. } = update {
+//!             println!("{}", live_view.html().unwrap());
+//!         }
+//!     }
+//! }
+//! ```
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use log::error;
+use serde_json::{json, Map, Value};
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+
+use crate::ffi::channel::join_payload::{JoinPayload, JoinPayloadProvider};
+use crate::ffi::channel::statuses::ChannelStatuses;
+use crate::ffi::channel::{
+    CallError, Channel, ChannelJoinError, ChannelStatus, EventPayload, Events, EventsError,
+};
+use crate::ffi::json::JSON;
+use crate::ffi::listeners::ListenerHandle;
+use crate::ffi::message::{Event, Payload};
+use crate::ffi::observable_status::StatusesError;
+use crate::rust;
+use crate::ffi::live_view::upload::{LiveViewUpload, UploadEntry, UploadError};
+use crate::ffi::socket::Socket;
+use crate::rust::live_view::{
+    Merged, Rendered, DIFF_EVENT, EVENT_EVENT, LIVE_PATCH_EVENT, LIVE_REDIRECT_EVENT,
+    REDIRECT_EVENT,
+};
+
+pub mod upload;
+
+/// How many [LiveViewUpdate]s each [LiveViewUpdates] buffers before it starts missing updates.
+const UPDATE_BUFFER_SIZE: usize = 100;
+
+/// What to join a LiveView with, as rendered into the page by the first, disconnected render.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "uniffi",
+    derive(uniffi::Record)
+)]
+pub struct LiveViewJoin {
+    /// The `id` of the LiveView's container element, which is joined as the topic `lv:{id}`.
+    pub id: String,
+    /// The URL of the page the LiveView was rendered in.
+    pub url: String,
+    /// The `data-phx-session` of the LiveView's container element.
+    pub session: String,
+    /// The `data-phx-static` of the LiveView's container element.
+    ///
+    /// * [None] - the container has no `data-phx-static`, as with nested LiveViews.
+    #[cfg_attr(feature = "uniffi", uniffi(default = None))]
+    pub static_token: Option<String>,
+    /// The connect params available with `get_connect_params/1` in `mount/3`, such as
+    /// `_csrf_token`.  `_mounts` is set to how many times the LiveView was joined before.
+    #[cfg_attr(feature = "uniffi", uniffi(default = None))]
+    pub params: Option<HashMap<String, JSON>>,
+}
+impl LiveViewJoin {
+    /// Joins the LiveView with container `id` rendered in the page at `url` with `session`.
+    pub fn new(id: String, url: String, session: String) -> Self {
+        Self {
+            id,
+            url,
+            session,
+            static_token: None,
+            params: None,
+        }
+    }
+
+    /// Sets [LiveViewJoin::static_token].
+    pub fn with_static_token(mut self, static_token: String) -> Self {
+        self.static_token = Some(static_token);
+        self
+    }
+
+    /// Sets [LiveViewJoin::params].
+    pub fn with_params(mut self, params: HashMap<String, JSON>) -> Self {
+        self.params = Some(params);
+        self
+    }
+
+    pub(crate) fn topic(&self) -> String {
+        format!("lv:{}", self.id)
+    }
+}
+
+/// Builds the join payload for each join and rejoin, so that `_mounts` counts them like
+/// `phoenix_live_view.js` does.
+struct MountingJoinPayloadProvider {
+    join: LiveViewJoin,
+    mounts: AtomicU64,
+}
+impl JoinPayloadProvider for MountingJoinPayloadProvider {
+    fn join_payload(&self) -> Payload {
+        let mut params: Map<String, Value> = self
+            .join
+            .params
+            .clone()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(key, value)| (key, value.into()))
+            .collect();
+        params.insert(
+            "_mounts".to_string(),
+            self.mounts.fetch_add(1, Ordering::SeqCst).into(),
+        );
+
+        rust::message::Payload::from(json!({
+            "url": self.join.url,
+            "params": params,
+            "session": self.join.session,
+            "static": self.join.static_token,
+        }))
+        .into()
+    }
+}
+
+/// A LiveView joined over a [Channel], created with [Socket::live_view](crate::Socket::live_view).
+///
+/// The rendered tree is replaced with the render in the reply to each join and rejoin and merged
+/// with the diffs from `diff` events and from replies to [LiveView::push_event] and
+/// [LiveView::call], until the [Channel] shuts down or this is dropped.  Dropping this does not
+/// leave the [Channel], so use [LiveView::channel] to leave or shut it down.
+#[cfg_attr(
+    feature = "uniffi",
+    derive(uniffi::Object)
+)]
+pub struct LiveView {
+    /// The [Socket] the `lvu:` channels of uploads are created on.
+    socket: Arc<Socket>,
+    channel: Arc<Channel>,
+    shared: Arc<Shared>,
+    join_handle: JoinHandle<()>,
+}
+#[cfg_attr(
+    feature = "uniffi",
+    uniffi::export
+)]
+impl LiveView {
+    /// The [Channel] the LiveView is joined over.
+    pub fn channel(&self) -> Arc<Channel> {
+        self.channel.clone()
+    }
+
+    /// Joins the LiveView within `timeout`, which mounts it on the server and renders it, so that
+    /// [LiveView::rendered] is the initial render once this returns.
+    ///
+    /// The server rejects the join with a `redirect` or `live_redirect` in the
+    /// [ChannelJoinError::Rejected] response if the LiveView should be joined at another URL.
+    pub async fn join(&self, timeout: Duration) -> Result<(), LiveViewError> {
+        self.channel.join(timeout).await?;
+
+        self.shared.join_reply_received(&self.channel.join_payload)
+    }
+
+    /// The merged rendered tree, as sent by the server.
+    ///
+    /// * [None] - the LiveView has not been joined yet.
+    pub fn rendered(&self) -> Option<JSON> {
+        self.shared
+            .rendered
+            .lock()
+            .unwrap()
+            .rendered
+            .as_ref()
+            .map(|rendered| Value::Object(rendered.tree().clone()).into())
+    }
+
+    /// The HTML of [LiveView::rendered], without the attributes `phoenix_live_view.js` adds to
+    /// track components.
+    ///
+    /// * [None] - the LiveView has not been joined yet.
+    pub fn html(&self) -> Option<String> {
+        self.shared
+            .rendered
+            .lock()
+            .unwrap()
+            .rendered
+            .as_ref()
+            .map(Rendered::to_html)
+    }
+
+    /// Pushes a client event, like a `phx-click` or `phx-submit` binding, for `handle_event/3`
+    /// and merges the diff in the reply.
+    ///
+    /// * `type` - the kind of binding, such as `click`, `form` or `keyup`.
+    /// * `event` - the event name given to the binding.
+    /// * `value` - the value of the event, such as the `phx-value-*` attributes for a `click` or
+    ///   the URL-encoded form for a `form`.
+    /// * `cid` - the component to send the event to with `phx-target`.
+    ///   * [None] - the LiveView itself.
+    ///
+    /// Returns the map from a `handle_event/3` that returned `{:reply, map, socket}`.
+    pub async fn push_event(
+        &self,
+        r#type: String,
+        event: String,
+        value: JSON,
+        cid: Option<u32>,
+        timeout: Duration,
+    ) -> Result<Option<JSON>, LiveViewError> {
+        let mut payload = json!({
+            "type": r#type,
+            "event": event,
+            "value": Value::from(value),
+        });
+
+        if let Some(cid) = cid {
+            payload["cid"] = cid.into();
+        }
+
+        let (_, merged) = self
+            .call_actual(
+                Event::from_string(EVENT_EVENT.to_string()),
+                rust::message::Payload::from(payload).into(),
+                timeout,
+            )
+            .await?;
+
+        Ok(merged.and_then(|merged| merged.reply).map(From::from))
+    }
+
+    /// Calls the LiveView with `event` and `payload` like [Channel::call], such as for
+    /// `live_patch`, and merges the `diff` in the reply, if any.
+    pub async fn call(
+        &self,
+        event: Event,
+        payload: Payload,
+        timeout: Duration,
+    ) -> Result<Payload, LiveViewError> {
+        self.call_actual(event, payload, timeout)
+            .await
+            .map(|(reply, _)| reply)
+    }
+
+    /// Asks the LiveView to allow uploading `entries` to the upload with `upload_ref`, the
+    /// `data-phx-upload-ref` of its `live_file_input`, and merges the diff in the reply.
+    ///
+    /// * `cid` - the component that called `allow_upload/3`.
+    ///   * [None] - the LiveView itself.
+    ///
+    /// Returns a [LiveViewUpload] for each of `entries`, in order, to send the files with.
+    pub async fn allow_upload(
+        self: Arc<Self>,
+        upload_ref: String,
+        entries: Vec<UploadEntry>,
+        cid: Option<u32>,
+        timeout: Duration,
+    ) -> Result<Vec<Arc<LiveViewUpload>>, UploadError> {
+        LiveViewUpload::allow(self, upload_ref, entries, cid, timeout).await
+    }
+
+    /// Broadcasts [LiveViewUpdate]s.
+    pub fn updates(&self) -> Arc<LiveViewUpdates> {
+        let receiver = match self.shared.update_tx.lock().unwrap().as_ref() {
+            Some(update_tx) => update_tx.subscribe(),
+            None => broadcast::channel(1).1,
+        };
+
+        Arc::new(LiveViewUpdates {
+            receiver: tokio::sync::Mutex::new(receiver),
+        })
+    }
+
+    /// Calls `listener` with [LiveViewUpdate]s until the returned [ListenerHandle] is removed or
+    /// the LiveView is no longer rendered.  If the listener was too slow and some updates were
+    /// missed, it is called with a [LiveViewUpdate::Render] of the current tree instead.
+    pub fn add_update_listener(&self, listener: Box<dyn LiveViewListener>) -> Arc<ListenerHandle> {
+        let shared = self.shared.clone();
+        let updates = self.updates();
+
+        ListenerHandle::spawn(async move {
+            loop {
+                match updates.update().await {
+                    Ok(update) => listener.on_update(update),
+                    Err(LiveViewUpdatesError::MissedUpdates { .. }) => {
+                        if let Some(update) = shared.current_render() {
+                            listener.on_update(update)
+                        }
+                    }
+                    Err(LiveViewUpdatesError::NoMoreUpdates) => break,
+                }
+            }
+        })
+    }
+}
+impl LiveView {
+    pub(crate) fn new(socket: Arc<Socket>, channel: Arc<Channel>, join: LiveViewJoin) -> Arc<Self> {
+        channel.set_join_payload_provider(Some(Box::new(MountingJoinPayloadProvider {
+            join,
+            mounts: AtomicU64::new(0),
+        })));
+
+        let events = channel.on_matching(|event| match event {
+            Event::User { user } => [
+                DIFF_EVENT,
+                REDIRECT_EVENT,
+                LIVE_REDIRECT_EVENT,
+                LIVE_PATCH_EVENT,
+            ]
+            .contains(&user.as_str()),
+            Event::Phoenix { .. } => false,
+        });
+        let (update_tx, _) = broadcast::channel(UPDATE_BUFFER_SIZE);
+        let shared = Arc::new(Shared {
+            rendered: Mutex::new(RenderedState {
+                join_reply: None,
+                rendered: None,
+            }),
+            update_tx: Mutex::new(Some(update_tx)),
+        });
+        let join_handle = tokio::spawn(shared.clone().listen(
+            channel.join_payload.clone(),
+            events,
+            channel.statuses(),
+        ));
+
+        Arc::new(Self {
+            socket,
+            channel,
+            shared,
+            join_handle,
+        })
+    }
+
+    async fn call_actual(
+        &self,
+        event: Event,
+        payload: Payload,
+        timeout: Duration,
+    ) -> Result<(Payload, Option<Merged>), LiveViewError> {
+        let reply = self.channel.call(event, payload, timeout).await?;
+
+        let merged = match rust::message::Payload::from(reply.clone()) {
+            rust::message::Payload::Value(value) => match value.get("diff") {
+                Some(diff) => Some(self.shared.diff_received(diff.clone())?),
+                None => None,
+            },
+            rust::message::Payload::Binary(_) => None,
+        };
+
+        Ok((reply, merged))
+    }
+}
+impl Drop for LiveView {
+    fn drop(&mut self) {
+        self.join_handle.abort();
+    }
+}
+
+struct RenderedState {
+    /// The join reply `rendered` was last replaced from, to only apply each once.
+    join_reply: Option<Arc<rust::message::Payload>>,
+    rendered: Option<Rendered>,
+}
+
+struct Shared {
+    rendered: Mutex<RenderedState>,
+    /// Dropped when [Shared::listen] ends, so that [LiveViewUpdates] end too.
+    update_tx: Mutex<Option<broadcast::Sender<LiveViewUpdate>>>,
+}
+impl Shared {
+    async fn listen(
+        self: Arc<Self>,
+        join_payload: Arc<JoinPayload>,
+        events: Arc<Events>,
+        statuses: Arc<ChannelStatuses>,
+    ) {
+        loop {
+            tokio::select! {
+                result = events.event() => match result {
+                    Ok(event_payload) => self.event_payload_received(&join_payload, event_payload),
+                    Err(EventsError::MissedEvents { missed_event_count }) => error!(
+                        "LiveView missed {} events, so it may render wrong until the next rejoin",
+                        missed_event_count
+                    ),
+                    Err(EventsError::NoMoreEvents) => break,
+                },
+                result = statuses.status() => match result {
+                    Ok(Ok(ChannelStatus::Joined)) | Err(StatusesError::MissedStatuses { .. }) => {
+                        if let Err(error) = self.join_reply_received(&join_payload) {
+                            error!("LiveView ignored invalid join reply: {}", error)
+                        }
+                    }
+                    Ok(_) => (),
+                    Err(StatusesError::NoMoreStatuses) => break,
+                }
+            }
+        }
+
+        self.update_tx.lock().unwrap().take();
+    }
+
+    fn event_payload_received(&self, join_payload: &JoinPayload, event_payload: EventPayload) {
+        let EventPayload { event, payload } = event_payload;
+        let value = match rust::message::Payload::from(payload) {
+            rust::message::Payload::Value(value) => value,
+            rust::message::Payload::Binary(_) => {
+                error!("LiveView ignored binary {} payload", event);
+
+                return;
+            }
+        };
+
+        let user = match &event {
+            Event::User { user } => user.as_str(),
+            Event::Phoenix { .. } => return,
+        };
+
+        if user == DIFF_EVENT {
+            // The diff is for the render in the reply to the join that is being delivered, which
+            // the status may not have announced yet.
+            let result = self
+                .join_reply_received(join_payload)
+                .and_then(|_| self.diff_received(value.as_ref().clone()));
+
+            if let Err(error) = result {
+                error!("LiveView ignored {} payload: {}", event, error)
+            }
+        } else {
+            match LiveViewUpdate::navigation(user, &value) {
+                Some(update) => self.send(update),
+                None => error!("LiveView ignored {} payload without `to`", event),
+            }
+        }
+    }
+
+    /// Replaces the rendered tree with the `rendered` of the reply to the last join, unless it was
+    /// already.
+    fn join_reply_received(&self, join_payload: &JoinPayload) -> Result<(), LiveViewError> {
+        let join_reply = match join_payload.reply() {
+            Some(join_reply) => join_reply,
+            None => return Ok(()),
+        };
+        let mut state = self.rendered.lock().unwrap();
+
+        if let Some(applied_join_reply) = &state.join_reply {
+            if Arc::ptr_eq(applied_join_reply, &join_reply) {
+                return Ok(());
+            }
+        }
+
+        state.join_reply = Some(join_reply.clone());
+
+        let rendered = match join_reply.as_ref() {
+            rust::message::Payload::Value(value) => value.get("rendered").cloned(),
+            rust::message::Payload::Binary(_) => None,
+        }
+        .ok_or_else(|| LiveViewError::InvalidRendered {
+            reason: "join reply has no rendered".to_string(),
+        })?;
+        let (rendered, merged) = Rendered::new(rendered)?;
+        let update = LiveViewUpdate::Render {
+            rendered: Value::Object(rendered.tree().clone()).into(),
+            title: merged.title,
+            events: pushed_events(merged.events),
+        };
+        state.rendered = Some(rendered);
+
+        // Sent while locked, so updates are in the order they were merged.
+        self.send(update);
+
+        Ok(())
+    }
+
+    fn diff_received(&self, diff: Value) -> Result<Merged, LiveViewError> {
+        let mut state = self.rendered.lock().unwrap();
+        let rendered = state
+            .rendered
+            .as_mut()
+            .ok_or_else(|| LiveViewError::InvalidRendered {
+                reason: "diff received before the join reply".to_string(),
+            })?;
+        let mut merged = rendered.merge_diff(diff)?;
+
+        self.send(LiveViewUpdate::Diff {
+            diff: Value::Object(merged.diff.clone()).into(),
+            changed_components: merged.changed_components.clone(),
+            title: merged.title.clone(),
+            events: pushed_events(std::mem::take(&mut merged.events)),
+        });
+
+        Ok(merged)
+    }
+
+    fn current_render(&self) -> Option<LiveViewUpdate> {
+        self.rendered
+            .lock()
+            .unwrap()
+            .rendered
+            .as_ref()
+            .map(|rendered| LiveViewUpdate::Render {
+                rendered: Value::Object(rendered.tree().clone()).into(),
+                title: None,
+                events: Vec::new(),
+            })
+    }
+
+    fn send(&self, update: LiveViewUpdate) {
+        if let Some(update_tx) = self.update_tx.lock().unwrap().as_ref() {
+            update_tx.send(update).ok();
+        }
+    }
+}
+
+fn pushed_events(events: Vec<(String, Value)>) -> Vec<LiveViewPushedEvent> {
+    events
+        .into_iter()
+        .map(|(event, payload)| LiveViewPushedEvent {
+            event,
+            payload: payload.into(),
+        })
+        .collect()
+}
+
+/// A change to the render of a [LiveView].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "uniffi",
+    derive(uniffi::Enum)
+)]
+pub enum LiveViewUpdate {
+    /// The LiveView was mounted by a join or rejoin, so the rendered tree was replaced.
+    Render {
+        /// The new [LiveView::rendered].
+        rendered: JSON,
+        /// The page title set in `mount/3`.
+        title: Option<String>,
+        /// The events pushed with `push_event/3` in `mount/3`.
+        events: Vec<LiveViewPushedEvent>,
+    },
+    /// A diff from a `diff` event or a reply was merged into the rendered tree.
+    Diff {
+        /// The diff as sent by the server, without the title, events or reply.
+        diff: JSON,
+        /// The IDs of the components in the diff.
+        changed_components: Vec<u32>,
+        /// The new page title.
+        title: Option<String>,
+        /// The events pushed with `push_event/3`.
+        events: Vec<LiveViewPushedEvent>,
+    },
+    /// The server navigated away from the LiveView.
+    Navigation {
+        /// How the server navigated.
+        navigation: LiveViewNavigation,
+        /// The path or URL to navigate to.
+        to: String,
+        /// `push` or `replace` for how the browser history is changed, if sent.
+        kind: Option<String>,
+    },
+}
+impl LiveViewUpdate {
+    fn navigation(event: &str, value: &Value) -> Option<Self> {
+        let navigation = match event {
+            REDIRECT_EVENT => LiveViewNavigation::Redirect,
+            LIVE_REDIRECT_EVENT => LiveViewNavigation::LiveRedirect,
+            _ => LiveViewNavigation::LivePatch,
+        };
+        let to = value.get("to")?.as_str()?.to_string();
+        let kind = value
+            .get("kind")
+            .and_then(Value::as_str)
+            .map(ToString::to_string);
+
+        Some(Self::Navigation {
+            navigation,
+            to,
+            kind,
+        })
+    }
+}
+
+/// How the server navigated away from a [LiveView].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "uniffi",
+    derive(uniffi::Enum)
+)]
+pub enum LiveViewNavigation {
+    /// `redirect/2` to a page that is not a LiveView in the same `live_session`, so the page
+    /// should be loaded again.
+    Redirect,
+    /// `push_navigate/2` to another LiveView, which should be joined instead.
+    LiveRedirect,
+    /// `push_patch/2` to the same LiveView, which only changes the URL.
+    LivePatch,
+}
+
+/// An event pushed to the client with `push_event/3`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "uniffi",
+    derive(uniffi::Record)
+)]
+pub struct LiveViewPushedEvent {
+    /// The event name.
+    pub event: String,
+    /// The payload.
+    pub payload: JSON,
+}
+
+/// Waits for [LiveViewUpdate]s from a [LiveView].
+#[cfg_attr(
+    feature = "uniffi",
+    derive(uniffi::Object)
+)]
+pub struct LiveViewUpdates {
+    receiver: tokio::sync::Mutex<broadcast::Receiver<LiveViewUpdate>>,
+}
+#[cfg_attr(
+    feature = "uniffi",
+    uniffi::export
+)]
+impl LiveViewUpdates {
+    /// Wait for next [LiveViewUpdate].
+    pub async fn update(&self) -> Result<LiveViewUpdate, LiveViewUpdatesError> {
+        self.receiver
+            .lock()
+            .await
+            .recv()
+            .await
+            .map_err(From::from)
+    }
+}
+
+/// Errors when calling [LiveViewUpdates::update].
+#[derive(Debug, thiserror::Error)]
+#[cfg_attr(
+    feature = "uniffi",
+    derive(uniffi::Error)
+)]
+pub enum LiveViewUpdatesError {
+    /// There are no more updates because the [LiveView] was dropped or its [Channel] shutdown.
+    #[error("No more LiveView updates left")]
+    NoMoreUpdates,
+    /// [LiveViewUpdates::update] wasn't called often enough and some [LiveViewUpdate]s were
+    /// dropped.  Use [LiveView::rendered] to get the current render.
+    #[error("Missed {missed_update_count} LiveView updates; jumping to next update")]
+    MissedUpdates {
+        /// How many [LiveViewUpdate]s were missed.
+        missed_update_count: u64,
+    },
+}
+impl From<broadcast::error::RecvError> for LiveViewUpdatesError {
+    fn from(recv_error: broadcast::error::RecvError) -> Self {
+        match recv_error {
+            broadcast::error::RecvError::Closed => Self::NoMoreUpdates,
+            broadcast::error::RecvError::Lagged(missed_update_count) => Self::MissedUpdates {
+                missed_update_count,
+            },
+        }
+    }
+}
+
+/// Called with [LiveViewUpdate]s, as registered with [LiveView::add_update_listener].
+#[cfg_attr(feature = "uniffi", uniffi::export(callback_interface))]
+pub trait LiveViewListener: Send + Sync {
+    /// The next [LiveViewUpdate].
+    fn on_update(&self, update: LiveViewUpdate);
+}
+
+/// Errors when calling [LiveView::join], [LiveView::push_event] or [LiveView::call].
+#[derive(Debug, thiserror::Error)]
+#[cfg_attr(
+    feature = "uniffi",
+    derive(uniffi::Error)
+)]
+pub enum LiveViewError {
+    /// Joining the [Channel] failed.
+    #[error("join error: {join_error}")]
+    Join {
+        /// The error from [Channel::join].
+        join_error: ChannelJoinError,
+    },
+    /// Calling the [Channel] failed.
+    #[error("call error: {call_error}")]
+    Call {
+        /// The error from [Channel::call].
+        call_error: CallError,
+    },
+    /// The render in the join reply or a diff could not be merged into the rendered tree.
+    #[error("invalid rendered: {reason}")]
+    InvalidRendered {
+        /// Why the render or diff is invalid.
+        reason: String,
+    },
+}
+impl From<ChannelJoinError> for LiveViewError {
+    fn from(join_error: ChannelJoinError) -> Self {
+        Self::Join { join_error }
+    }
+}
+impl From<CallError> for LiveViewError {
+    fn from(call_error: CallError) -> Self {
+        Self::Call { call_error }
+    }
+}
+impl From<rust::live_view::RenderedError> for LiveViewError {
+    fn from(rendered_error: rust::live_view::RenderedError) -> Self {
+        Self::InvalidRendered {
+            reason: rendered_error.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::testing::{json_payload, FakeServer, ScriptedReply, TIMEOUT};
+
+    async fn next_diff(updates: &LiveViewUpdates) -> LiveViewUpdate {
+        loop {
+            let update = updates.update().await.unwrap();
+
+            if let LiveViewUpdate::Diff { .. } = update {
+                return update;
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn live_view_renders_join_reply_and_merges_diffs() {
+        let server = FakeServer::new();
+        server.reply_to_join(
+            "lv:phx-1",
+            ScriptedReply::Ok(json_payload(json!({
+                "rendered": { "0": "0", "s": ["<p>Count: ", "</p>"], "t": "Counter" }
+            }))),
+        );
+        server.reply_to_call(
+            "lv:phx-1",
+            EVENT_EVENT,
+            ScriptedReply::Ok(json_payload(json!({
+                "diff": { "0": "1", "r": { "count": 1 } }
+            }))),
+        );
+        let socket = server.connected_socket().await;
+        let live_view = socket
+            .live_view(
+                LiveViewJoin::new(
+                    "phx-1".to_string(),
+                    "http://phoenix.test/counter".to_string(),
+                    "session-token".to_string(),
+                )
+                .with_static_token("static-token".to_string()),
+            )
+            .await
+            .unwrap();
+        let updates = live_view.updates();
+
+        live_view.join(TIMEOUT).await.unwrap();
+
+        assert_eq!(
+            rust::message::Payload::from(live_view.channel().payload()),
+            rust::message::Payload::from(json!({
+                "url": "http://phoenix.test/counter",
+                "params": { "_mounts": 0 },
+                "session": "session-token",
+                "static": "static-token"
+            }))
+        );
+        assert_eq!(live_view.html().unwrap(), "<p>Count: 0</p>");
+        assert!(matches!(
+            updates.update().await.unwrap(),
+            LiveViewUpdate::Render { title: Some(title), .. } if title == "Counter"
+        ));
+
+        let reply = live_view
+            .push_event(
+                "click".to_string(),
+                "increment".to_string(),
+                JSON::Object {
+                    object: HashMap::new(),
+                },
+                None,
+                TIMEOUT,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(reply, Some(json!({ "count": 1 }).into()));
+        assert_eq!(live_view.html().unwrap(), "<p>Count: 1</p>");
+
+        server.push(
+            "lv:phx-1",
+            DIFF_EVENT,
+            json_payload(json!({ "0": "2", "e": [["counted", { "count": 2 }]] })),
+        );
+
+        loop {
+            if let LiveViewUpdate::Diff { events, .. } = next_diff(&updates).await {
+                if !events.is_empty() {
+                    assert_eq!(
+                        events,
+                        vec![LiveViewPushedEvent {
+                            event: "counted".to_string(),
+                            payload: json!({ "count": 2 }).into(),
+                        }]
+                    );
+
+                    break;
+                }
+            }
+        }
+        assert_eq!(live_view.html().unwrap(), "<p>Count: 2</p>");
+
+        server.push(
+            "lv:phx-1",
+            LIVE_PATCH_EVENT,
+            json_payload(json!({ "to": "/counter?step=2", "kind": "push" })),
+        );
+
+        loop {
+            if let LiveViewUpdate::Navigation {
+                navigation,
+                to,
+                kind,
+            } = updates.update().await.unwrap()
+            {
+                assert_eq!(navigation, LiveViewNavigation::LivePatch);
+                assert_eq!(to, "/counter?step=2");
+                assert_eq!(kind, Some("push".to_string()));
+
+                break;
+            }
+        }
+
+        socket.shutdown().await.unwrap();
+    }
+}
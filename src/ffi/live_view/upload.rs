@@ -0,0 +1,909 @@
+//! Uploads files to a `Phoenix.LiveView` that called `allow_upload/3`, like the `LiveUploader` of
+//! `phoenix_live_view.js`.
+//!
+//! [LiveView::allow_upload] validates the entries with the LiveView, then
+//! [LiveViewUpload::send] joins an `lvu:` channel for each entry, pushes the file as binary
+//! `chunk`s and reports the progress to the LiveView after each chunk.
+//!
+//! ```no_run
+//! # use std::sync::Arc;
+//! # use std::time::Duration;
+//! #
+//! # use phoenix_channels_client::{LiveView, UploadEntry};
+//! #
+//! async fn upload_avatar(live_view: Arc<LiveView>, path: &str) {
+//!     let file = tokio::fs::File::open(path).await.unwrap();
+//!     let size = file.metadata().await.unwrap().len();
+//!     let uploads = live_view
+//!         .allow_upload(
+//!             // The `data-phx-upload-ref` of the `live_file_input`.
+//!             "phx-upload-ref".to_string(),
+//!             vec![UploadEntry::new("avatar.png".to_string(), size, "image/png".to_string())],
+//!             None,
+//!             Duration::from_secs(5),
+//!         )
+//!         .await
+//!         .unwrap();
+//!
+//!     uploads[0].send_reader(file, None).await.unwrap();
+//! }
+//! ```
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use log::debug;
+use serde_json::{json, Value};
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::sync::watch;
+
+use crate::ffi::channel::statuses::ChannelStatusJoinError;
+use crate::ffi::channel::{CallError, Channel, ChannelJoinError, ChannelStatus};
+use crate::ffi::live_view::{LiveView, LiveViewError};
+use crate::ffi::message::{Event, Payload};
+use crate::ffi::observable_status::StatusesError;
+use crate::ffi::socket::SocketChannelError;
+use crate::ffi::topic::Topic;
+use crate::rust;
+
+/// The event pushed to the LiveView to validate the entries of an upload.
+const ALLOW_UPLOAD_EVENT: &str = "allow_upload";
+/// The event pushed to the LiveView after each chunk.
+const PROGRESS_EVENT: &str = "progress";
+/// The event pushed to the `lvu:` channel with each chunk.
+const CHUNK_EVENT: &str = "chunk";
+/// `phoenix_live_view.js`'s default for `allow_upload/3`'s `:chunk_size`.
+const DEFAULT_CHUNK_SIZE: u64 = 64_000;
+/// `phoenix_live_view.js`'s default for `allow_upload/3`'s `:chunk_timeout`.
+const DEFAULT_CHUNK_TIMEOUT: Duration = Duration::from_millis(10_000);
+
+/// A file to upload, as described to the LiveView by [LiveView::allow_upload].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "uniffi",
+    derive(uniffi::Record)
+)]
+pub struct UploadEntry {
+    /// The file name.
+    pub name: String,
+    /// The size of the file in bytes, which is how many bytes are read from its source.
+    pub size: u64,
+    /// The MIME type, such as `image/png`.
+    pub r#type: String,
+    /// When the file was last modified, in milliseconds since the Unix epoch.
+    #[cfg_attr(feature = "uniffi", uniffi(default = None))]
+    pub last_modified: Option<u64>,
+    /// The path of the file within an uploaded directory.
+    #[cfg_attr(feature = "uniffi", uniffi(default = None))]
+    pub relative_path: Option<String>,
+}
+impl UploadEntry {
+    /// A file called `name` of `size` bytes and MIME `type`.
+    pub fn new(name: String, size: u64, r#type: String) -> Self {
+        Self {
+            name,
+            size,
+            r#type,
+            last_modified: None,
+            relative_path: None,
+        }
+    }
+
+    /// Sets [UploadEntry::last_modified].
+    pub fn with_last_modified(mut self, last_modified: u64) -> Self {
+        self.last_modified = Some(last_modified);
+        self
+    }
+
+    /// Sets [UploadEntry::relative_path].
+    pub fn with_relative_path(mut self, relative_path: String) -> Self {
+        self.relative_path = Some(relative_path);
+        self
+    }
+}
+
+/// Reads the bytes of an [UploadEntry] for [LiveViewUpload::send].
+#[cfg_attr(feature = "uniffi", uniffi::export(callback_interface))]
+pub trait UploadSource: Send + Sync {
+    /// The next bytes of the file, at most `max_len`.  Empty once the whole file was read.
+    fn read(&self, max_len: u32) -> Vec<u8>;
+}
+
+/// Called with the progress of a [LiveViewUpload::send].
+#[cfg_attr(feature = "uniffi", uniffi::export(callback_interface))]
+pub trait UploadProgressListener: Send + Sync {
+    /// The percentage of the file that the server received, from 0 to 100.
+    fn on_progress(&self, progress: u8);
+}
+
+/// An entry the LiveView allowed, to upload with [LiveViewUpload::send].
+#[cfg_attr(
+    feature = "uniffi",
+    derive(uniffi::Object)
+)]
+pub struct LiveViewUpload {
+    live_view: Arc<LiveView>,
+    /// The `data-phx-upload-ref` of the upload, which is the `ref` of the upload config.
+    upload_ref: String,
+    entry_ref: String,
+    cid: Option<u32>,
+    size: u64,
+    /// The token the `lvu:` channel is joined with.
+    token: String,
+    chunk_size: u64,
+    chunk_timeout: Duration,
+    sent: AtomicBool,
+    cancelled_tx: watch::Sender<bool>,
+}
+#[cfg_attr(
+    feature = "uniffi",
+    uniffi::export
+)]
+impl LiveViewUpload {
+    /// The reference the LiveView knows the entry by, such as in `cancel_upload/3`.
+    pub fn entry_ref(&self) -> String {
+        self.entry_ref.clone()
+    }
+
+    /// Uploads the file read from `source`, calling `listener` with the progress, and returns
+    /// once the LiveView acknowledged the whole file.
+    ///
+    /// If the connection drops, the file is sent again from byte 0 once the `lvu:` channel
+    /// rejoins, as the server starts the entry over, so the bytes read from `source` are kept
+    /// until the upload finishes.
+    pub async fn send(
+        &self,
+        source: Box<dyn UploadSource>,
+        listener: Option<Box<dyn UploadProgressListener>>,
+    ) -> Result<(), UploadError> {
+        self.send_actual(Source::Callback(source.into()), listener)
+            .await
+    }
+
+    /// Stops a [LiveViewUpload::send] in progress, which then returns [UploadError::Cancelled],
+    /// and tells the LiveView the entry failed.
+    pub fn cancel(&self) {
+        self.cancelled_tx.send_replace(true);
+    }
+}
+// Rust-only, as generic types can't be exported with `uniffi`
+impl LiveViewUpload {
+    /// Uploads the file read from `reader`, like [LiveViewUpload::send].
+    pub async fn send_reader<R>(
+        &self,
+        reader: R,
+        listener: Option<Box<dyn UploadProgressListener>>,
+    ) -> Result<(), UploadError>
+    where
+        R: AsyncRead + Send + Unpin + 'static,
+    {
+        self.send_actual(Source::Reader(Box::new(reader)), listener)
+            .await
+    }
+
+    /// Pushes `allow_upload` for `entries` and returns an upload for each, in order.
+    pub(crate) async fn allow(
+        live_view: Arc<LiveView>,
+        upload_ref: String,
+        entries: Vec<UploadEntry>,
+        cid: Option<u32>,
+        timeout: Duration,
+    ) -> Result<Vec<Arc<Self>>, UploadError> {
+        let entry_refs: Vec<String> = entries
+            .iter()
+            .map(|_| live_view.socket.next_upload_entry_ref())
+            .collect();
+        let mut payload = json!({
+            "ref": upload_ref,
+            "entries": entries
+                .iter()
+                .zip(&entry_refs)
+                .map(|(entry, entry_ref)| json!({
+                    "name": entry.name,
+                    "size": entry.size,
+                    "type": entry.r#type,
+                    "last_modified": entry.last_modified,
+                    "relative_path": entry.relative_path,
+                    "ref": entry_ref,
+                }))
+                .collect::<Vec<_>>(),
+        });
+
+        if let Some(cid) = cid {
+            payload["cid"] = cid.into();
+        }
+
+        let (reply, _) = live_view
+            .call_actual(
+                Event::from_string(ALLOW_UPLOAD_EVENT.to_string()),
+                rust::message::Payload::from(payload).into(),
+                timeout,
+            )
+            .await?;
+        let reply = match rust::message::Payload::from(reply) {
+            rust::message::Payload::Value(value) => value,
+            rust::message::Payload::Binary(_) => Arc::new(Value::Null),
+        };
+
+        let errors = entry_errors(&reply);
+
+        if !errors.is_empty() {
+            return Err(UploadError::Rejected { errors });
+        }
+
+        let config = &reply["config"];
+        let chunk_size = config["chunk_size"]
+            .as_u64()
+            .filter(|chunk_size| *chunk_size > 0)
+            .unwrap_or(DEFAULT_CHUNK_SIZE);
+        let chunk_timeout = config["chunk_timeout"]
+            .as_u64()
+            .map(Duration::from_millis)
+            .unwrap_or(DEFAULT_CHUNK_TIMEOUT);
+        let mut uploads = Vec::with_capacity(entries.len());
+        let mut errors = Vec::new();
+
+        for (entry, entry_ref) in entries.into_iter().zip(entry_refs) {
+            // External uploads have a map of metadata for the client's uploader instead.
+            match reply["entries"][&entry_ref].as_str() {
+                Some(token) => uploads.push(Arc::new(Self {
+                    live_view: live_view.clone(),
+                    upload_ref: upload_ref.clone(),
+                    entry_ref,
+                    cid,
+                    size: entry.size,
+                    token: token.to_string(),
+                    chunk_size,
+                    chunk_timeout,
+                    sent: AtomicBool::new(false),
+                    cancelled_tx: watch::channel(false).0,
+                })),
+                None => errors.push(UploadEntryError {
+                    entry_ref,
+                    reason: "invalid".to_string(),
+                }),
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(uploads)
+        } else {
+            Err(UploadError::Rejected { errors })
+        }
+    }
+
+    async fn send_actual(
+        &self,
+        mut source: Source,
+        listener: Option<Box<dyn UploadProgressListener>>,
+    ) -> Result<(), UploadError> {
+        if self.sent.swap(true, Ordering::SeqCst) {
+            return Err(UploadError::AlreadySent);
+        }
+
+        let mut cancelled_rx = self.cancelled_tx.subscribe();
+        let channel = self
+            .live_view
+            .socket
+            .channel(
+                Topic::from_string(format!("lvu:{}", self.entry_ref)),
+                Some(rust::message::Payload::from(json!({ "token": self.token })).into()),
+            )
+            .await?;
+
+        let result = tokio::select! {
+            result = self.upload(&channel, &mut source, listener.as_deref()) => result,
+            _ = cancelled(&mut cancelled_rx) => Err(UploadError::Cancelled),
+        };
+
+        if let Err(error) = &result {
+            let reason = match error {
+                UploadError::Cancelled => "cancelled",
+                _ => "failed",
+            };
+
+            self.push_progress(json!({ "error": reason })).await.ok();
+        }
+
+        tokio::time::timeout(self.chunk_timeout, channel.leave())
+            .await
+            .ok();
+        channel.shutdown().await.ok();
+
+        result
+    }
+
+    async fn upload(
+        &self,
+        channel: &Channel,
+        source: &mut Source,
+        listener: Option<&dyn UploadProgressListener>,
+    ) -> Result<(), UploadError> {
+        channel.join(self.chunk_timeout).await?;
+
+        // A rejoined `lvu:` channel starts the entry over on the server, so keep what was read
+        // to send it again.
+        let mut read = Vec::new();
+        let mut join_reply = channel.join_payload.reply();
+        let mut offset = 0;
+        let mut last_progress = None;
+
+        loop {
+            let end = self.size.min(offset + self.chunk_size) as usize;
+
+            if read.len() < end {
+                let chunk =
+                    source
+                        .read(end - read.len())
+                        .await
+                        .map_err(|error| UploadError::Source {
+                            reason: error.to_string(),
+                        })?;
+                read.extend_from_slice(&chunk);
+            }
+
+            let chunk = &read[offset as usize..end.min(read.len())];
+
+            if chunk.is_empty() && offset < self.size {
+                return Err(UploadError::Source {
+                    reason: format!("ended after {} of {} bytes", offset, self.size),
+                });
+            }
+
+            if !chunk.is_empty() {
+                let len = chunk.len() as u64;
+                self.send_chunk(channel, chunk.to_vec(), &join_reply)
+                    .await?;
+
+                let current_join_reply = channel.join_payload.reply();
+
+                if same_join_reply(&join_reply, &current_join_reply) {
+                    offset += len;
+                } else {
+                    debug!(
+                        "lvu:{} rejoined after {} bytes, so sending from byte 0",
+                        self.entry_ref, offset
+                    );
+                    join_reply = current_join_reply;
+                    offset = 0;
+                    continue;
+                }
+            }
+
+            // An empty file is complete without any chunks.
+            let progress = (offset * 100)
+                .checked_div(self.size)
+                .map_or(100, |progress| progress as u8);
+
+            if last_progress < Some(progress) {
+                last_progress = Some(progress);
+                self.push_progress(progress.into()).await?;
+
+                if let Some(listener) = listener {
+                    listener.on_progress(progress);
+                }
+            }
+
+            if offset >= self.size {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Sends `chunk`, or waits for the channel to rejoin if the connection dropped, which
+    /// [LiveViewUpload::upload] sees as a `join_reply` other than the one passed.
+    async fn send_chunk(
+        &self,
+        channel: &Channel,
+        chunk: Vec<u8>,
+        join_reply: &Option<Arc<rust::message::Payload>>,
+    ) -> Result<(), UploadError> {
+        match channel
+            .call(
+                Event::from_string(CHUNK_EVENT.to_string()),
+                Payload::binary_from_bytes(chunk),
+                self.chunk_timeout,
+            )
+            .await
+        {
+            Ok(_) => Ok(()),
+            Err(CallError::SocketDisconnected) => rejoined(channel, join_reply).await,
+            Err(CallError::Timeout)
+                if channel.status() != ChannelStatus::Joined
+                    || !same_join_reply(join_reply, &channel.join_payload.reply()) =>
+            {
+                rejoined(channel, join_reply).await
+            }
+            Err(call_error) => Err(UploadError::Chunk { call_error }),
+        }
+    }
+
+    async fn push_progress(&self, progress: Value) -> Result<(), UploadError> {
+        let mut payload = json!({
+            "event": null,
+            "ref": self.upload_ref,
+            "entry_ref": self.entry_ref,
+            "progress": progress,
+        });
+
+        if let Some(cid) = self.cid {
+            payload["cid"] = cid.into();
+        }
+
+        self.live_view
+            .call_actual(
+                Event::from_string(PROGRESS_EVENT.to_string()),
+                rust::message::Payload::from(payload).into(),
+                self.chunk_timeout,
+            )
+            .await?;
+
+        Ok(())
+    }
+}
+
+/// The `errors` in an `allow_upload` reply, sent either as a map or as a list of pairs of entry
+/// reference and reason.
+fn entry_errors(reply: &Value) -> Vec<UploadEntryError> {
+    let entry_error = |entry_ref: &Value, reason: &Value| UploadEntryError {
+        entry_ref: entry_ref
+            .as_str()
+            .map(ToString::to_string)
+            .unwrap_or_else(|| entry_ref.to_string()),
+        reason: reason
+            .as_str()
+            .map(ToString::to_string)
+            .unwrap_or_else(|| reason.to_string()),
+    };
+
+    match reply.get("errors").or_else(|| reply.get("error")) {
+        Some(Value::Object(errors)) => errors
+            .iter()
+            .map(|(entry_ref, reason)| entry_error(&Value::String(entry_ref.clone()), reason))
+            .collect(),
+        Some(Value::Array(errors)) => match errors.as_slice() {
+            [entry_ref, reason] if !entry_ref.is_array() => vec![entry_error(entry_ref, reason)],
+            errors => errors
+                .iter()
+                .filter_map(|error| match error.as_array()?.as_slice() {
+                    [entry_ref, reason] => Some(entry_error(entry_ref, reason)),
+                    _ => None,
+                })
+                .collect(),
+        },
+        _ => Vec::new(),
+    }
+}
+
+/// Waits until [LiveViewUpload::cancel] is called.
+async fn cancelled(cancelled_rx: &mut watch::Receiver<bool>) {
+    while !*cancelled_rx.borrow() {
+        if cancelled_rx.changed().await.is_err() {
+            futures::future::pending::<()>().await;
+        }
+    }
+}
+
+/// Waits until `channel` is joined again after an automatic rejoin, which replaces `join_reply`.
+async fn rejoined(
+    channel: &Channel,
+    join_reply: &Option<Arc<rust::message::Payload>>,
+) -> Result<(), UploadError> {
+    let statuses = channel.statuses();
+
+    loop {
+        match channel.status() {
+            // The reply is stored before the status changes, so a joined channel with the same
+            // reply has not rejoined yet.
+            ChannelStatus::Joined
+                if !same_join_reply(join_reply, &channel.join_payload.reply()) =>
+            {
+                return Ok(())
+            }
+            ChannelStatus::RejoinAttemptsExhausted
+            | ChannelStatus::Left
+            | ChannelStatus::ShuttingDown
+            | ChannelStatus::ShutDown => return Err(UploadError::Interrupted),
+            _ => (),
+        }
+
+        match statuses.status().await {
+            Ok(Ok(_)) | Err(StatusesError::MissedStatuses { .. }) => (),
+            Ok(Err(ChannelStatusJoinError::Rejected { response })) => {
+                return Err(UploadError::RejoinRejected { response })
+            }
+            Err(StatusesError::NoMoreStatuses) => return Err(UploadError::Interrupted),
+        }
+    }
+}
+
+/// Whether both are the reply to the same join.
+fn same_join_reply(
+    join_reply: &Option<Arc<rust::message::Payload>>,
+    other_join_reply: &Option<Arc<rust::message::Payload>>,
+) -> bool {
+    match (join_reply, other_join_reply) {
+        (Some(join_reply), Some(other_join_reply)) => Arc::ptr_eq(join_reply, other_join_reply),
+        (None, None) => true,
+        _ => false,
+    }
+}
+
+enum Source {
+    Callback(Arc<dyn UploadSource>),
+    Reader(Box<dyn AsyncRead + Send + Unpin>),
+}
+impl Source {
+    /// Reads `len` bytes, unless the source ends first.
+    async fn read(&mut self, len: usize) -> std::io::Result<Vec<u8>> {
+        let mut chunk = Vec::with_capacity(len);
+
+        while chunk.len() < len {
+            let remaining = len - chunk.len();
+            let read = match self {
+                Source::Callback(source) => {
+                    let source = source.clone();
+                    let max_len = remaining.min(u32::MAX as usize) as u32;
+                    // A blocking foreign callback, such as one reading a file, must not stall the
+                    // runtime's worker thread.
+                    let mut bytes = tokio::task::spawn_blocking(move || source.read(max_len))
+                        .await
+                        .unwrap_or_else(|join_error| {
+                            std::panic::resume_unwind(join_error.into_panic())
+                        });
+                    bytes.truncate(remaining);
+                    let read = bytes.len();
+                    chunk.extend(bytes);
+
+                    read
+                }
+                Source::Reader(reader) => {
+                    (&mut **reader)
+                        .take(remaining as u64)
+                        .read_to_end(&mut chunk)
+                        .await?
+                }
+            };
+
+            if read == 0 {
+                break;
+            }
+        }
+
+        Ok(chunk)
+    }
+}
+
+/// Why the LiveView did not allow an [UploadEntry].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "uniffi",
+    derive(uniffi::Record)
+)]
+pub struct UploadEntryError {
+    /// The reference of the entry.
+    pub entry_ref: String,
+    /// The reason from the LiveView, such as `too_large` or `not_accepted`.
+    pub reason: String,
+}
+
+/// Errors when calling [LiveView::allow_upload] or [LiveViewUpload::send].
+#[derive(Debug, thiserror::Error)]
+#[cfg_attr(
+    feature = "uniffi",
+    derive(uniffi::Error)
+)]
+pub enum UploadError {
+    /// Pushing `allow_upload` or `progress` to the LiveView failed.
+    #[error("LiveView error: {live_view_error}")]
+    LiveView {
+        /// The error from the [LiveView].
+        live_view_error: LiveViewError,
+    },
+    /// The LiveView did not allow some of the entries.
+    #[error("upload entries rejected: {errors:?}")]
+    Rejected {
+        /// Why each entry was not allowed.
+        errors: Vec<UploadEntryError>,
+    },
+    /// The `lvu:` channel could not be created.
+    #[error("socket channel error: {socket_channel_error}")]
+    SocketChannel {
+        /// The error from [Socket::channel](crate::Socket::channel).
+        socket_channel_error: SocketChannelError,
+    },
+    /// Joining the `lvu:` channel failed.
+    #[error("join error: {join_error}")]
+    Join {
+        /// The error from [Channel::join].
+        join_error: ChannelJoinError,
+    },
+    /// The server rejected a rejoin of the `lvu:` channel, such as because the LiveView was
+    /// remounted and no longer has the upload.
+    #[error("rejoin rejected: {response}")]
+    RejoinRejected {
+        /// The response from the server.
+        response: Payload,
+    },
+    /// The `lvu:` channel stopped rejoining before the whole file was sent.
+    #[error("upload channel stopped rejoining")]
+    Interrupted,
+    /// Sending a chunk failed.
+    #[error("chunk error: {call_error}")]
+    Chunk {
+        /// The error from [Channel::call].
+        call_error: CallError,
+    },
+    /// Reading the file failed or it was shorter than [UploadEntry::size].
+    #[error("source error: {reason}")]
+    Source {
+        /// Why reading failed.
+        reason: String,
+    },
+    /// [LiveViewUpload::cancel] was called.
+    #[error("upload cancelled")]
+    Cancelled,
+    /// [LiveViewUpload::send] was already called for this entry.
+    #[error("upload already sent")]
+    AlreadySent,
+}
+impl From<LiveViewError> for UploadError {
+    fn from(live_view_error: LiveViewError) -> Self {
+        Self::LiveView { live_view_error }
+    }
+}
+impl From<SocketChannelError> for UploadError {
+    fn from(socket_channel_error: SocketChannelError) -> Self {
+        Self::SocketChannel {
+            socket_channel_error,
+        }
+    }
+}
+impl From<ChannelJoinError> for UploadError {
+    fn from(join_error: ChannelJoinError) -> Self {
+        Self::Join { join_error }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ffi::live_view::LiveViewJoin;
+    use crate::ffi::socket::Socket;
+    use crate::testing::{json_payload, FakeServer, ScriptedReply, TIMEOUT};
+
+    #[test]
+    fn entry_errors_are_read_from_map_or_pairs() {
+        let expected = vec![UploadEntryError {
+            entry_ref: "0".to_string(),
+            reason: "too_large".to_string(),
+        }];
+
+        assert_eq!(
+            entry_errors(&json!({ "errors": { "0": "too_large" } })),
+            expected
+        );
+        assert_eq!(
+            entry_errors(&json!({ "errors": [["0", "too_large"]] })),
+            expected
+        );
+        assert_eq!(
+            entry_errors(&json!({ "error": ["0", "too_large"] })),
+            expected
+        );
+        assert!(entry_errors(&json!({ "entries": { "0": "token" } })).is_empty());
+    }
+
+    #[tokio::test]
+    async fn source_reads_up_to_len() {
+        let mut source = Source::Reader(Box::new(&b"abcde"[..]));
+
+        assert_eq!(source.read(3).await.unwrap(), b"abc");
+        assert_eq!(source.read(3).await.unwrap(), b"de");
+        assert!(source.read(3).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn callback_source_reads_up_to_len() {
+        struct Bytes(std::sync::Mutex<Vec<u8>>);
+        impl UploadSource for Bytes {
+            fn read(&self, max_len: u32) -> Vec<u8> {
+                let mut bytes = self.0.lock().unwrap();
+                let len = bytes.len().min(max_len as usize);
+
+                bytes.drain(..len).collect()
+            }
+        }
+
+        let mut source = Source::Callback(Arc::new(Bytes(b"abcde".to_vec().into())));
+
+        assert_eq!(source.read(3).await.unwrap(), b"abc");
+        assert_eq!(source.read(3).await.unwrap(), b"de");
+        assert!(source.read(3).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn live_views_on_one_socket_allow_distinct_entry_refs() {
+        let (server, socket, upload) = allowed_upload(json!({ "chunk_size": 4 })).await;
+        script_live_view(&server, "phx-2", "1", json!({ "chunk_size": 4 }));
+        let other_upload = allow_avatar(&socket, "phx-2").await;
+
+        assert_eq!(upload.entry_ref(), "0");
+        assert_eq!(other_upload.entry_ref(), "1");
+
+        socket.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn upload_sends_chunks_and_progress() {
+        struct Progresses(std::sync::Mutex<Vec<u8>>);
+        impl UploadProgressListener for Arc<Progresses> {
+            fn on_progress(&self, progress: u8) {
+                self.0.lock().unwrap().push(progress);
+            }
+        }
+
+        let (server, socket, upload) = allowed_upload(json!({ "chunk_size": 4 })).await;
+        for _ in 0..2 {
+            server.reply_to_call(
+                "lvu:0",
+                CHUNK_EVENT,
+                ScriptedReply::Ok(json_payload(json!({}))),
+            );
+            server.reply_to_call(
+                "lv:phx-1",
+                PROGRESS_EVENT,
+                ScriptedReply::Ok(json_payload(json!({ "diff": {} }))),
+            );
+        }
+        let progresses = Arc::new(Progresses(Default::default()));
+
+        upload
+            .send_reader(&b"abcdef"[..], Some(Box::new(progresses.clone())))
+            .await
+            .unwrap();
+
+        assert_eq!(*progresses.0.lock().unwrap(), vec![66, 100]);
+        assert!(matches!(
+            upload.send_reader(&b""[..], None).await,
+            Err(UploadError::AlreadySent)
+        ));
+
+        let mut chunks = Vec::new();
+
+        while chunks.len() < 2 {
+            let received = server.next_received().await;
+
+            if received.topic == "lvu:0"
+                && received.event == Event::from_string(CHUNK_EVENT.to_string())
+            {
+                chunks.push(received.payload);
+            }
+        }
+
+        assert_eq!(
+            chunks,
+            vec![
+                Payload::binary_from_bytes(b"abcd".to_vec()),
+                Payload::binary_from_bytes(b"ef".to_vec())
+            ]
+        );
+
+        socket.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn upload_starts_over_when_connection_drops() {
+        let (server, socket, upload) =
+            allowed_upload(json!({ "chunk_size": 4, "chunk_timeout": 1_000 })).await;
+        server.reply_to_join(
+            "lv:phx-1",
+            ScriptedReply::Ok(json_payload(json!({ "rendered": { "s": [""] } }))),
+        );
+        server.reply_to_call("lvu:0", CHUNK_EVENT, ScriptedReply::NoReply);
+        for _ in 0..2 {
+            server.reply_to_call(
+                "lvu:0",
+                CHUNK_EVENT,
+                ScriptedReply::Ok(json_payload(json!({}))),
+            );
+            server.reply_to_call(
+                "lv:phx-1",
+                PROGRESS_EVENT,
+                ScriptedReply::Ok(json_payload(json!({ "diff": {} }))),
+            );
+        }
+        let send = tokio::spawn(async move { upload.send_reader(&b"abcdef"[..], None).await });
+        let mut chunks = Vec::new();
+
+        while chunks.len() < 3 {
+            let received = server.next_received().await;
+
+            if received.topic == "lvu:0"
+                && received.event == Event::from_string(CHUNK_EVENT.to_string())
+            {
+                chunks.push(received.payload);
+
+                if chunks.len() == 1 {
+                    server.disconnect();
+                }
+            }
+        }
+
+        tokio::time::timeout(TIMEOUT, send)
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            chunks,
+            vec![
+                Payload::binary_from_bytes(b"abcd".to_vec()),
+                Payload::binary_from_bytes(b"abcd".to_vec()),
+                Payload::binary_from_bytes(b"ef".to_vec())
+            ]
+        );
+
+        socket.shutdown().await.unwrap();
+    }
+
+    /// A connected socket with the LiveView `phx-1` joined and a 6 byte `avatar.png` allowed with
+    /// `config`.
+    async fn allowed_upload(config: Value) -> (FakeServer, Arc<Socket>, Arc<LiveViewUpload>) {
+        let server = FakeServer::new();
+        script_live_view(&server, "phx-1", "0", config);
+        let socket = server.connected_socket().await;
+        let upload = allow_avatar(&socket, "phx-1").await;
+
+        (server, socket, upload)
+    }
+
+    /// Scripts the join of the LiveView `id` and its reply to `allow_upload`, which allows
+    /// `entry_ref` with `config`.
+    fn script_live_view(server: &FakeServer, id: &str, entry_ref: &str, config: Value) {
+        let topic = format!("lv:{}", id);
+        server.reply_to_join(
+            &topic,
+            ScriptedReply::Ok(json_payload(json!({ "rendered": { "s": [""] } }))),
+        );
+        server.reply_to_call(
+            &topic,
+            ALLOW_UPLOAD_EVENT,
+            ScriptedReply::Ok(json_payload(json!({
+                "ref": "phx-upload",
+                "config": config,
+                "entries": { entry_ref: "upload-token" }
+            }))),
+        );
+    }
+
+    /// Joins the LiveView `id` and allows a 6 byte `avatar.png`.
+    async fn allow_avatar(socket: &Arc<Socket>, id: &str) -> Arc<LiveViewUpload> {
+        let live_view = socket
+            .live_view(LiveViewJoin::new(
+                id.to_string(),
+                "http://phoenix.test/avatar".to_string(),
+                "session-token".to_string(),
+            ))
+            .await
+            .unwrap();
+        live_view.join(TIMEOUT).await.unwrap();
+
+        let mut uploads = live_view
+            .allow_upload(
+                "phx-upload".to_string(),
+                vec![UploadEntry::new(
+                    "avatar.png".to_string(),
+                    6,
+                    "image/png".to_string(),
+                )],
+                None,
+                TIMEOUT,
+            )
+            .await
+            .unwrap();
+
+        uploads.remove(0)
+    }
+}
@@ -0,0 +1,549 @@
+//! Tracks who is present on a [Channel] that uses `Phoenix.Presence`, like `Presence` in
+//! `phoenix.js`.
+//!
+//! ```no_run
+//! # use std::sync::Arc;
+//! # use std::time::Duration;
+//! #
+//! # use phoenix_channels_client::{Channel, Presence, PresenceChange};
+//! #
+//! async fn print_presences(channel: Arc<Channel>) {
+//!     // Before joining, as the server sends the state right after the join.
+//!     let presence = Presence::new(channel.clone());
+//!     let changes = presence.changes();
+//!     channel.join(Duration::from_secs(5)).await.unwrap();
+//!
+//!     while let Ok(change) = changes.change().await {
+//!         if let PresenceChange::Sync = change {
+//!             for entry in presence.list() {
+//!                 println!("{} has {} sessions", entry.key, entry.metas.len());
+//!             }
+//!         }
+//!     }
+//! }
+//! ```
+
+use std::sync::{Arc, Mutex};
+
+use log::error;
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+
+use crate::ffi::channel::join_payload::JoinPayload;
+use crate::ffi::channel::{Channel, EventPayload, Events, EventsError};
+use crate::ffi::json::JSON;
+use crate::ffi::listeners::ListenerHandle;
+use crate::ffi::message::Event;
+use crate::rust;
+use crate::rust::presence::{Change, Metas, PresenceSync, DIFF_EVENT, STATE_EVENT};
+
+/// How many [PresenceChange]s each [PresenceChanges] buffers before it starts missing changes.
+const CHANGE_BUFFER_SIZE: usize = 100;
+
+/// The presences on a [Channel], kept in sync with the `presence_state` and `presence_diff` events
+/// from `Phoenix.Presence` until the [Channel] shuts down or this is dropped.
+///
+/// `presence_diff`s that arrive before the `presence_state` of a join or rejoin are applied once
+/// it arrives.
+/// If the [Channel]'s [Events] miss events, the presences may be wrong until the next
+/// `presence_state` after a rejoin, so consider a lossless
+/// [EventDelivery](crate::EventDelivery) for the [Channel].
+#[cfg_attr(
+    feature = "uniffi",
+    derive(uniffi::Object)
+)]
+pub struct Presence {
+    shared: Arc<Shared>,
+    join_handle: JoinHandle<()>,
+}
+#[cfg_attr(
+    feature = "uniffi",
+    uniffi::export
+)]
+impl Presence {
+    /// Tracks the presences on `channel`.  Create it before [Channel::join], as the server sends
+    /// `presence_state` right after the join.
+    #[cfg(feature = "uniffi")]
+    #[uniffi::constructor]
+    pub fn new(channel: Arc<Channel>) -> Arc<Self> {
+        Self::new_actual(channel)
+    }
+
+    /// The presences, ordered by [PresenceEntry::key].
+    pub fn list(&self) -> Vec<PresenceEntry> {
+        self.shared
+            .presence_sync
+            .lock()
+            .unwrap()
+            .list()
+            .map(|(key, metas)| PresenceEntry::new(key.clone(), metas))
+            .collect()
+    }
+
+    /// The presence for `key` if present.
+    pub fn get(&self, key: String) -> Option<PresenceEntry> {
+        let presence_sync = self.shared.presence_sync.lock().unwrap();
+        let metas = presence_sync.get(&key)?;
+
+        Some(PresenceEntry::new(key, metas))
+    }
+
+    /// Broadcasts [PresenceChange]s.
+    pub fn changes(&self) -> Arc<PresenceChanges> {
+        let receiver = match self.shared.change_tx.lock().unwrap().as_ref() {
+            Some(change_tx) => change_tx.subscribe(),
+            None => broadcast::channel(1).1,
+        };
+
+        Arc::new(PresenceChanges {
+            receiver: tokio::sync::Mutex::new(receiver),
+        })
+    }
+
+    /// Calls `listener` with [PresenceChange]s until the returned [ListenerHandle] is removed or
+    /// the presences are no longer tracked.  If the listener was too slow and some changes were
+    /// missed, it is called with [PresenceChange::Sync] instead, so it can use [Presence::list].
+    pub fn add_change_listener(&self, listener: Box<dyn PresenceListener>) -> Arc<ListenerHandle> {
+        let changes = self.changes();
+
+        ListenerHandle::spawn(async move {
+            loop {
+                match changes.change().await {
+                    Ok(change) => listener.on_change(change),
+                    Err(PresenceChangesError::MissedChanges { .. }) => {
+                        listener.on_change(PresenceChange::Sync)
+                    }
+                    Err(PresenceChangesError::NoMoreChanges) => break,
+                }
+            }
+        })
+    }
+}
+impl Presence {
+    /// Tracks the presences on `channel`.  Create it before [Channel::join], as the server sends
+    /// `presence_state` right after the join.
+    #[cfg(not(feature = "uniffi"))]
+    pub fn new(channel: Arc<Channel>) -> Arc<Self> {
+        Self::new_actual(channel)
+    }
+
+    fn new_actual(channel: Arc<Channel>) -> Arc<Self> {
+        let events = channel.on_matching(|event| match event {
+            Event::User { user } => user == STATE_EVENT || user == DIFF_EVENT,
+            Event::Phoenix { .. } => false,
+        });
+        let (change_tx, _) = broadcast::channel(CHANGE_BUFFER_SIZE);
+        let shared = Arc::new(Shared {
+            presence_sync: Mutex::new(PresenceSync::new()),
+            change_tx: Mutex::new(Some(change_tx)),
+        });
+        let join_handle = tokio::spawn(shared.clone().listen(channel.join_payload.clone(), events));
+
+        Arc::new(Self {
+            shared,
+            join_handle,
+        })
+    }
+}
+impl Drop for Presence {
+    fn drop(&mut self) {
+        self.join_handle.abort();
+    }
+}
+
+struct Shared {
+    presence_sync: Mutex<PresenceSync>,
+    /// Dropped when [Shared::listen] ends, so that [PresenceChanges] end too.
+    change_tx: Mutex<Option<broadcast::Sender<PresenceChange>>>,
+}
+impl Shared {
+    async fn listen(self: Arc<Self>, join_payload: Arc<JoinPayload>, events: Arc<Events>) {
+        // The reply to the join whose state the presences are synced to.
+        let mut synced_join_reply = None;
+
+        loop {
+            match events.event().await {
+                Ok(event_payload) => self.event_payload_received(
+                    &join_payload,
+                    &mut synced_join_reply,
+                    event_payload,
+                ),
+                Err(EventsError::MissedEvents { missed_event_count }) => error!(
+                    "Presence missed {} events, so its presences may be wrong until the next {}",
+                    missed_event_count, STATE_EVENT
+                ),
+                Err(EventsError::NoMoreEvents) => break,
+            }
+        }
+
+        self.change_tx.lock().unwrap().take();
+    }
+
+    fn event_payload_received(
+        &self,
+        join_payload: &JoinPayload,
+        synced_join_reply: &mut Option<Arc<rust::message::Payload>>,
+        event_payload: EventPayload,
+    ) {
+        let EventPayload { event, payload } = event_payload;
+        let value = match rust::message::Payload::from(payload) {
+            rust::message::Payload::Value(value) => value,
+            rust::message::Payload::Binary(_) => {
+                error!("Presence ignored binary {} payload", event);
+
+                return;
+            }
+        };
+
+        let result = {
+            let mut presence_sync = self.presence_sync.lock().unwrap();
+
+            // The reply is stored before the events of its join are delivered, so a different one
+            // means the channel rejoined and the server will send a new state.
+            let join_reply = join_payload.reply();
+            let rejoined = match (synced_join_reply.as_ref(), join_reply.as_ref()) {
+                (Some(synced_join_reply), Some(join_reply)) => {
+                    !Arc::ptr_eq(synced_join_reply, join_reply)
+                }
+                (None, None) => false,
+                _ => true,
+            };
+
+            if rejoined {
+                presence_sync.rejoined();
+                *synced_join_reply = join_reply;
+            }
+
+            match &event {
+                Event::User { user } if user == STATE_EVENT => {
+                    presence_sync.state_received(&value).map(Some)
+                }
+                _ => presence_sync.diff_received(&value),
+            }
+        };
+
+        match result {
+            Ok(Some(changes)) => {
+                if let Some(change_tx) = self.change_tx.lock().unwrap().as_ref() {
+                    for change in changes {
+                        change_tx.send(change.into()).ok();
+                    }
+
+                    change_tx.send(PresenceChange::Sync).ok();
+                }
+            }
+            Ok(None) => (),
+            Err(error) => error!("Presence ignored invalid {} payload: {}", event, error),
+        }
+    }
+}
+
+/// The metas of a presence key.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "uniffi",
+    derive(uniffi::Record)
+)]
+pub struct PresenceEntry {
+    /// The key the presence was tracked under on the server, such as a user ID.
+    pub key: String,
+    /// One meta for each time `key` is tracked, such as each device a user is on.
+    pub metas: Vec<PresenceMeta>,
+}
+impl PresenceEntry {
+    fn new(key: String, metas: &Metas) -> Self {
+        Self {
+            key,
+            metas: metas.iter().cloned().map(From::from).collect(),
+        }
+    }
+}
+
+/// The metadata of one tracking of a presence key.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "uniffi",
+    derive(uniffi::Record)
+)]
+pub struct PresenceMeta {
+    /// The unique reference `Phoenix.Presence` assigned to this tracking.
+    pub phx_ref: Option<String>,
+    /// The metadata as sent by the server, including `phx_ref`.
+    pub meta: JSON,
+}
+impl From<serde_json::Value> for PresenceMeta {
+    fn from(meta: serde_json::Value) -> Self {
+        Self {
+            phx_ref: rust::presence::phx_ref(&meta).map(ToString::to_string),
+            meta: meta.into(),
+        }
+    }
+}
+
+fn presence_metas(metas: Metas) -> Vec<PresenceMeta> {
+    metas.into_iter().map(From::from).collect()
+}
+
+/// A change to the presences of a [Presence], like the `onJoin`, `onLeave` and `onSync` callbacks
+/// of `phoenix.js`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "uniffi",
+    derive(uniffi::Enum)
+)]
+pub enum PresenceChange {
+    /// `joined_metas` were tracked for `key`.
+    Join {
+        /// The presence key.
+        key: String,
+        /// The metas before the join, empty if `key` wasn't present.
+        current_metas: Vec<PresenceMeta>,
+        /// The metas that joined.
+        joined_metas: Vec<PresenceMeta>,
+    },
+    /// `left_metas` were untracked for `key`.
+    Leave {
+        /// The presence key.
+        key: String,
+        /// The metas after the leave, empty if `key` is no longer present.
+        remaining_metas: Vec<PresenceMeta>,
+        /// The metas that left.
+        left_metas: Vec<PresenceMeta>,
+    },
+    /// A `presence_state` or `presence_diff` was applied, so [Presence::list] is up to date.
+    Sync,
+}
+impl From<Change> for PresenceChange {
+    fn from(change: Change) -> Self {
+        match change {
+            Change::Join {
+                key,
+                current_metas,
+                joined_metas,
+            } => Self::Join {
+                key,
+                current_metas: presence_metas(current_metas),
+                joined_metas: presence_metas(joined_metas),
+            },
+            Change::Leave {
+                key,
+                remaining_metas,
+                left_metas,
+            } => Self::Leave {
+                key,
+                remaining_metas: presence_metas(remaining_metas),
+                left_metas: presence_metas(left_metas),
+            },
+        }
+    }
+}
+
+/// Waits for [PresenceChange]s from a [Presence].
+#[cfg_attr(
+    feature = "uniffi",
+    derive(uniffi::Object)
+)]
+pub struct PresenceChanges {
+    receiver: tokio::sync::Mutex<broadcast::Receiver<PresenceChange>>,
+}
+#[cfg_attr(
+    feature = "uniffi",
+    uniffi::export
+)]
+impl PresenceChanges {
+    /// Wait for next [PresenceChange].
+    pub async fn change(&self) -> Result<PresenceChange, PresenceChangesError> {
+        self.receiver
+            .lock()
+            .await
+            .recv()
+            .await
+            .map_err(From::from)
+    }
+}
+
+/// Errors when calling [PresenceChanges::change].
+#[derive(Debug, thiserror::Error)]
+#[cfg_attr(
+    feature = "uniffi",
+    derive(uniffi::Error)
+)]
+pub enum PresenceChangesError {
+    /// There are no more changes because the [Presence] was dropped or its [Channel] shutdown.
+    #[error("No more presence changes left")]
+    NoMoreChanges,
+    /// [PresenceChanges::change] wasn't called often enough and some [PresenceChange]s were
+    /// dropped.  Use [Presence::list] to get the current presences.
+    #[error("Missed {missed_change_count} presence changes; jumping to next change")]
+    MissedChanges {
+        /// How many [PresenceChange]s were missed.
+        missed_change_count: u64,
+    },
+}
+impl From<broadcast::error::RecvError> for PresenceChangesError {
+    fn from(recv_error: broadcast::error::RecvError) -> Self {
+        match recv_error {
+            broadcast::error::RecvError::Closed => Self::NoMoreChanges,
+            broadcast::error::RecvError::Lagged(missed_change_count) => Self::MissedChanges {
+                missed_change_count,
+            },
+        }
+    }
+}
+
+/// Called with [PresenceChange]s, as registered with [Presence::add_change_listener].
+#[cfg_attr(feature = "uniffi", uniffi::export(callback_interface))]
+pub trait PresenceListener: Send + Sync {
+    /// The next [PresenceChange].
+    fn on_change(&self, change: PresenceChange);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::testing::{json_payload, FakeServer, TIMEOUT};
+
+    #[tokio::test]
+    async fn presence_syncs_state_and_diffs() {
+        let server = FakeServer::new();
+        let (socket, channel) = server.joined_channel("room:lobby").await;
+        let presence = Presence::new(channel.clone());
+        let changes = presence.changes();
+
+        // A diff before the state is applied after it.
+        server.push(
+            "room:lobby",
+            DIFF_EVENT,
+            json_payload(json!({
+                "joins": { "u2": { "metas": [{ "phx_ref": "2" }] } },
+                "leaves": {}
+            })),
+        );
+        server.push(
+            "room:lobby",
+            STATE_EVENT,
+            json_payload(json!({ "u1": { "metas": [{ "phx_ref": "1", "device": "phone" }] } })),
+        );
+
+        loop {
+            if changes.change().await.unwrap() == PresenceChange::Sync {
+                break;
+            }
+        }
+
+        let list = presence.list();
+        assert_eq!(
+            list.iter().map(|entry| entry.key.as_str()).collect::<Vec<_>>(),
+            vec!["u1", "u2"]
+        );
+        assert_eq!(
+            presence.get("u1".to_string()).unwrap().metas[0].phx_ref,
+            Some("1".to_string())
+        );
+
+        server.push(
+            "room:lobby",
+            DIFF_EVENT,
+            json_payload(json!({
+                "joins": {},
+                "leaves": { "u1": { "metas": [{ "phx_ref": "1" }] } }
+            })),
+        );
+
+        match changes.change().await.unwrap() {
+            PresenceChange::Leave {
+                key,
+                remaining_metas,
+                ..
+            } => {
+                assert_eq!(key, "u1");
+                assert!(remaining_metas.is_empty());
+            }
+            other => panic!("expected leave, got {:?}", other),
+        }
+        assert_eq!(changes.change().await.unwrap(), PresenceChange::Sync);
+        assert!(presence.get("u1".to_string()).is_none());
+
+        drop(presence);
+        assert!(matches!(
+            changes.change().await,
+            Err(PresenceChangesError::NoMoreChanges)
+        ));
+
+        socket.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn diffs_before_state_are_held_back_after_rejoin() {
+        let server = FakeServer::new();
+        let (socket, channel) = server.joined_channel("room:lobby").await;
+        let presence = Presence::new(channel.clone());
+        let changes = presence.changes();
+
+        server.push(
+            "room:lobby",
+            STATE_EVENT,
+            json_payload(json!({ "u1": { "metas": [{ "phx_ref": "1" }] } })),
+        );
+
+        loop {
+            if changes.change().await.unwrap() == PresenceChange::Sync {
+                break;
+            }
+        }
+
+        server.close("room:lobby");
+
+        tokio::time::timeout(TIMEOUT, async {
+            while !server.is_joined("room:lobby") {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+
+        // After the rejoin, the diff is relative to the new state, so it is applied after it
+        // instead of joining and then leaving u2.
+        server.push(
+            "room:lobby",
+            DIFF_EVENT,
+            json_payload(json!({
+                "joins": { "u2": { "metas": [{ "phx_ref": "2" }] } },
+                "leaves": {}
+            })),
+        );
+        server.push(
+            "room:lobby",
+            STATE_EVENT,
+            json_payload(json!({
+                "u1": { "metas": [{ "phx_ref": "1" }] },
+                "u3": { "metas": [{ "phx_ref": "3" }] }
+            })),
+        );
+
+        let mut joined_keys = Vec::new();
+        loop {
+            match changes.change().await.unwrap() {
+                PresenceChange::Join { key, .. } => joined_keys.push(key),
+                PresenceChange::Leave { key, .. } => panic!("{} left", key),
+                PresenceChange::Sync => break,
+            }
+        }
+
+        assert_eq!(joined_keys, vec!["u3", "u2"]);
+        assert_eq!(
+            presence
+                .list()
+                .iter()
+                .map(|entry| entry.key.as_str())
+                .collect::<Vec<_>>(),
+            vec!["u1", "u2", "u3"]
+        );
+
+        socket.shutdown().await.unwrap();
+    }
+}
@@ -0,0 +1,43 @@
+//! The wire format a [Socket](crate::Socket) encodes and decodes messages with, which must match
+//! the serializer configured for the endpoint's socket on the server.
+//!
+//! ```
+//! # use phoenix_channels_client::{SerializerVersion, SocketOptions};
+//! #
+//! // Endpoints that predate `Phoenix.Socket.V2.JSONSerializer` only accept `vsn=1.0.0`.
+//! let options = SocketOptions::default().with_serializer_version(SerializerVersion::V1);
+//! ```
+
+/// The version of the Phoenix JSON serializer, sent as the `vsn` param when connecting.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "uniffi",
+    derive(uniffi::Enum)
+)]
+pub enum SerializerVersion {
+    /// `Phoenix.Socket.V1.JSONSerializer` (`vsn=1.0.0`), which encodes each message as a JSON
+    /// object with `topic`, `event`, `payload`, `ref` and `join_ref` keys.  It has no binary
+    /// format, so binary [Payload](crate::Payload)s are rejected.
+    V1,
+    /// `Phoenix.Socket.V2.JSONSerializer` (`vsn=2.0.0`), which encodes each message as a JSON
+    /// array and binary [Payload](crate::Payload)s in its binary format.
+    #[default]
+    V2,
+}
+impl SerializerVersion {
+    /// The `vsn` param for this version.
+    pub(crate) fn vsn(&self) -> &'static str {
+        match self {
+            Self::V1 => "1.0.0",
+            Self::V2 => "2.0.0",
+        }
+    }
+
+    /// Whether binary [Payload](crate::Payload)s can be encoded.
+    pub(crate) fn supports_binary_payloads(&self) -> bool {
+        match self {
+            Self::V1 => false,
+            Self::V2 => true,
+        }
+    }
+}
@@ -26,6 +26,32 @@
 //! # }
 //! ```
 //!
+//! [Socket::spawn_with_options] tunes the heartbeat, timeouts and buffer sizes with
+//! [SocketOptions].
+//!
+//! ```
+//! # use std::time::Duration;
+//! #
+//! # use url::Url;
+//! #
+//! # use phoenix_channels_client::{PhoenixError, Socket, SocketOptions};
+//! #
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), PhoenixError> {
+//! # let url = Url::parse_with_params(
+//! #     "ws://127.0.0.1:9002/socket/websocket",
+//! #     &[("shared_secret", "supersecret"), ("id", "user-id")],
+//! # )?;
+//! let socket = Socket::spawn_with_options(
+//!     url,
+//!     SocketOptions::default()
+//!         .with_heartbeat_interval(Duration::from_secs(10))
+//!         .with_channel_event_buffer_size(100),
+//! )?;
+//! # Ok(())
+//! # }
+//! ```
+//!
 //! If the [Socket::spawn] [Url] does not have the correct params for authorization, then it will
 //! pass back the error from [Socket::connect].
 //!
@@ -97,7 +123,7 @@
 //! delete_secret(&id, &secret).await;
 //!
 //! let until = match statuses.status().await? {
-//!     Ok(SocketStatus::WaitingToReconnect { until }) => until,
+//!     Ok(SocketStatus::WaitingToReconnect { until, .. }) => until,
 //!     other => panic!("Didn't wait to reconnect and instead {:?}", other)
 //! };
 //! println!("Will reconnect in {:?}", until.duration_since(SystemTime::now()).unwrap_or_else(|_| Duration::from_micros(0)));
@@ -180,10 +206,17 @@
 //! #     }
 //! # }
 
+pub mod frames;
+pub mod options;
+pub mod params;
+
 use atomic_take::AtomicTake;
+use futures::stream::{self, BoxStream};
+use futures::StreamExt;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, SystemTime};
-use tokio::sync::{mpsc, oneshot};
+use tokio::sync::{broadcast, mpsc, oneshot};
 use tokio::task::JoinHandle;
 use tokio::time;
 use tokio::time::error::Elapsed;
@@ -191,17 +224,29 @@ use tokio::time::Instant;
 use tokio_tungstenite::tungstenite;
 use url::Url;
 
+use crate::ffi::absinthe::{self, Absinthe};
+use crate::ffi::backoff::Backoff;
 use crate::ffi::channel::Channel;
+use crate::ffi::channel::options::{ChannelOptions, EventDelivery};
+use crate::ffi::live_view::{LiveView, LiveViewJoin};
+use crate::ffi::listeners::{ListenerHandle, SocketStatusListener};
 use crate::ffi::message::Payload;
 use crate::ffi::observable_status::StatusesError;
+use crate::ffi::socket::frames::{WireFrame, WireFrames, FRAME_BUFFER_SIZE};
+use crate::ffi::socket::options::SocketOptions;
+use crate::ffi::socket::params::{ConnectParams, DynamicParams, ParamsProvider, ParamsSource};
 use crate::ffi::topic::Topic;
 use crate::ffi::{http, instant_to_system_time, web_socket};
 use crate::rust;
+use crate::rust::message::Broadcast;
 use crate::rust::observable_status;
+use crate::rust::serializer::Serializer;
+use crate::rust::socket::transport::{DefaultConnector, FrameTap, TappedConnector};
+use crate::rust::transport::{Connector, Transport};
 
 use crate::rust::socket::listener::{
     ChannelSendCommand, ChannelSpawn, ChannelStateCommand, Connect, Listener, ObservableStatus,
-    StateCommand,
+    SocketHandles, StateCommand,
 };
 
 /// Errors when calling [Socket] functions.
@@ -248,8 +293,6 @@ pub enum SocketError {
     },
 }
 
-const PHOENIX_SERIALIZER_VSN: &'static str = "2.0.0";
-
 /// A [`Socket`] manages the underlying WebSocket connection used to talk to Phoenix.
 ///
 /// It acts as the primary interface (along with [`Channel`]) for working with Phoenix Channels.
@@ -266,57 +309,243 @@ const PHOENIX_SERIALIZER_VSN: &'static str = "2.0.0";
 )]
 pub struct Socket {
     url: Arc<Url>,
+    pub(crate) options: SocketOptions,
+    /// [SocketOptions::rejoin_backoff] or the `rejoin_backoff` passed to
+    /// [Socket::spawn_with_backoffs].
+    pub(crate) rejoin_backoff: Arc<dyn Backoff>,
+    /// [SocketOptions::serializer_version] or the `serializer` passed to
+    /// [Socket::spawn_with_serializer].
+    pub(crate) serializer: Arc<dyn Serializer>,
+    /// [Socket::set_auth_token] and [Socket::set_params_provider]
+    dynamic_params: Arc<DynamicParams>,
     status: ObservableStatus,
     state_command_tx: mpsc::Sender<StateCommand>,
     channel_spawn_tx: mpsc::Sender<ChannelSpawn>,
     pub(crate) channel_state_command_tx: mpsc::Sender<ChannelStateCommand>,
     pub(crate) channel_send_command_tx: mpsc::Sender<ChannelSendCommand>,
+    /// Broadcasts on topics no channel joined, such as the subscription topics of
+    /// [Absinthe](crate::Absinthe).
+    pub(crate) unrouted_broadcast_tx: broadcast::Sender<Broadcast>,
+    /// Sent each [WireFrame] by the transport for [Socket::frames].
+    frame_tx: broadcast::Sender<Arc<WireFrame>>,
+    /// The next reference given to an [UploadEntry](crate::UploadEntry) by
+    /// [LiveView::allow_upload](crate::LiveView::allow_upload).  Shared by all [LiveView]s on
+    /// this socket, like the page-global counter of `phoenix_live_view.js`, so their `lvu:`
+    /// topics can't collide.
+    next_upload_entry_ref: AtomicU64,
     /// The join handle corresponding to the socket listener
     /// * Some - spawned task has not been joined.
     /// * None - spawned task has been joined once.
     pub(crate) join_handle: AtomicTake<JoinHandle<Result<(), rust::socket::ShutdownError>>>,
 }
 impl Socket {
-    fn spawn_actual(mut url: Url) -> Result<Arc<Self>, SpawnError> {
+    fn spawn_actual(
+        url: Url,
+        options: SocketOptions,
+        serializer: Option<Arc<dyn Serializer>>,
+        reconnect_backoff: Option<Arc<dyn Backoff>>,
+        rejoin_backoff: Option<Arc<dyn Backoff>>,
+    ) -> Result<Arc<Self>, SpawnError> {
         match url.scheme() {
             "wss" | "ws" => (),
             _ => return Err(SpawnError::UnsupportedScheme { url }),
         }
 
+        let serializer =
+            serializer.unwrap_or_else(|| Arc::new(options.serializer_version_or_default()));
+        let (frame_tx, _) = broadcast::channel(FRAME_BUFFER_SIZE);
+        let connector = Box::new(DefaultConnector::new(
+            &options,
+            serializer.clone(),
+            FrameTap::new(frame_tx.clone()),
+        ));
+
+        Self::spawn_with_connector_actual(
+            url,
+            options,
+            serializer,
+            connector,
+            frame_tx,
+            reconnect_backoff,
+            rejoin_backoff,
+        )
+    }
+
+    fn spawn_with_connector_actual<T: Transport>(
+        mut url: Url,
+        options: SocketOptions,
+        serializer: Arc<dyn Serializer>,
+        connector: Box<dyn Connector<Transport = T>>,
+        frame_tx: broadcast::Sender<Arc<WireFrame>>,
+        reconnect_backoff: Option<Arc<dyn Backoff>>,
+        rejoin_backoff: Option<Arc<dyn Backoff>>,
+    ) -> Result<Arc<Self>, SpawnError> {
+        options.validate()?;
+        let upgrade_headers = options.upgrade_headers()?;
+
         // Modify url with given parameters
         {
             let mut query = url.query_pairs_mut();
-            query.append_pair("vsn", PHOENIX_SERIALIZER_VSN);
+            query.append_pair("vsn", serializer.vsn());
         }
 
         let url = Arc::new(url);
+        let reconnect_backoff =
+            reconnect_backoff.unwrap_or_else(|| Arc::new(options.reconnect_backoff_or_default()));
+        let rejoin_backoff =
+            rejoin_backoff.unwrap_or_else(|| Arc::new(options.rejoin_backoff_or_default()));
+        let dynamic_params: Arc<DynamicParams> = Default::default();
         let status = ObservableStatus::new(rust::socket::Status::default());
-        let (channel_spawn_tx, channel_spawn_rx) = mpsc::channel(50);
-        let (state_command_tx, state_command_rx) = mpsc::channel(50);
-        let (channel_state_command_tx, channel_state_command_rx) = mpsc::channel(50);
-        let (channel_send_command_tx, channel_send_command_rx) = mpsc::channel(50);
+        let command_queue_depth = options.socket_command_queue_depth as usize;
+        let (channel_spawn_tx, channel_spawn_rx) = mpsc::channel(command_queue_depth);
+        let (state_command_tx, state_command_rx) = mpsc::channel(command_queue_depth);
+        let (channel_state_command_tx, channel_state_command_rx) =
+            mpsc::channel(command_queue_depth);
+        let (channel_send_command_tx, channel_send_command_rx) =
+            mpsc::channel(command_queue_depth);
+        let (unrouted_broadcast_tx, _) =
+            broadcast::channel(options.channel_event_buffer_size as usize);
         let join_handle = Listener::spawn(
             url.clone(),
-            status.clone(),
-            channel_spawn_rx,
-            state_command_rx,
-            channel_state_command_rx,
-            channel_send_command_rx,
+            connector,
+            upgrade_headers,
+            &options,
+            reconnect_backoff,
+            dynamic_params.clone(),
+            SocketHandles {
+                socket_status: status.clone(),
+                channel_spawn_rx,
+                state_command_rx,
+                channel_state_command_rx,
+                channel_send_command_rx,
+                unrouted_broadcast_tx: unrouted_broadcast_tx.clone(),
+            },
         );
 
         Ok(Arc::new(Self {
             url,
+            options,
+            rejoin_backoff,
+            serializer,
+            dynamic_params,
             status,
             channel_spawn_tx,
             state_command_tx,
             channel_state_command_tx,
             channel_send_command_tx,
+            unrouted_broadcast_tx,
+            frame_tx,
+            next_upload_entry_ref: AtomicU64::new(0),
             join_handle: AtomicTake::new(join_handle),
         }))
     }
+
+    pub(crate) fn next_upload_entry_ref(&self) -> String {
+        self.next_upload_entry_ref
+            .fetch_add(1, Ordering::SeqCst)
+            .to_string()
+    }
+
+    /// Spawns a new [Socket] tuned by `options` that must be [Socket::connect]ed, but connects
+    /// with `connector` instead of web sockets, so `url` can have any scheme.
+    ///
+    /// [SocketOptions::subprotocols] and [SocketOptions::long_poll_fallback_after] are only used
+    /// by the built-in web socket transport, so `connector` has to handle them itself.
+    /// [Socket::frames] are the [Message](crate::transport::Message)s sent and received over
+    /// `connector`'s transports, as [SocketOptions::serializer_version] encodes them.
+    pub fn spawn_with_connector<C: Connector>(
+        url: Url,
+        options: SocketOptions,
+        connector: C,
+    ) -> Result<Arc<Self>, SpawnError> {
+        let serializer: Arc<dyn Serializer> = Arc::new(options.serializer_version_or_default());
+        let (frame_tx, _) = broadcast::channel(FRAME_BUFFER_SIZE);
+        let connector = Box::new(TappedConnector::new(
+            connector,
+            serializer.clone(),
+            FrameTap::new(frame_tx.clone()),
+        ));
+
+        Self::spawn_with_connector_actual(
+            url,
+            options,
+            serializer,
+            connector,
+            frame_tx,
+            None,
+            None,
+        )
+    }
+
+    /// Spawns a new [Socket] tuned by `options` that must be [Socket::connect]ed, but encodes and
+    /// decodes frames with `serializer` instead of [SocketOptions::serializer_version], which is
+    /// ignored.
+    pub fn spawn_with_serializer<S: Serializer>(
+        url: Url,
+        options: SocketOptions,
+        serializer: S,
+    ) -> Result<Arc<Self>, SpawnError> {
+        Self::spawn_actual(url, options, Some(Arc::new(serializer)), None, None)
+    }
+
+    /// Sets an async closure called for fresh [ConnectParams] before each [Socket::connect] and
+    /// automatic reconnect, replacing any earlier provider.  Use [Socket::set_params_provider]
+    /// with [None] to remove it.
+    ///
+    /// ```
+    /// # use std::collections::HashMap;
+    /// #
+    /// # use url::Url;
+    /// #
+    /// # use phoenix_channels_client::{ConnectParams, PhoenixError, Socket};
+    /// #
+    /// # async fn fetch_secret() -> String { "secret".to_string() }
+    /// #
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), PhoenixError> {
+    /// # let url = Url::parse("ws://127.0.0.1:9002/socket/websocket")?;
+    /// let socket = Socket::spawn(url)?;
+    /// socket.set_async_params_provider(|| async {
+    ///     ConnectParams {
+    ///         query_params: Some(HashMap::from([("secret".to_string(), fetch_secret().await)])),
+    ///         headers: None,
+    ///     }
+    /// });
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_async_params_provider<F, Fut>(&self, params_provider: F)
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: std::future::Future<Output = ConnectParams> + Send + 'static,
+    {
+        self.dynamic_params
+            .params_source
+            .store(Some(Arc::new(ParamsSource::from_async(params_provider))));
+    }
+
     #[cfg(not(feature = "uniffi"))]
     pub fn spawn(url: Url) -> Result<Arc<Self>, SpawnError> {
-        Self::spawn_actual(url)
+        Self::spawn_actual(url, SocketOptions::default(), None, None, None)
+    }
+    #[cfg(not(feature = "uniffi"))]
+    pub fn spawn_with_options(url: Url, options: SocketOptions) -> Result<Arc<Self>, SpawnError> {
+        Self::spawn_actual(url, options, None, None, None)
+    }
+    #[cfg(not(feature = "uniffi"))]
+    pub fn spawn_with_backoffs(
+        url: Url,
+        options: SocketOptions,
+        reconnect_backoff: Option<Box<dyn Backoff>>,
+        rejoin_backoff: Option<Box<dyn Backoff>>,
+    ) -> Result<Arc<Self>, SpawnError> {
+        Self::spawn_actual(
+            url,
+            options,
+            None,
+            reconnect_backoff.map(Arc::from),
+            rejoin_backoff.map(Arc::from),
+        )
     }
 }
 #[cfg_attr(
@@ -328,7 +557,34 @@ impl Socket {
     #[cfg(feature = "uniffi")]
     #[uniffi::constructor]
     pub fn spawn(url: Url) -> Result<Arc<Self>, SpawnError> {
-        Self::spawn_actual(url)
+        Self::spawn_actual(url, SocketOptions::default(), None, None, None)
+    }
+
+    /// Spawns a new [Socket] tuned by `options` that must be [Socket::connect]ed.
+    #[cfg(feature = "uniffi")]
+    #[uniffi::constructor]
+    pub fn spawn_with_options(url: Url, options: SocketOptions) -> Result<Arc<Self>, SpawnError> {
+        Self::spawn_actual(url, options, None, None, None)
+    }
+
+    /// Spawns a new [Socket] tuned by `options` that must be [Socket::connect]ed, but with custom
+    /// [Backoff]s in place of [SocketOptions::reconnect_backoff] and
+    /// [SocketOptions::rejoin_backoff] when they are [Some].
+    #[cfg(feature = "uniffi")]
+    #[uniffi::constructor]
+    pub fn spawn_with_backoffs(
+        url: Url,
+        options: SocketOptions,
+        reconnect_backoff: Option<Box<dyn Backoff>>,
+        rejoin_backoff: Option<Box<dyn Backoff>>,
+    ) -> Result<Arc<Self>, SpawnError> {
+        Self::spawn_actual(
+            url,
+            options,
+            None,
+            reconnect_backoff.map(Arc::from),
+            rejoin_backoff.map(Arc::from),
+        )
     }
 
     /// The `url` passed to [Socket::spawn]
@@ -336,6 +592,34 @@ impl Socket {
         (*self.url).clone()
     }
 
+    /// The `options` passed to [Socket::spawn_with_options] or the defaults for [Socket::spawn].
+    pub fn options(&self) -> SocketOptions {
+        self.options.clone()
+    }
+
+    /// Sets the token sent to the server in the `Sec-WebSocket-Protocol` header, the same as
+    /// `authToken` in `phoenix.js`, so it doesn't have to be a [Url] param.  The token is read for
+    /// each [Socket::connect] and automatic reconnect, so refreshing it before it expires keeps
+    /// reconnects authorized.
+    ///
+    /// * [None] - stop sending a token.
+    pub fn set_auth_token(&self, auth_token: Option<String>) {
+        self.dynamic_params
+            .auth_token
+            .store(auth_token.map(Arc::new));
+    }
+
+    /// Sets the [ParamsProvider] called for fresh [ConnectParams] before each [Socket::connect] and
+    /// automatic reconnect, replacing any earlier provider.
+    ///
+    /// * [None] - stop calling a provider, so only the [Url] params and [SocketOptions::headers]
+    ///   are sent.
+    pub fn set_params_provider(&self, params_provider: Option<Box<dyn ParamsProvider>>) {
+        self.dynamic_params.params_source.store(
+            params_provider.map(|provider| Arc::new(ParamsSource::Callback(provider.into()))),
+        );
+    }
+
     /// The current [SocketStatus].
     ///
     /// Use [Socket::status] to receive changes to the status.
@@ -350,6 +634,38 @@ impl Socket {
         Arc::new(self.status.subscribe().into())
     }
 
+    /// Broadcasts each [WireFrame] sent or received from now on, including heartbeats and frames
+    /// that could not be decoded, to debug the wire protocol without `log` output.
+    ///
+    /// A [Socket] spawned with [Socket::spawn_with_connector] has no ping, pong or close frames,
+    /// only a frame for each [Message](crate::transport::Message) its transport sends or receives.
+    pub fn frames(&self) -> Arc<WireFrames> {
+        Arc::new(self.frame_tx.subscribe().into())
+    }
+
+    /// Calls `listener` with [Socket::status] changes until the returned [ListenerHandle] is
+    /// removed or this socket shuts down.
+    pub fn add_status_listener(
+        &self,
+        listener: Box<dyn SocketStatusListener>,
+    ) -> Arc<ListenerHandle> {
+        let status = self.status.clone();
+        let statuses = self.statuses();
+
+        ListenerHandle::spawn(async move {
+            loop {
+                match statuses.status().await {
+                    Ok(Ok(socket_status)) => listener.on_status(socket_status),
+                    Ok(Err(web_socket_error)) => listener.on_error(web_socket_error),
+                    Err(StatusesError::MissedStatuses { .. }) => {
+                        listener.on_status(status.get().into())
+                    }
+                    Err(StatusesError::NoMoreStatuses) => break,
+                }
+            }
+        })
+    }
+
     /// Connects this client to the configured Phoenix Channels endpoint
     ///
     /// This function must be called before using the client to join channels, etc.
@@ -417,6 +733,20 @@ impl Socket {
         topic: Arc<Topic>,
         payload: Option<Payload>,
     ) -> Result<Arc<Channel>, SocketChannelError> {
+        self.channel_with_options(topic, payload, ChannelOptions::default())
+            .await
+    }
+
+    /// Creates a new, unjoined Phoenix Channel tuned by `options`, such as to buffer more events
+    /// or to not drop events for slow [Events](crate::Events) receivers.
+    pub async fn channel_with_options(
+        self: &Arc<Self>,
+        topic: Arc<Topic>,
+        payload: Option<Payload>,
+        options: ChannelOptions,
+    ) -> Result<Arc<Channel>, SocketChannelError> {
+        options.validate()?;
+
         let (sender, receiver) = oneshot::channel();
 
         match self
@@ -425,6 +755,7 @@ impl Socket {
                 socket: self.clone(),
                 topic,
                 payload: payload.map(From::from),
+                options,
                 sender,
             })
             .await
@@ -436,6 +767,34 @@ impl Socket {
             Err(_) => Err(self.listener_shutdown().await.unwrap_err().into()),
         }
     }
+
+    /// Creates a new, unjoined [LiveView] on the topic `lv:{id}` for the LiveView rendered in the
+    /// page at [LiveViewJoin::url].  Its [Channel] never drops events, as a missed diff would
+    /// leave the render wrong until the next rejoin.
+    pub async fn live_view(
+        self: &Arc<Self>,
+        join: LiveViewJoin,
+    ) -> Result<Arc<LiveView>, SocketChannelError> {
+        let channel = self
+            .channel_with_options(
+                Topic::from_string(join.topic()),
+                None,
+                ChannelOptions::default().with_event_delivery(EventDelivery::Unbounded),
+            )
+            .await?;
+
+        Ok(LiveView::new(self.clone(), channel, join))
+    }
+
+    /// Creates a new, unjoined [Absinthe] on the topic `__absinthe__:control` for GraphQL
+    /// subscriptions served by `Absinthe.Phoenix`.
+    pub async fn absinthe(self: &Arc<Self>) -> Result<Arc<Absinthe>, SocketChannelError> {
+        let channel = self
+            .channel(Topic::from_string(absinthe::CONTROL_TOPIC.to_string()), None)
+            .await?;
+
+        Ok(Absinthe::new(self, channel))
+    }
 }
 
 /// The status of the [Socket].
@@ -454,6 +813,15 @@ pub enum SocketStatus {
     WaitingToReconnect {
         /// When the [Socket] will automatically [Socket::connect] next.
         until: SystemTime,
+        /// Why the [Socket] is not connected.
+        reason: ReconnectReason,
+    },
+    /// The [Socket] was disconnected, but [SocketOptions::max_reconnect_attempts] automatic
+    /// reconnects all failed, so the [Socket] will stay disconnected until [Socket::connect] is
+    /// called.
+    ReconnectAttemptsExhausted {
+        /// Why the [Socket] was not connected.
+        reason: ReconnectReason,
     },
     /// [Socket::disconnect] was called and the server responded that the socket as disconnected.
     Disconnected,
@@ -467,9 +835,13 @@ impl From<rust::socket::Status> for SocketStatus {
         match rust_status {
             rust::socket::Status::NeverConnected => Self::NeverConnected,
             rust::socket::Status::Connected => Self::Connected,
-            rust::socket::Status::WaitingToReconnect(until) => Self::WaitingToReconnect {
+            rust::socket::Status::WaitingToReconnect(until, reason) => Self::WaitingToReconnect {
                 until: instant_to_system_time(until),
+                reason,
             },
+            rust::socket::Status::ReconnectAttemptsExhausted(reason) => {
+                Self::ReconnectAttemptsExhausted { reason }
+            }
             rust::socket::Status::Disconnected => Self::Disconnected,
             rust::socket::Status::ShuttingDown => Self::ShuttingDown,
             rust::socket::Status::ShutDown => Self::ShutDown,
@@ -477,6 +849,24 @@ impl From<rust::socket::Status> for SocketStatus {
     }
 }
 
+/// Why the [Socket] is [SocketStatus::WaitingToReconnect] or
+/// [SocketStatus::ReconnectAttemptsExhausted].
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "uniffi",
+    derive(uniffi::Enum)
+)]
+pub enum ReconnectReason {
+    /// [Socket::connect] could not connect to the server.
+    ConnectFailed,
+    /// The server closed the connection or the connection errored.
+    ConnectionLost,
+    /// The server did not reply to a heartbeat for
+    /// [SocketOptions::max_missed_heartbeats] heartbeat intervals, so the connection was assumed
+    /// dead and closed.
+    HeartbeatTimeout,
+}
+
 /// A wrapper anound `observable_status::Statuses` because `uniffi` does not support generics
 #[cfg_attr(
     feature = "uniffi",
@@ -498,6 +888,29 @@ impl SocketStatuses {
         })
     }
 }
+// Rust-only, as streams can't be exported with `uniffi`
+impl SocketStatuses {
+    /// Converts into a [Stream](futures::Stream) of [SocketStatuses::status] results for use with
+    /// [StreamExt] and `select!`.
+    ///
+    /// The stream ends where [SocketStatuses::status] would return
+    /// [StatusesError::NoMoreStatuses], so it only yields [StatusesError::MissedStatuses], after
+    /// which it continues with the next status.
+    pub fn into_stream(
+        self: Arc<Self>,
+    ) -> BoxStream<
+        'static,
+        Result<Result<SocketStatus, web_socket::error::WebSocketError>, StatusesError>,
+    > {
+        stream::unfold(self, |statuses| async move {
+            match statuses.status().await {
+                Err(StatusesError::NoMoreStatuses) => None,
+                result => Some((result, statuses)),
+            }
+        })
+        .boxed()
+    }
+}
 impl From<observable_status::Statuses<rust::socket::Status, Arc<tungstenite::Error>>>
     for SocketStatuses
 {
@@ -519,6 +932,12 @@ pub enum SpawnError {
     /// Occurs when the configured url's scheme is not ws or wss.
     #[error("Unsupported scheme in url ({url}). Supported schemes are ws and wss.")]
     UnsupportedScheme { url: Url },
+    /// Occurs when an option passed to [Socket::spawn_with_options] is out of range.
+    #[error("Invalid socket option ({option}): {reason}")]
+    InvalidOption {
+        option: &'static str,
+        reason: &'static str,
+    },
 }
 
 /// Errors from [Socket::connect].
@@ -588,7 +1007,7 @@ impl From<rust::socket::ShutdownError> for ConnectError {
     }
 }
 
-/// Errors when calling [Socket::channel]
+/// Errors when calling [Socket::channel] or [Socket::channel_with_options]
 #[derive(Debug, thiserror::Error)]
 #[cfg_attr(
     feature = "uniffi",
@@ -597,6 +1016,14 @@ impl From<rust::socket::ShutdownError> for ConnectError {
 pub enum SocketChannelError {
     #[error("socket shutdown: {shutdown_error}")]
     Shutdown { shutdown_error: SocketShutdownError },
+    /// A [ChannelOptions] field has an invalid value.
+    #[error("invalid channel option {option}: {reason}")]
+    InvalidOption {
+        /// The name of the [ChannelOptions] field.
+        option: String,
+        /// Why the value is invalid.
+        reason: String,
+    },
 }
 impl From<rust::socket::ShutdownError> for SocketChannelError {
     fn from(rust_shutdown_error: rust::socket::ShutdownError) -> Self {
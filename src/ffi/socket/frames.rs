@@ -0,0 +1,394 @@
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use futures::stream::{self, BoxStream};
+use futures::StreamExt;
+use tokio::sync::broadcast;
+
+use crate::ffi::message::{Event, Payload};
+use crate::ffi::web_socket::protocol::WebSocketMessage;
+use crate::rust::message::{Message, MessageDecodingError};
+
+/// How many [WireFrame]s each [WireFrames] buffers before it starts missing frames.
+pub(crate) const FRAME_BUFFER_SIZE: usize = 1000;
+
+/// Whether a [WireFrame] was sent or received.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "uniffi",
+    derive(uniffi::Enum)
+)]
+pub enum WireFrameDirection {
+    /// Received from the server.
+    Inbound,
+    /// Sent to the server.
+    Outbound,
+}
+
+/// A frame sent or received by the transport of a [Socket](crate::Socket), as observed with
+/// [Socket::frames](crate::Socket::frames).
+#[derive(Debug)]
+#[cfg_attr(
+    feature = "uniffi",
+    derive(uniffi::Object)
+)]
+pub struct WireFrame {
+    direction: WireFrameDirection,
+    timestamp: SystemTime,
+    /// * [None] - a ping, pong or close frame, which have no [Message].
+    message: Option<Result<Message, MessageDecodingError>>,
+    web_socket_message: WebSocketMessage,
+}
+#[cfg_attr(
+    feature = "uniffi",
+    uniffi::export
+)]
+impl WireFrame {
+    /// Whether the frame was sent or received.
+    pub fn direction(&self) -> WireFrameDirection {
+        self.direction
+    }
+
+    /// When the frame was sent or received.
+    pub fn timestamp(&self) -> SystemTime {
+        self.timestamp
+    }
+
+    /// The frame as sent or received.
+    pub fn web_socket_message(&self) -> WebSocketMessage {
+        self.web_socket_message.clone()
+    }
+
+    /// The topic of the message in the frame, which is `phoenix` for heartbeats.
+    ///
+    /// * [None] - the frame has no message or it could not be decoded.
+    pub fn topic(&self) -> Option<String> {
+        self.decoded().map(|message| match message {
+            Message::Control(_) => "phoenix".to_string(),
+            Message::Broadcast(broadcast) => broadcast.topic.to_string(),
+            Message::Reply(reply) => reply.topic.to_string(),
+            Message::Push(push) => push.topic.to_string(),
+        })
+    }
+
+    /// The event of the message in the frame.
+    ///
+    /// * [None] - the frame has no message or it could not be decoded.
+    pub fn event(&self) -> Option<Event> {
+        self.decoded().map(|message| {
+            match message {
+                Message::Control(control) => control.event.clone(),
+                Message::Broadcast(broadcast) => broadcast.event_payload.event.clone(),
+                Message::Reply(reply) => reply.event.clone(),
+                Message::Push(push) => push.event_payload.event.clone(),
+            }
+            .into()
+        })
+    }
+
+    /// The payload of the message in the frame, which for replies is the response without the
+    /// status.
+    ///
+    /// * [None] - the frame has no message or it could not be decoded.
+    pub fn payload(&self) -> Option<Payload> {
+        self.decoded().map(|message| message.payload().into())
+    }
+
+    /// The join reference of the message in the frame.
+    ///
+    /// * [None] - the frame has no message, it could not be decoded, or the message is a
+    ///   heartbeat or broadcast.
+    pub fn join_reference(&self) -> Option<String> {
+        match self.decoded()? {
+            Message::Control(_) | Message::Broadcast(_) => None,
+            Message::Reply(reply) => Some(reply.join_reference.to_string()),
+            Message::Push(push) => Some(push.join_reference.to_string()),
+        }
+    }
+
+    /// The reference of the message in the frame, which matches a call with its reply.
+    ///
+    /// * [None] - the frame has no message, it could not be decoded, or the message is not
+    ///   replied to.
+    pub fn reference(&self) -> Option<String> {
+        match self.decoded()? {
+            Message::Control(control) => control.reference.as_ref().map(ToString::to_string),
+            Message::Broadcast(_) => None,
+            Message::Reply(reply) => Some(reply.reference.to_string()),
+            Message::Push(push) => push.reference.as_ref().map(ToString::to_string),
+        }
+    }
+
+    /// Why the received frame could not be decoded into a message, in which case the
+    /// [Socket](crate::Socket) dropped it.
+    pub fn decode_error(&self) -> Option<String> {
+        match &self.message {
+            Some(Err(decoding_error)) => Some(decoding_error.to_string()),
+            _ => None,
+        }
+    }
+}
+// Rust-only, as `Message` can't be exported with `uniffi`
+impl WireFrame {
+    /// The [Message] in the frame, or why it could not be decoded.
+    ///
+    /// * [None] - a ping, pong or close frame, which have no [Message].
+    pub fn message(&self) -> Option<&Result<Message, MessageDecodingError>> {
+        self.message.as_ref()
+    }
+}
+impl WireFrame {
+    pub(crate) fn new(
+        direction: WireFrameDirection,
+        message: Option<Result<Message, MessageDecodingError>>,
+        web_socket_message: WebSocketMessage,
+    ) -> Arc<Self> {
+        Arc::new(Self {
+            direction,
+            timestamp: SystemTime::now(),
+            message,
+            web_socket_message,
+        })
+    }
+
+    fn decoded(&self) -> Option<&Message> {
+        self.message.as_ref()?.as_ref().ok()
+    }
+}
+
+/// Waits for [WireFrame]s from [Socket::frames](crate::Socket::frames).
+#[cfg_attr(
+    feature = "uniffi",
+    derive(uniffi::Object)
+)]
+pub struct WireFrames {
+    receiver: tokio::sync::Mutex<broadcast::Receiver<Arc<WireFrame>>>,
+}
+#[cfg_attr(
+    feature = "uniffi",
+    uniffi::export
+)]
+impl WireFrames {
+    /// Wait for next [WireFrame].
+    pub async fn frame(&self) -> Result<Arc<WireFrame>, WireFramesError> {
+        self.receiver.lock().await.recv().await.map_err(From::from)
+    }
+}
+// Rust-only, as streams can't be exported with `uniffi`
+impl WireFrames {
+    /// Converts into a [Stream](futures::Stream) of [WireFrames::frame] results for use with
+    /// [StreamExt] and `select!`.
+    ///
+    /// The stream ends where [WireFrames::frame] would return [WireFramesError::NoMoreFrames], so
+    /// it only yields [WireFramesError::MissedFrames], after which it continues with the next
+    /// [WireFrame].
+    pub fn into_stream(
+        self: Arc<Self>,
+    ) -> BoxStream<'static, Result<Arc<WireFrame>, WireFramesError>> {
+        stream::unfold(self, |frames| async move {
+            match frames.frame().await {
+                Err(WireFramesError::NoMoreFrames) => None,
+                result => Some((result, frames)),
+            }
+        })
+        .boxed()
+    }
+}
+impl From<broadcast::Receiver<Arc<WireFrame>>> for WireFrames {
+    fn from(receiver: broadcast::Receiver<Arc<WireFrame>>) -> Self {
+        Self {
+            receiver: tokio::sync::Mutex::new(receiver),
+        }
+    }
+}
+
+/// Errors when calling [WireFrames::frame].
+#[derive(Debug, thiserror::Error)]
+#[cfg_attr(
+    feature = "uniffi",
+    derive(uniffi::Error)
+)]
+pub enum WireFramesError {
+    /// There are no more frames because the [Socket](crate::Socket) was dropped.
+    #[error("No more frames left")]
+    NoMoreFrames,
+    /// [WireFrames::frame] wasn't called often enough and some [WireFrame]s were dropped to not
+    /// block the [Socket](crate::Socket).
+    #[error("Missed {missed_frame_count} frames; jumping to next frame")]
+    MissedFrames {
+        /// How many [WireFrame]s were missed.
+        missed_frame_count: u64,
+    },
+}
+impl From<broadcast::error::RecvError> for WireFramesError {
+    fn from(recv_error: broadcast::error::RecvError) -> Self {
+        match recv_error {
+            broadcast::error::RecvError::Closed => Self::NoMoreFrames,
+            broadcast::error::RecvError::Lagged(missed_frame_count) => {
+                Self::MissedFrames { missed_frame_count }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::SinkExt;
+    use serde_json::{json, Value};
+    use tokio::net::TcpListener;
+    use tokio_tungstenite::tungstenite;
+    use url::Url;
+
+    use super::*;
+    use crate::testing::{FakeServer, TIMEOUT};
+    use crate::{PhoenixEvent, Socket, Topic};
+
+    async fn next_frame(frames: &WireFrames) -> Arc<WireFrame> {
+        tokio::time::timeout(TIMEOUT, frames.frame())
+            .await
+            .unwrap()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn frames_are_tapped_in_both_directions() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = Url::parse(&format!(
+            "ws://{}/socket/websocket",
+            listener.local_addr().unwrap()
+        ))
+        .unwrap();
+
+        // Replies to the join, then sends a frame that can't be decoded and a ping
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut web_socket = tokio_tungstenite::accept_async(stream).await.unwrap();
+
+            let join = match web_socket.next().await.unwrap().unwrap() {
+                tungstenite::Message::Text(text) => serde_json::from_str::<Value>(&text).unwrap(),
+                other => panic!("expected text, got {:?}", other),
+            };
+            let reply = json!([
+                join[0],
+                join[1],
+                join[2],
+                "phx_reply",
+                { "status": "ok", "response": {} }
+            ]);
+            web_socket
+                .send(tungstenite::Message::Text(reply.to_string()))
+                .await
+                .unwrap();
+            web_socket
+                .send(tungstenite::Message::Text("not json".to_string()))
+                .await
+                .unwrap();
+            web_socket
+                .send(tungstenite::Message::Ping(vec![1]))
+                .await
+                .unwrap();
+
+            while let Some(Ok(_)) = web_socket.next().await {}
+        });
+
+        let socket = Socket::spawn(url).unwrap();
+        let frames = socket.frames();
+        socket.connect(TIMEOUT).await.unwrap();
+        let channel = socket
+            .channel(Topic::from_string("room:lobby".to_string()), None)
+            .await
+            .unwrap();
+        channel.join(TIMEOUT).await.unwrap();
+
+        let join = next_frame(&frames).await;
+        assert_eq!(join.direction(), WireFrameDirection::Outbound);
+        assert_eq!(join.topic(), Some("room:lobby".to_string()));
+        assert_eq!(
+            join.event(),
+            Some(Event::Phoenix {
+                phoenix: PhoenixEvent::Join
+            })
+        );
+        assert!(matches!(join.message(), Some(Ok(Message::Push(_)))));
+        assert!(matches!(
+            join.web_socket_message(),
+            WebSocketMessage::Text { .. }
+        ));
+
+        let reply = next_frame(&frames).await;
+        assert_eq!(reply.direction(), WireFrameDirection::Inbound);
+        assert!(matches!(reply.message(), Some(Ok(Message::Reply(_)))));
+        assert_eq!(reply.reference(), join.reference());
+        assert_eq!(reply.join_reference(), join.join_reference());
+
+        let invalid = next_frame(&frames).await;
+        assert_eq!(invalid.direction(), WireFrameDirection::Inbound);
+        assert!(invalid.decode_error().is_some());
+        assert_eq!(
+            invalid.web_socket_message(),
+            WebSocketMessage::Text {
+                text: "not json".to_string()
+            }
+        );
+
+        let ping = next_frame(&frames).await;
+        assert_eq!(ping.direction(), WireFrameDirection::Inbound);
+        assert!(ping.message().is_none());
+        assert_eq!(
+            ping.web_socket_message(),
+            WebSocketMessage::Ping { bytes: vec![1] }
+        );
+
+        let pong = next_frame(&frames).await;
+        assert_eq!(pong.direction(), WireFrameDirection::Outbound);
+        assert_eq!(
+            pong.web_socket_message(),
+            WebSocketMessage::Pong { bytes: vec![1] }
+        );
+
+        socket.disconnect().await.unwrap();
+
+        let close = loop {
+            let frame = next_frame(&frames).await;
+
+            if frame.message().is_none() {
+                break frame;
+            }
+        };
+        assert_eq!(close.direction(), WireFrameDirection::Outbound);
+        assert_eq!(
+            close.web_socket_message(),
+            WebSocketMessage::Close { close_frame: None }
+        );
+
+        socket.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn frames_are_tapped_for_connector_transports() {
+        let server = FakeServer::new();
+        let socket = server.socket().unwrap();
+        let frames = socket.frames();
+        socket.connect(TIMEOUT).await.unwrap();
+        let channel = socket
+            .channel(Topic::from_string("room:lobby".to_string()), None)
+            .await
+            .unwrap();
+        channel.join(TIMEOUT).await.unwrap();
+
+        let join = next_frame(&frames).await;
+        assert_eq!(join.direction(), WireFrameDirection::Outbound);
+        assert_eq!(join.topic(), Some("room:lobby".to_string()));
+        assert!(matches!(
+            join.web_socket_message(),
+            WebSocketMessage::Text { .. }
+        ));
+
+        let reply = next_frame(&frames).await;
+        assert_eq!(reply.direction(), WireFrameDirection::Inbound);
+        assert!(matches!(reply.message(), Some(Ok(Message::Reply(_)))));
+        assert_eq!(reply.reference(), join.reference());
+
+        socket.shutdown().await.unwrap();
+    }
+}
@@ -0,0 +1,452 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use tokio_tungstenite::tungstenite::http::header::{
+    CONNECTION, HOST, SEC_WEBSOCKET_KEY, SEC_WEBSOCKET_PROTOCOL, SEC_WEBSOCKET_VERSION, UPGRADE,
+};
+use tokio_tungstenite::tungstenite::http::{HeaderMap, HeaderName, HeaderValue};
+
+use crate::ffi::backoff::BackoffStrategy;
+use crate::ffi::serializer::SerializerVersion;
+use crate::ffi::socket::SpawnError;
+
+/// Tunes a [Socket](crate::Socket) created with
+/// [Socket::spawn_with_options](crate::Socket::spawn_with_options).
+///
+/// Every field has a default, so only the fields that need to differ have to be set.
+///
+/// ```
+/// # use std::time::Duration;
+/// #
+/// # use phoenix_channels_client::{BackoffStrategy, SocketOptions};
+/// #
+/// // Battery-constrained clients can send heartbeats less often and back off longer.
+/// let options = SocketOptions::default()
+///     .with_heartbeat_interval(Duration::from_secs(60))
+///     .with_reconnect_backoff(BackoffStrategy::Multipliers {
+///         multipliers: vec![1, 5, 10, 30],
+///     });
+///
+/// // Gateways in front of the server may require headers on the web socket upgrade request.
+/// let options = SocketOptions::default()
+///     .with_header("Authorization".to_string(), "Bearer secret".to_string())
+///     .with_header("Origin".to_string(), "https://example.com".to_string());
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "uniffi",
+    derive(uniffi::Record)
+)]
+pub struct SocketOptions {
+    /// How often a heartbeat is sent to the server to keep the connection alive.
+    ///
+    /// * [None] - every 30 seconds, the same as `phoenix.js`.
+    #[cfg_attr(feature = "uniffi", uniffi(default = None))]
+    pub heartbeat_interval: Option<Duration>,
+    /// How many heartbeat intervals can pass without a reply to the outstanding heartbeat before
+    /// the connection is assumed dead.  The [Socket](crate::Socket) then closes the connection and
+    /// waits to reconnect with [ReconnectReason::HeartbeatTimeout](crate::ReconnectReason::HeartbeatTimeout).
+    #[cfg_attr(feature = "uniffi", uniffi(default = 1))]
+    pub max_missed_heartbeats: u32,
+    /// How long each automatic reconnect waits for the server to accept the connection.  The
+    /// `timeout` passed to [SocketOptions::reconnect_backoff].
+    ///
+    /// * [None] - reuse the `timeout` passed to the last [Socket::connect](crate::Socket::connect).
+    #[cfg_attr(feature = "uniffi", uniffi(default = None))]
+    pub connect_timeout: Option<Duration>,
+    /// How many commands from the [Socket](crate::Socket) and its [Channel](crate::Channel)s can
+    /// be queued for the [Socket](crate::Socket)'s async task before senders wait.
+    #[cfg_attr(feature = "uniffi", uniffi(default = 50))]
+    pub socket_command_queue_depth: u32,
+    /// How many commands to each [Channel](crate::Channel) can be queued for the
+    /// [Channel](crate::Channel)'s async task before senders wait.
+    #[cfg_attr(feature = "uniffi", uniffi(default = 10))]
+    pub channel_command_queue_depth: u32,
+    /// How many [EventPayload](crate::EventPayload)s each [Events](crate::Events) of a
+    /// [Channel](crate::Channel) buffers before it starts missing events, unless overridden by
+    /// [ChannelOptions::event_buffer_size](crate::ChannelOptions::event_buffer_size).
+    #[cfg_attr(feature = "uniffi", uniffi(default = 10))]
+    pub channel_event_buffer_size: u32,
+    /// How long to sleep before each automatic reconnect attempt.
+    ///
+    /// * [None] - [BackoffStrategy::default].
+    #[cfg_attr(feature = "uniffi", uniffi(default = None))]
+    pub reconnect_backoff: Option<BackoffStrategy>,
+    /// How many automatic reconnect attempts to make before the [Socket](crate::Socket) gives up
+    /// with [SocketStatus::ReconnectAttemptsExhausted](crate::SocketStatus::ReconnectAttemptsExhausted).
+    ///
+    /// * [None] - never give up.
+    #[cfg_attr(feature = "uniffi", uniffi(default = None))]
+    pub max_reconnect_attempts: Option<u32>,
+    /// How long each [Channel](crate::Channel) sleeps before each automatic rejoin attempt.
+    ///
+    /// * [None] - [BackoffStrategy::default].
+    #[cfg_attr(feature = "uniffi", uniffi(default = None))]
+    pub rejoin_backoff: Option<BackoffStrategy>,
+    /// How many automatic rejoin attempts each [Channel](crate::Channel) makes before it gives up
+    /// with [ChannelStatus::RejoinAttemptsExhausted](crate::ChannelStatus::RejoinAttemptsExhausted).
+    ///
+    /// * [None] - never give up.
+    #[cfg_attr(feature = "uniffi", uniffi(default = None))]
+    pub max_rejoin_attempts: Option<u32>,
+    /// Extra HTTP headers, such as `Authorization`, `Cookie`, `User-Agent` or `Origin`, sent with
+    /// the web socket upgrade request.  The headers web sockets use for the handshake itself can't
+    /// be set; use [SocketOptions::subprotocols] for `Sec-WebSocket-Protocol`.
+    ///
+    /// * [None] - no extra headers.
+    #[cfg_attr(feature = "uniffi", uniffi(default = None))]
+    pub headers: Option<HashMap<String, String>>,
+    /// The subprotocols requested in the `Sec-WebSocket-Protocol` header of the web socket upgrade
+    /// request, in order of preference.
+    ///
+    /// * [None] - no `Sec-WebSocket-Protocol` header.
+    #[cfg_attr(feature = "uniffi", uniffi(default = None))]
+    pub subprotocols: Option<Vec<String>>,
+    /// How many web socket upgrades must fail in a row before the [Socket](crate::Socket) falls
+    /// back to `Phoenix.Transports.LongPoll` at the `longpoll` path next to the `websocket` path,
+    /// like `phoenix.js` does, for networks where proxies strip the `Upgrade` header.  Once it
+    /// falls back, the [Socket](crate::Socket) keeps long polling until it is shutdown.  Once a
+    /// web socket upgrade succeeded, the network evidently allows web sockets, so the
+    /// [Socket](crate::Socket) never falls back, however long the server is down.
+    ///
+    /// * [None] - never fall back.
+    /// * `Some(0)` - always long poll.
+    #[cfg_attr(feature = "uniffi", uniffi(default = None))]
+    pub long_poll_fallback_after: Option<u32>,
+    /// The serializer messages are encoded and decoded with, which must match the serializer
+    /// configured for the endpoint's socket.
+    ///
+    /// * [None] - [SerializerVersion::V2].
+    #[cfg_attr(feature = "uniffi", uniffi(default = None))]
+    pub serializer_version: Option<SerializerVersion>,
+}
+impl SocketOptions {
+    const DEFAULT_HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
+
+    /// Sets [SocketOptions::heartbeat_interval].
+    pub fn with_heartbeat_interval(mut self, heartbeat_interval: Duration) -> Self {
+        self.heartbeat_interval = Some(heartbeat_interval);
+        self
+    }
+
+    /// Sets [SocketOptions::max_missed_heartbeats].
+    pub fn with_max_missed_heartbeats(mut self, max_missed_heartbeats: u32) -> Self {
+        self.max_missed_heartbeats = max_missed_heartbeats;
+        self
+    }
+
+    /// Sets [SocketOptions::connect_timeout].
+    pub fn with_connect_timeout(mut self, connect_timeout: Duration) -> Self {
+        self.connect_timeout = Some(connect_timeout);
+        self
+    }
+
+    /// Sets [SocketOptions::socket_command_queue_depth].
+    pub fn with_socket_command_queue_depth(mut self, socket_command_queue_depth: u32) -> Self {
+        self.socket_command_queue_depth = socket_command_queue_depth;
+        self
+    }
+
+    /// Sets [SocketOptions::channel_command_queue_depth].
+    pub fn with_channel_command_queue_depth(mut self, channel_command_queue_depth: u32) -> Self {
+        self.channel_command_queue_depth = channel_command_queue_depth;
+        self
+    }
+
+    /// Sets [SocketOptions::channel_event_buffer_size].
+    pub fn with_channel_event_buffer_size(mut self, channel_event_buffer_size: u32) -> Self {
+        self.channel_event_buffer_size = channel_event_buffer_size;
+        self
+    }
+
+    /// Sets [SocketOptions::reconnect_backoff].
+    pub fn with_reconnect_backoff(mut self, reconnect_backoff: BackoffStrategy) -> Self {
+        self.reconnect_backoff = Some(reconnect_backoff);
+        self
+    }
+
+    /// Sets [SocketOptions::max_reconnect_attempts].
+    pub fn with_max_reconnect_attempts(mut self, max_reconnect_attempts: u32) -> Self {
+        self.max_reconnect_attempts = Some(max_reconnect_attempts);
+        self
+    }
+
+    /// Sets [SocketOptions::rejoin_backoff].
+    pub fn with_rejoin_backoff(mut self, rejoin_backoff: BackoffStrategy) -> Self {
+        self.rejoin_backoff = Some(rejoin_backoff);
+        self
+    }
+
+    /// Sets [SocketOptions::max_rejoin_attempts].
+    pub fn with_max_rejoin_attempts(mut self, max_rejoin_attempts: u32) -> Self {
+        self.max_rejoin_attempts = Some(max_rejoin_attempts);
+        self
+    }
+
+    /// Adds `name: value` to [SocketOptions::headers], replacing any earlier value for `name`.
+    pub fn with_header(mut self, name: String, value: String) -> Self {
+        self.headers.get_or_insert_with(Default::default).insert(name, value);
+        self
+    }
+
+    /// Appends `subprotocol` to [SocketOptions::subprotocols].
+    pub fn with_subprotocol(mut self, subprotocol: String) -> Self {
+        self.subprotocols
+            .get_or_insert_with(Default::default)
+            .push(subprotocol);
+        self
+    }
+
+    /// Sets [SocketOptions::long_poll_fallback_after].
+    pub fn with_long_poll_fallback_after(mut self, long_poll_fallback_after: u32) -> Self {
+        self.long_poll_fallback_after = Some(long_poll_fallback_after);
+        self
+    }
+
+    /// Sets [SocketOptions::serializer_version].
+    pub fn with_serializer_version(mut self, serializer_version: SerializerVersion) -> Self {
+        self.serializer_version = Some(serializer_version);
+        self
+    }
+
+    pub(crate) fn heartbeat_interval_or_default(&self) -> Duration {
+        self.heartbeat_interval
+            .unwrap_or(Self::DEFAULT_HEARTBEAT_INTERVAL)
+    }
+
+    pub(crate) fn serializer_version_or_default(&self) -> SerializerVersion {
+        self.serializer_version.unwrap_or_default()
+    }
+
+    pub(crate) fn reconnect_backoff_or_default(&self) -> BackoffStrategy {
+        self.reconnect_backoff.clone().unwrap_or_default()
+    }
+
+    pub(crate) fn rejoin_backoff_or_default(&self) -> BackoffStrategy {
+        self.rejoin_backoff.clone().unwrap_or_default()
+    }
+
+    /// [SocketOptions::headers] and [SocketOptions::subprotocols] as headers for the web socket
+    /// upgrade request.
+    pub(crate) fn upgrade_headers(&self) -> Result<HeaderMap, SpawnError> {
+        let mut upgrade_headers = HeaderMap::new();
+
+        for (name, value) in self.headers.iter().flatten() {
+            let name =
+                HeaderName::from_bytes(name.as_bytes()).map_err(|_| SpawnError::InvalidOption {
+                    option: "headers",
+                    reason: "invalid header name",
+                })?;
+
+            if is_handshake_header(&name) {
+                return Err(SpawnError::InvalidOption {
+                    option: "headers",
+                    reason: "web socket handshake headers can't be set",
+                });
+            }
+
+            let value = HeaderValue::from_str(value).map_err(|_| SpawnError::InvalidOption {
+                option: "headers",
+                reason: "invalid header value",
+            })?;
+
+            upgrade_headers.insert(name, value);
+        }
+
+        if let Some(subprotocols) = self.subprotocols.as_ref().filter(|s| !s.is_empty()) {
+            if subprotocols.iter().any(|subprotocol| {
+                subprotocol.is_empty()
+                    || subprotocol
+                        .chars()
+                        .any(|c| !c.is_ascii_graphic() || c == ',')
+            }) {
+                return Err(SpawnError::InvalidOption {
+                    option: "subprotocols",
+                    reason: "must be non-empty tokens without commas or whitespace",
+                });
+            }
+
+            upgrade_headers.insert(
+                SEC_WEBSOCKET_PROTOCOL,
+                HeaderValue::from_str(&subprotocols.join(", ")).unwrap(),
+            );
+        }
+
+        Ok(upgrade_headers)
+    }
+
+    pub(crate) fn validate(&self) -> Result<(), SpawnError> {
+        if self.heartbeat_interval_or_default().is_zero() {
+            return Err(SpawnError::InvalidOption {
+                option: "heartbeat_interval",
+                reason: "must be greater than zero",
+            });
+        }
+
+        if self.max_missed_heartbeats == 0 {
+            return Err(SpawnError::InvalidOption {
+                option: "max_missed_heartbeats",
+                reason: "must be greater than zero",
+            });
+        }
+
+        for (option, capacity) in [
+            (
+                "socket_command_queue_depth",
+                self.socket_command_queue_depth,
+            ),
+            (
+                "channel_command_queue_depth",
+                self.channel_command_queue_depth,
+            ),
+            ("channel_event_buffer_size", self.channel_event_buffer_size),
+        ] {
+            if capacity == 0 {
+                return Err(SpawnError::InvalidOption {
+                    option,
+                    reason: "must be greater than zero",
+                });
+            }
+        }
+
+        for (option, backoff) in [
+            ("reconnect_backoff", &self.reconnect_backoff),
+            ("rejoin_backoff", &self.rejoin_backoff),
+        ] {
+            if let Some(backoff) = backoff {
+                backoff
+                    .validate()
+                    .map_err(|reason| SpawnError::InvalidOption { option, reason })?;
+            }
+        }
+
+        self.upgrade_headers()?;
+
+        Ok(())
+    }
+}
+/// Headers web sockets use for the handshake itself, so they can't be set by users.
+/// `Sec-WebSocket-Protocol` is only set through [SocketOptions::subprotocols] and
+/// [Socket::set_auth_token](crate::Socket::set_auth_token).
+pub(crate) fn is_handshake_header(name: &HeaderName) -> bool {
+    [
+        HOST,
+        CONNECTION,
+        UPGRADE,
+        SEC_WEBSOCKET_VERSION,
+        SEC_WEBSOCKET_KEY,
+        SEC_WEBSOCKET_PROTOCOL,
+    ]
+    .contains(name)
+}
+
+impl Default for SocketOptions {
+    fn default() -> Self {
+        Self {
+            heartbeat_interval: None,
+            max_missed_heartbeats: 1,
+            connect_timeout: None,
+            socket_command_queue_depth: 50,
+            channel_command_queue_depth: 10,
+            channel_event_buffer_size: 10,
+            reconnect_backoff: None,
+            max_reconnect_attempts: None,
+            rejoin_backoff: None,
+            max_rejoin_attempts: None,
+            headers: None,
+            subprotocols: None,
+            long_poll_fallback_after: None,
+            serializer_version: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_is_valid() {
+        assert!(SocketOptions::default().validate().is_ok());
+    }
+
+    #[test]
+    fn zero_heartbeat_interval_is_invalid() {
+        assert!(matches!(
+            SocketOptions::default()
+                .with_heartbeat_interval(Duration::ZERO)
+                .validate(),
+            Err(SpawnError::InvalidOption {
+                option: "heartbeat_interval",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn zero_max_missed_heartbeats_is_invalid() {
+        assert!(matches!(
+            SocketOptions::default()
+                .with_max_missed_heartbeats(0)
+                .validate(),
+            Err(SpawnError::InvalidOption {
+                option: "max_missed_heartbeats",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn zero_channel_event_buffer_size_is_invalid() {
+        assert!(matches!(
+            SocketOptions::default()
+                .with_channel_event_buffer_size(0)
+                .validate(),
+            Err(SpawnError::InvalidOption {
+                option: "channel_event_buffer_size",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn empty_reconnect_backoff_multipliers_is_invalid() {
+        assert!(matches!(
+            SocketOptions::default()
+                .with_reconnect_backoff(BackoffStrategy::Multipliers {
+                    multipliers: vec![]
+                })
+                .validate(),
+            Err(SpawnError::InvalidOption {
+                option: "reconnect_backoff",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn upgrade_headers_include_headers_and_subprotocols() {
+        let upgrade_headers = SocketOptions::default()
+            .with_header("Authorization".to_string(), "Bearer secret".to_string())
+            .with_subprotocol("phoenix".to_string())
+            .with_subprotocol("v2.json".to_string())
+            .upgrade_headers()
+            .unwrap();
+
+        assert_eq!(upgrade_headers["authorization"], "Bearer secret");
+        assert_eq!(upgrade_headers[SEC_WEBSOCKET_PROTOCOL], "phoenix, v2.json");
+    }
+
+    #[test]
+    fn handshake_header_is_invalid() {
+        assert!(matches!(
+            SocketOptions::default()
+                .with_header("Sec-WebSocket-Key".to_string(), "key".to_string())
+                .validate(),
+            Err(SpawnError::InvalidOption {
+                option: "headers",
+                ..
+            })
+        ));
+    }
+}
@@ -0,0 +1,170 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use arc_swap::ArcSwapOption;
+use tokio_tungstenite::tungstenite::error::ProtocolError;
+use tokio_tungstenite::tungstenite::http::{HeaderMap, HeaderName, HeaderValue};
+use url::Url;
+
+use crate::ffi::socket::options::is_handshake_header;
+
+/// Query params and headers for a single [Socket::connect](crate::Socket::connect) or automatic
+/// reconnect, produced by a [ParamsProvider].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "uniffi",
+    derive(uniffi::Record)
+)]
+pub struct ConnectParams {
+    /// Query params for the [Socket](crate::Socket) [Url], replacing any params with the same name
+    /// in the [Url] passed to [Socket::spawn](crate::Socket::spawn).
+    ///
+    /// * [None] - the [Url] params are used as is.
+    #[cfg_attr(feature = "uniffi", uniffi(default = None))]
+    pub query_params: Option<HashMap<String, String>>,
+    /// Headers for the web socket upgrade request, replacing
+    /// [SocketOptions::headers](crate::SocketOptions::headers) with the same name.
+    ///
+    /// * [None] - only [SocketOptions::headers](crate::SocketOptions::headers) are sent.
+    #[cfg_attr(feature = "uniffi", uniffi(default = None))]
+    pub headers: Option<HashMap<String, String>>,
+}
+impl ConnectParams {
+    /// `url` with its query params replaced by [ConnectParams::query_params].
+    pub(crate) fn url(&self, url: &Url) -> Url {
+        match &self.query_params {
+            Some(query_params) if !query_params.is_empty() => {
+                let kept_pairs: Vec<(String, String)> = url
+                    .query_pairs()
+                    .filter(|(name, _)| !query_params.contains_key(name.as_ref()))
+                    .map(|(name, value)| (name.into_owned(), value.into_owned()))
+                    .collect();
+
+                let mut url = url.clone();
+                url.query_pairs_mut()
+                    .clear()
+                    .extend_pairs(kept_pairs)
+                    .extend_pairs(query_params);
+
+                url
+            }
+            _ => url.clone(),
+        }
+    }
+
+    /// Inserts [ConnectParams::headers] into `headers`.
+    pub(crate) fn insert_headers(&self, headers: &mut HeaderMap) -> Result<(), ProtocolError> {
+        for (name, value) in self.headers.iter().flatten() {
+            let name = HeaderName::from_bytes(name.as_bytes())
+                .map_err(|_| ProtocolError::HttparseError(httparse::Error::HeaderName))?;
+
+            if is_handshake_header(&name) {
+                return Err(ProtocolError::InvalidHeader(name));
+            }
+
+            let value = HeaderValue::from_str(value)
+                .map_err(|_| ProtocolError::HttparseError(httparse::Error::HeaderValue))?;
+
+            headers.insert(name, value);
+        }
+
+        Ok(())
+    }
+}
+
+/// Produces fresh [ConnectParams] before each [Socket::connect](crate::Socket::connect) and
+/// automatic reconnect, like the `params` function option in `phoenix.js`, so that tokens that
+/// expire while the [Socket](crate::Socket) is connected don't cause endless failing reconnects.
+///
+/// Rust code can instead use an async closure with
+/// [Socket::set_async_params_provider](crate::Socket::set_async_params_provider).
+#[cfg_attr(feature = "uniffi", uniffi::export(callback_interface))]
+pub trait ParamsProvider: Send + Sync {
+    /// The [ConnectParams] for the next connection attempt.
+    fn params(&self) -> ConnectParams;
+}
+
+type AsyncParamsProvider =
+    dyn Fn() -> Pin<Box<dyn Future<Output = ConnectParams> + Send>> + Send + Sync;
+
+pub(crate) enum ParamsSource {
+    Callback(Arc<dyn ParamsProvider>),
+    Async(Box<AsyncParamsProvider>),
+}
+impl ParamsSource {
+    pub(crate) fn from_async<F, Fut>(provider: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ConnectParams> + Send + 'static,
+    {
+        Self::Async(Box::new(move || Box::pin(provider())))
+    }
+
+    async fn params(&self) -> ConnectParams {
+        match self {
+            // `params` is a blocking foreign callback, so keep it off the async workers
+            ParamsSource::Callback(provider) => {
+                let provider = provider.clone();
+
+                tokio::task::spawn_blocking(move || provider.params())
+                    .await
+                    .unwrap_or_else(|join_error| std::panic::resume_unwind(join_error.into_panic()))
+            }
+            ParamsSource::Async(provider) => provider().await,
+        }
+    }
+}
+
+/// The parts of the upgrade request that can change between connection attempts, shared between
+/// the [Socket](crate::Socket) and its async task.
+#[derive(Default)]
+pub(crate) struct DynamicParams {
+    /// [Socket::set_auth_token](crate::Socket::set_auth_token)
+    pub(crate) auth_token: ArcSwapOption<String>,
+    /// [Socket::set_params_provider](crate::Socket::set_params_provider) or
+    /// [Socket::set_async_params_provider](crate::Socket::set_async_params_provider)
+    pub(crate) params_source: ArcSwapOption<ParamsSource>,
+}
+impl DynamicParams {
+    /// The [ConnectParams] from the current [ParamsSource], if any.
+    pub(crate) async fn connect_params(&self) -> Option<ConnectParams> {
+        match self.params_source.load_full() {
+            Some(params_source) => Some(params_source.params().await),
+            None => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn url_replaces_query_params_with_same_name() {
+        let url = Url::parse("ws://127.0.0.1:9002/socket/websocket?id=1&secret=old&vsn=2.0.0")
+            .unwrap();
+        let connect_params = ConnectParams {
+            query_params: Some(HashMap::from([("secret".to_string(), "new".to_string())])),
+            headers: None,
+        };
+
+        assert_eq!(
+            connect_params.url(&url).as_str(),
+            "ws://127.0.0.1:9002/socket/websocket?id=1&vsn=2.0.0&secret=new"
+        );
+    }
+
+    #[test]
+    fn insert_headers_rejects_handshake_headers() {
+        let connect_params = ConnectParams {
+            query_params: None,
+            headers: Some(HashMap::from([("Upgrade".to_string(), "h2c".to_string())])),
+        };
+
+        assert!(connect_params
+            .insert_headers(&mut HeaderMap::new())
+            .is_err());
+    }
+}
@@ -8,18 +8,55 @@ mod ffi;
 mod rust;
 
 // All types should be at the root as `uniffi` only exposes one namespace to foreign code
+pub use ffi::absinthe::{
+    Absinthe, AbsintheError, AbsintheSubscription, AbsintheSubscriptionError,
+};
+pub use ffi::backoff::{Backoff, BackoffStrategy};
+pub use ffi::channel::join_payload::JoinPayloadProvider;
+pub use ffi::channel::options::{ChannelOptions, EventDelivery};
 pub use ffi::channel::statuses::{ChannelStatusJoinError, ChannelStatuses};
 pub use ffi::channel::{
-    CallError, Channel, ChannelJoinError, ChannelStatus, EventPayload, Events, EventsError,
+    CallError, CastError, Channel, ChannelJoinError, ChannelStatus, EventPayload, Events,
+    EventsError,
 };
 pub use ffi::io::error::IoError;
 pub use ffi::json::{JSONDeserializationError, JSON};
+pub use ffi::listeners::{
+    ChannelStatusListener, EventListener, ListenerHandle, SocketStatusListener,
+};
+pub use ffi::live_view::upload::{
+    LiveViewUpload, UploadEntry, UploadEntryError, UploadError, UploadProgressListener,
+    UploadSource,
+};
+pub use ffi::live_view::{
+    LiveView, LiveViewError, LiveViewJoin, LiveViewListener, LiveViewNavigation,
+    LiveViewPushedEvent, LiveViewUpdate, LiveViewUpdates, LiveViewUpdatesError,
+};
 pub use ffi::message::{Event, Payload, PhoenixEvent};
-pub use ffi::socket::{ConnectError, Socket, SocketStatus, SocketStatuses, SocketError};
+pub use ffi::presence::{
+    Presence, PresenceChange, PresenceChanges, PresenceChangesError, PresenceEntry, PresenceListener,
+    PresenceMeta,
+};
+pub use ffi::serializer::SerializerVersion;
+pub use ffi::socket::frames::{WireFrame, WireFrameDirection, WireFrames, WireFramesError};
+pub use ffi::socket::options::SocketOptions;
+pub use ffi::socket::params::{ConnectParams, ParamsProvider};
+pub use ffi::socket::{ConnectError, ReconnectReason, Socket, SocketStatus, SocketStatuses, SocketError};
 pub use ffi::topic::Topic;
 pub use ffi::web_socket::error::WebSocketError;
 pub use ffi::web_socket::protocol::WebSocketMessage;
 pub use ffi::PhoenixError;
+// Rust-only, as generic traits and types can't be exported with `uniffi`
+pub use rust::channel::typed::{TypedError, TypedEventPayload, TypedEvents};
+pub use rust::channel_events::{ChannelEvents, ChannelEventsError};
+#[doc(hidden)]
+pub use rust::channel_events::private as __private;
+#[cfg(feature = "derive")]
+pub use phoenix_channels_client_derive::ChannelEvents;
+pub use rust::serializer;
+pub use rust::transport;
+#[cfg(any(test, feature = "testing"))]
+pub use rust::testing;
 
 #[cfg(feature = "uniffi")]
 uniffi::setup_scaffolding!("phoenix_channels_client");
@@ -2,8 +2,15 @@
 //!
 //! [uniffi] should NOT be used in any of the code under this namespace.
 pub mod channel;
+pub mod channel_events;
 pub mod join_reference;
 pub mod message;
 pub mod observable_status;
+pub(crate) mod live_view;
+pub(crate) mod presence;
 pub mod reference;
+pub mod serializer;
 pub mod socket;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+pub mod transport;
@@ -1,4 +1,7 @@
 pub(crate) mod listener;
+pub(crate) mod push_buffer;
+pub(crate) mod subscriptions;
+pub mod typed;
 
 use atomic_take::AtomicTake;
 use std::sync::Arc;
@@ -8,11 +11,15 @@ use tokio::sync::{broadcast, mpsc, oneshot};
 use tokio::time::error::Elapsed;
 use tokio_tungstenite::tungstenite;
 
+use crate::ffi::channel::join_payload::JoinPayload;
+use crate::ffi::channel::options::ChannelOptions;
 use crate::ffi::channel::Channel;
 use crate::ffi::socket::Socket;
 use crate::ffi::topic::Topic;
 pub(crate) use crate::rust::channel::listener::{Call, LeaveError, Status};
-use crate::rust::channel::listener::{Listener, ObservableStatus, SendCommand};
+use crate::rust::channel::listener::{ChannelHandles, Listener, ObservableStatus, SendCommand};
+use crate::rust::channel::push_buffer::PushBuffer;
+use crate::rust::channel::subscriptions::Subscriptions;
 use crate::rust::message::Payload;
 use crate::rust::socket;
 use crate::rust::socket::listener::Connectivity;
@@ -21,41 +28,52 @@ use crate::rust::socket::listener::Connectivity;
 impl Channel {
     /// Spawns a new [Channel] that must be [join]ed.  The `topic` and `payload` is sent on the
     /// first [join] and any rejoins if the underlying `socket` is disconnected and
-    /// reconnects.
+    /// reconnects, unless the payload is replaced with [Channel::set_join_payload] or
+    /// [Channel::set_join_payload_provider].
     pub(crate) async fn spawn(
         socket: Arc<Socket>,
         socket_connectivity_rx: broadcast::Receiver<Connectivity>,
         topic: Arc<Topic>,
         payload: Option<Payload>,
+        options: ChannelOptions,
         state: listener::State,
     ) -> Self {
-        let payload = payload.unwrap_or_default();
+        let join_payload = Arc::new(JoinPayload::new(payload.unwrap_or_default()));
+        let serializer = socket.serializer.clone();
         let status = ObservableStatus::new(state.status());
         let (shutdown_tx, shutdown_rx) = oneshot::channel();
-        let (event_payload_tx, _) = broadcast::channel(10);
-        let (state_command_tx, state_command_rx) = mpsc::channel(10);
-        let (send_command_tx, send_command_rx) = mpsc::channel(10);
+        let subscriptions = Arc::new(Subscriptions::new(
+            options.event_buffer_size_or_default(&socket.options),
+            options.event_delivery_or_default(),
+        ));
+        let command_queue_depth = socket.options.channel_command_queue_depth as usize;
+        let (state_command_tx, state_command_rx) = mpsc::channel(command_queue_depth);
+        let (send_command_tx, send_command_rx) = mpsc::channel(command_queue_depth);
         let join_handle = Listener::spawn(
             socket,
             socket_connectivity_rx,
             topic.clone(),
-            payload.clone(),
             state,
-            status.clone(),
-            shutdown_rx,
-            event_payload_tx.clone(),
-            state_command_rx,
-            send_command_rx,
+            ChannelHandles {
+                join_payload: join_payload.clone(),
+                channel_status: status.clone(),
+                shutdown_rx,
+                subscriptions: subscriptions.clone(),
+                state_command_rx,
+                send_command_rx,
+                push_buffer: PushBuffer::new(options.push_buffer_size.map(|size| size as usize)),
+            },
         );
 
         Self {
             topic,
-            payload,
+            join_payload,
             status,
-            event_payload_tx,
+            subscriptions,
             shutdown_tx: AtomicTake::new(shutdown_tx),
             state_command_tx,
             send_command_tx,
+            serializer,
             join_handle: AtomicTake::new(join_handle),
         }
     }
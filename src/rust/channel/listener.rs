@@ -10,27 +10,44 @@ use tokio::task::JoinHandle;
 use tokio::time::{Instant, Sleep};
 use tokio_tungstenite::tungstenite;
 
+use crate::ffi::backoff::Backoff;
+use crate::ffi::channel::join_payload::JoinPayload;
 use crate::ffi::channel::ChannelShutdownError;
 use crate::ffi::message::PhoenixEvent;
 use crate::ffi::socket::Socket;
 use crate::ffi::topic::Topic;
+use crate::rust::channel::push_buffer::PushBuffer;
+use crate::rust::channel::subscriptions::Subscriptions;
 use crate::rust::join_reference::JoinReference;
 use crate::rust::message::{Broadcast, Push};
 use crate::rust::message::{Event, EventPayload, Payload};
 use crate::rust::socket;
 use crate::rust::socket::listener::{Connectivity, Disconnected};
 
+/// The [Channel](crate::Channel)'s ends of what it shares with its [Listener], grouped so that
+/// adding one doesn't grow [Listener::spawn].
+pub(crate) struct ChannelHandles {
+    pub(crate) join_payload: Arc<JoinPayload>,
+    pub(crate) channel_status: ObservableStatus,
+    pub(crate) shutdown_rx: oneshot::Receiver<()>,
+    pub(crate) subscriptions: Arc<Subscriptions>,
+    pub(crate) state_command_rx: mpsc::Receiver<StateCommand>,
+    pub(crate) send_command_rx: mpsc::Receiver<SendCommand>,
+    pub(crate) push_buffer: PushBuffer,
+}
+
 pub(crate) struct Listener {
     socket: Arc<Socket>,
     socket_connectivity_rx: broadcast::Receiver<Connectivity>,
     topic: Arc<Topic>,
-    payload: Payload,
+    join_payload: Arc<JoinPayload>,
     channel_status: ObservableStatus,
     shutdown_rx: oneshot::Receiver<()>,
-    event_payload_tx: broadcast::Sender<EventPayload>,
+    subscriptions: Arc<Subscriptions>,
     state_command_rx: mpsc::Receiver<StateCommand>,
     state: Option<State>,
     send_command_rx: mpsc::Receiver<SendCommand>,
+    push_buffer: PushBuffer,
     join_reference: JoinReference,
 }
 impl Listener {
@@ -38,25 +55,15 @@ impl Listener {
         socket: Arc<Socket>,
         socket_connectivity_rx: broadcast::Receiver<Connectivity>,
         topic: Arc<Topic>,
-        payload: Payload,
         state: State,
-        channel_status: ObservableStatus,
-        shutdown_rx: oneshot::Receiver<()>,
-        event_payload_tx: broadcast::Sender<EventPayload>,
-        state_command_rx: mpsc::Receiver<StateCommand>,
-        send_command_rx: mpsc::Receiver<SendCommand>,
+        channel_handles: ChannelHandles,
     ) -> JoinHandle<Result<(), ChannelShutdownError>> {
         let listener = Self::init(
             socket,
             socket_connectivity_rx,
             topic,
-            payload,
             state,
-            channel_status,
-            shutdown_rx,
-            event_payload_tx,
-            state_command_rx,
-            send_command_rx,
+            channel_handles,
         );
 
         tokio::spawn(listener.listen())
@@ -66,25 +73,31 @@ impl Listener {
         socket: Arc<Socket>,
         socket_connectivity_rx: broadcast::Receiver<Connectivity>,
         topic: Arc<Topic>,
-        payload: Payload,
         state: State,
-        channel_status: ObservableStatus,
-        shutdown_rx: oneshot::Receiver<()>,
-        event_payload_tx: broadcast::Sender<EventPayload>,
-        state_command_rx: mpsc::Receiver<StateCommand>,
-        send_command_rx: mpsc::Receiver<SendCommand>,
+        channel_handles: ChannelHandles,
     ) -> Self {
+        let ChannelHandles {
+            join_payload,
+            channel_status,
+            shutdown_rx,
+            subscriptions,
+            state_command_rx,
+            send_command_rx,
+            push_buffer,
+        } = channel_handles;
+
         Self {
             socket,
             socket_connectivity_rx,
             topic,
-            payload,
+            join_payload,
             state: Some(state),
             channel_status,
             shutdown_rx,
-            event_payload_tx,
+            subscriptions,
             state_command_rx,
             send_command_rx,
+            push_buffer,
             join_reference: JoinReference::new(),
         }
     }
@@ -98,6 +111,7 @@ impl Listener {
         let result = loop {
             let mut current_state = self.state.take().unwrap();
             let current_discriminant = mem::discriminant(&current_state);
+            let buffering = self.push_buffer.has_space();
 
             let next_state = match current_state {
                 State::WaitingForSocketToConnect { .. } => tokio::select! {
@@ -105,6 +119,11 @@ impl Listener {
 
                     _ = &mut self.shutdown_rx => current_state.shutdown(),
                     Ok(socket_connectivity) = self.socket_connectivity_rx.recv() => current_state.connectivity_changed(socket_connectivity),
+                    Some(send_command) = self.send_command_rx.recv(), if buffering => {
+                        self.push_buffer.push(send_command);
+
+                        current_state
+                    }
                     else => break Ok(())
                 },
                 State::WaitingToJoin => tokio::select! {
@@ -113,6 +132,11 @@ impl Listener {
                     _ = &mut self.shutdown_rx => current_state.shutdown(),
                     Ok(socket_connectivity) = self.socket_connectivity_rx.recv() => current_state.connectivity_changed(socket_connectivity),
                     Some(state_command) = self.state_command_rx.recv() => self.update_state(current_state, state_command).await?,
+                    Some(send_command) = self.send_command_rx.recv(), if buffering => {
+                        self.push_buffer.push(send_command);
+
+                        current_state
+                    }
                     else => break Ok(())
                 },
                 State::Joining(mut joining) => tokio::select! {
@@ -121,18 +145,32 @@ impl Listener {
                     _ = &mut self.shutdown_rx => State::Joining(joining).shutdown(),
                     socket_joined_result = &mut joining.socket_joined_rx => self.joined_result_result_received(joining, socket_joined_result).await?,
                     Some(state_command) = self.state_command_rx.recv() => self.update_state(State::Joining(joining), state_command).await?,
+                    Some(send_command) = self.send_command_rx.recv(), if buffering => {
+                        self.push_buffer.push(send_command);
+
+                        State::Joining(joining)
+                    }
                     else => break Ok(())
                 },
                 State::WaitingToRejoin {
                     ref mut sleep,
-                    rejoin,
+                    ref rejoin,
                 } => tokio::select! {
                     biased;
 
                     _ = &mut self.shutdown_rx => current_state.shutdown(),
                     Ok(socket_connectivity) = self.socket_connectivity_rx.recv() => current_state.connectivity_changed(socket_connectivity),
-                    () = sleep => self.rejoin(current_state, rejoin).await?,
+                    () = sleep => {
+                        let rejoin = rejoin.clone();
+
+                        self.rejoin(current_state, rejoin).await?
+                    },
                     Some(state_command) = self.state_command_rx.recv() => self.update_state(current_state, state_command).await?,
+                    Some(send_command) = self.send_command_rx.recv(), if buffering => {
+                        self.push_buffer.push(send_command);
+
+                        current_state
+                    }
                     else => break Ok(())
                 },
                 State::Joined(mut joined) => tokio::select! {
@@ -147,9 +185,12 @@ impl Listener {
                         Ok(()) = &mut joined.left_rx => State::Left,
                         Some(state_command) = self.state_command_rx.recv() => self.update_state(State::Joined(joined), state_command).await?,
                         Some(send_command) = self.send_command_rx.recv() => self.send(joined, send_command).await,
-                        Some(push) = joined.push_rx.recv() => self.push_received(joined, push),
-                        Ok(broadcast) = joined.broadcast_rx.recv() => {
-                            self.send_event_payload(broadcast);
+                        Some(push) = joined.push_rx.recv() => self.push_received(joined, push).await,
+                        Some(broadcast_result) = recv_broadcast(&mut joined.broadcast_rx) => {
+                            match broadcast_result {
+                                Ok(broadcast) => self.send_event_payload(broadcast).await,
+                                Err(missed_event_count) => self.subscriptions.missed(missed_event_count).await,
+                            }
 
                             State::Joined(joined)
                         }
@@ -164,7 +205,7 @@ impl Listener {
                     Some(state_command) = self.state_command_rx.recv() => self.update_state(State::Leaving(leaving), state_command).await?,
                     else => break Ok(())
                 },
-                State::Left => tokio::select! {
+                State::Left | State::RejoinAttemptsExhausted => tokio::select! {
                     biased;
 
                     _ = &mut self.shutdown_rx => current_state.shutdown(),
@@ -174,6 +215,7 @@ impl Listener {
                 },
                 State::ShuttingDown | State::ShutDown => break Ok(()),
             };
+            let next_state = self.flush_push_buffer(next_state).await;
 
             self.channel_status.set(next_state.status());
 
@@ -217,7 +259,10 @@ impl Listener {
     ) -> Result<State, ChannelShutdownError> {
         match state {
             State::WaitingForSocketToConnect { .. } => unreachable!(),
-            State::WaitingToJoin { .. } | State::Leaving { .. } | State::Left { .. } => {
+            State::WaitingToJoin { .. }
+            | State::Leaving { .. }
+            | State::Left { .. }
+            | State::RejoinAttemptsExhausted => {
                 self.socket_join_if_not_timed_out(state, created_at, timeout, channel_joined_tx)
                     .await
             }
@@ -260,6 +305,9 @@ impl Listener {
         let rejoin = Rejoin {
             join_timeout: timeout,
             attempts: 0,
+            previous_sleep: Duration::ZERO,
+            backoff: self.socket.rejoin_backoff.clone(),
+            max_attempts: self.socket.options.max_rejoin_attempts,
         };
 
         if Instant::now() <= deadline {
@@ -302,18 +350,23 @@ impl Listener {
 
         let (channel_joined_result, next_state) = match result {
             Ok(JoinedChannelReceivers {
+                reply,
                 push: push_rx,
                 broadcast: broadcast_rx,
                 left: left_rx,
-            }) => (
-                Ok(()),
-                State::Joined(Joined {
-                    push_rx,
-                    broadcast_rx,
-                    left_rx,
-                    join_timeout: rejoin.join_timeout,
-                }),
-            ),
+            }) => {
+                self.join_payload.set_reply(reply);
+
+                (
+                    Ok(()),
+                    State::Joined(Joined {
+                        push_rx,
+                        broadcast_rx,
+                        left_rx,
+                        rejoin,
+                    }),
+                )
+            }
             Err(socket_join_error) => match socket_join_error {
                 socket::JoinError::Shutdown(shutdown_error) => (
                     Err(JoinError::SocketShutdown(Arc::new(shutdown_error))),
@@ -348,12 +401,28 @@ impl Listener {
         rejoin: Rejoin,
         channel_joined_txs: Vec<oneshot::Sender<Result<(), JoinError>>>,
     ) -> Result<State, ChannelShutdownError> {
+        let payload = self.join_payload.next().await;
+
+        if payload.is_binary() && !self.socket.serializer.supports_binary_payloads() {
+            debug!("{} can't join with a binary payload", self.topic);
+
+            State::send_to_channel_txs(
+                channel_joined_txs,
+                Err(JoinError::BinaryPayloadUnsupported),
+            );
+
+            return Ok(match state {
+                State::Leaving(_) => state,
+                _ => State::WaitingToJoin,
+            });
+        }
+
         match self
             .socket
             .join(
                 self.topic.clone(),
                 self.join_reference.clone(),
-                self.payload.clone(),
+                payload,
                 created_at + rejoin.join_timeout,
             )
             .await
@@ -393,6 +462,7 @@ impl Listener {
             | State::WaitingToRejoin { .. }
             | State::Joined { .. }
             | State::Left { .. }
+            | State::RejoinAttemptsExhausted
             | State::ShuttingDown
             | State::ShutDown => {}
             State::Joining(Joining {
@@ -414,13 +484,13 @@ impl Listener {
         ChannelShutdownError::SocketShutdown
     }
 
-    fn push_received(&self, joined: Joined, push: Push) -> State {
+    async fn push_received(&self, joined: Joined, push: Push) -> State {
         debug!(
             "{} joined as {} received push: {:#?}",
             &self.topic, &self.join_reference, push
         );
         let event_payload: EventPayload = push.into();
-        self.send_event_payload(event_payload.clone());
+        self.send_event_payload(event_payload.clone()).await;
 
         match event_payload {
             EventPayload {
@@ -486,7 +556,7 @@ impl Listener {
                     State::Joined(joined)
                 }
             },
-            State::WaitingToRejoin { .. } => {
+            State::WaitingToRejoin { .. } | State::RejoinAttemptsExhausted => {
                 left_tx.send(Ok(())).ok();
 
                 State::Left
@@ -502,6 +572,18 @@ impl Listener {
         }
     }
 
+    /// Sends the [PushBuffer] in order once joined.
+    async fn flush_push_buffer(&mut self, mut state: State) -> State {
+        while let State::Joined(joined) = state {
+            match self.push_buffer.pop() {
+                Some(send_command) => state = self.send(joined, send_command).await,
+                None => return State::Joined(joined),
+            }
+        }
+
+        state
+    }
+
     async fn send(&self, joined: Joined, send_command: SendCommand) -> State {
         match send_command {
             SendCommand::Cast(cast) => self.cast(joined, cast).await,
@@ -539,8 +621,23 @@ impl Listener {
         }
     }
 
-    fn send_event_payload<EP: Into<EventPayload>>(&self, event_payload: EP) {
-        self.event_payload_tx.send(event_payload.into()).ok();
+    async fn send_event_payload<EP: Into<EventPayload>>(&self, event_payload: EP) {
+        self.subscriptions.send(&event_payload.into()).await
+    }
+}
+
+/// The next [Broadcast] or how many were missed because this channel lagged behind the socket.
+/// [None] when the socket dropped the sender, so that the `select!` branch is disabled instead of
+/// matching [broadcast::error::RecvError::Closed] again immediately.
+async fn recv_broadcast(
+    broadcast_rx: &mut broadcast::Receiver<Broadcast>,
+) -> Option<Result<Broadcast, u64>> {
+    match broadcast_rx.recv().await {
+        Ok(broadcast) => Some(Ok(broadcast)),
+        Err(broadcast::error::RecvError::Lagged(missed_event_count)) => {
+            Some(Err(missed_event_count))
+        }
+        Err(broadcast::error::RecvError::Closed) => None,
     }
 }
 
@@ -622,6 +719,10 @@ pub(crate) enum State {
         /// been attempted already.
         rejoin: Rejoin,
     },
+    /// [super::Channel::join] was called previously, but all
+    /// [SocketOptions::max_rejoin_attempts](crate::SocketOptions::max_rejoin_attempts) automatic
+    /// rejoins failed, so the [super::Channel] won't rejoin until [super::Channel::join] is called.
+    RejoinAttemptsExhausted,
     /// [super::Channel::join] was called and the server responded that the [super::Channel::topic]
     /// was joined using [super::Channel::payload].
     Joined(Joined),
@@ -642,6 +743,7 @@ impl State {
             State::WaitingToJoin => Status::WaitingToJoin,
             State::Joining(_) => Status::Joining,
             State::WaitingToRejoin { sleep, .. } => Status::WaitingToRejoin(sleep.deadline()),
+            State::RejoinAttemptsExhausted => Status::RejoinAttemptsExhausted,
             State::Joined { .. } => Status::Joined,
             State::Leaving { .. } => Status::Leaving,
             State::Left => Status::Left,
@@ -662,20 +764,24 @@ impl State {
                     None => State::WaitingToJoin,
                     Some(rejoin) => rejoin.wait(),
                 },
-                Connectivity::Disconnected(disconnected) => match disconnected {
-                    Disconnected::Disconnect | Disconnected::Reconnect => self,
-                    Disconnected::Shutdown => State::ShuttingDown,
-                },
-            },
-            State::WaitingToJoin | State::Left => match connectivity {
-                Connectivity::Connected => self,
                 Connectivity::Disconnected(disconnected) => match disconnected {
                     Disconnected::Disconnect | Disconnected::Reconnect => {
-                        State::WaitingForSocketToConnect { rejoin: None }
+                        State::WaitingForSocketToConnect { rejoin }
                     }
                     Disconnected::Shutdown => State::ShuttingDown,
                 },
             },
+            State::WaitingToJoin | State::Left | State::RejoinAttemptsExhausted => {
+                match connectivity {
+                    Connectivity::Connected => self,
+                    Connectivity::Disconnected(disconnected) => match disconnected {
+                        Disconnected::Disconnect | Disconnected::Reconnect => {
+                            State::WaitingForSocketToConnect { rejoin: None }
+                        }
+                        Disconnected::Shutdown => State::ShuttingDown,
+                    },
+                }
+            }
             State::Joining(Joining {
                 channel_joined_txs,
                 rejoin,
@@ -754,6 +860,7 @@ impl State {
             State::WaitingForSocketToConnect { .. }
             | State::WaitingToJoin { .. }
             | State::WaitingToRejoin { .. }
+            | State::RejoinAttemptsExhausted
             | State::Joined(_)
             | State::Left { .. }
             | State::ShuttingDown
@@ -799,6 +906,7 @@ impl Debug for State {
                 )
                 .field("rejoin", rejoin)
                 .finish(),
+            State::RejoinAttemptsExhausted => f.write_str("RejoinAttemptsExhausted"),
             State::Joined(joined) => f.debug_tuple("Joined").field(joined).finish(),
             State::Leaving { .. } => f.debug_struct("Leaving").finish_non_exhaustive(),
             State::Left { .. } => f.write_str("Left"),
@@ -822,6 +930,10 @@ pub enum Status {
     /// [super::Channel::join] was called previously, but the [Socket] was disconnected and
     /// reconnected.
     WaitingToRejoin(Instant),
+    /// [super::Channel::join] was called previously, but all
+    /// [SocketOptions::max_rejoin_attempts](crate::SocketOptions::max_rejoin_attempts) automatic
+    /// rejoins failed, so the [super::Channel] won't rejoin until [super::Channel::join] is called.
+    RejoinAttemptsExhausted,
     /// [super::Channel::join] was called and the server responded that the [super::Channel::topic]
     /// was joined using [super::Channel::payload].
     Joined,
@@ -872,6 +984,16 @@ impl Status {
         }
     }
 
+    /// [super::Channel::join] was called previously, but all
+    /// [SocketOptions::max_rejoin_attempts](crate::SocketOptions::max_rejoin_attempts) automatic
+    /// rejoins failed, so the [super::Channel] won't rejoin until [super::Channel::join] is called.
+    pub const fn is_rejoin_attempts_exhausted(&self) -> bool {
+        match self {
+            Status::RejoinAttemptsExhausted => true,
+            _ => false,
+        }
+    }
+
     /// [super::Channel::join] was called and the server responded that the [super::Channel::topic]
     /// was joined using [super::Channel::payload].
     pub const fn is_joined(&self) -> bool {
@@ -920,42 +1042,79 @@ pub type ObservableStatus = crate::rust::observable_status::ObservableStatus<Sta
 #[doc(hidden)]
 #[derive(Debug)]
 pub(crate) struct JoinedChannelReceivers {
-    pub push: mpsc::Receiver<Push>,
+    /// The payload of the `ok` reply to the join.
+    pub reply: Payload,
+    pub push: mpsc::UnboundedReceiver<Push>,
     pub broadcast: broadcast::Receiver<Broadcast>,
     pub left: oneshot::Receiver<()>,
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Clone)]
 pub(crate) struct Rejoin {
     join_timeout: Duration,
     /// Enough for > 1 week of attempts; u8 would only be 42 minutes of attempts.
     attempts: u16,
+    /// The sleep before the previous attempt.
+    previous_sleep: Duration,
+    /// [SocketOptions::rejoin_backoff](crate::SocketOptions::rejoin_backoff)
+    backoff: Arc<dyn Backoff>,
+    /// [SocketOptions::max_rejoin_attempts](crate::SocketOptions::max_rejoin_attempts)
+    max_attempts: Option<u32>,
 }
 impl Rejoin {
     fn wait(self) -> State {
+        if self.is_exhausted() {
+            debug!("giving up rejoining after {} attempts", self.attempts);
+
+            return State::RejoinAttemptsExhausted;
+        }
+
+        let sleep_duration = self.sleep_duration();
+
         State::WaitingToRejoin {
-            sleep: Box::pin(tokio::time::sleep(self.sleep_duration())),
-            rejoin: self.next(),
+            sleep: Box::pin(tokio::time::sleep(sleep_duration)),
+            rejoin: self.next(sleep_duration),
         }
     }
 
-    fn next(self) -> Self {
+    fn is_exhausted(&self) -> bool {
+        self.max_attempts
+            .map_or(false, |max_attempts| u32::from(self.attempts) >= max_attempts)
+    }
+
+    fn next(self, previous_sleep: Duration) -> Self {
         Self {
-            join_timeout: self.join_timeout,
             attempts: self.attempts.saturating_add(1),
+            previous_sleep,
+            ..self
         }
     }
 
-    fn sleep_duration(&self) -> Duration {
-        self.join_timeout * self.sleep_duration_multiplier()
+    fn reset(self) -> Self {
+        Self {
+            attempts: 0,
+            previous_sleep: Duration::ZERO,
+            ..self
+        }
     }
 
-    fn sleep_duration_multiplier(&self) -> u32 {
-        Self::SLEEP_DURATION_MULTIPLIERS
-            [(self.attempts as usize).min(Self::SLEEP_DURATION_MULTIPLIERS.len() - 1)]
+    fn sleep_duration(&self) -> Duration {
+        self.backoff.sleep_duration(
+            u32::from(self.attempts),
+            self.join_timeout,
+            self.previous_sleep,
+        )
+    }
+}
+impl Debug for Rejoin {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Rejoin")
+            .field("join_timeout", &self.join_timeout)
+            .field("attempts", &self.attempts)
+            .field("previous_sleep", &self.previous_sleep)
+            .field("max_attempts", &self.max_attempts)
+            .finish_non_exhaustive()
     }
-
-    const SLEEP_DURATION_MULTIPLIERS: &'static [u32] = &[0, 1, 2, 5, 10];
 }
 
 pub(crate) struct Joining {
@@ -972,23 +1131,21 @@ impl Debug for Joining {
 }
 
 pub(crate) struct Joined {
-    push_rx: mpsc::Receiver<Push>,
+    push_rx: mpsc::UnboundedReceiver<Push>,
     broadcast_rx: broadcast::Receiver<Broadcast>,
     left_rx: oneshot::Receiver<()>,
-    join_timeout: Duration,
+    /// The [Rejoin] used to join, so its attempts can be reset for rejoining.
+    rejoin: Rejoin,
 }
 impl Joined {
     fn rejoin(self) -> Rejoin {
-        Rejoin {
-            join_timeout: self.join_timeout,
-            attempts: 0,
-        }
+        self.rejoin.reset()
     }
 }
 impl Debug for Joined {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("Joined")
-            .field("join_timeout", &self.join_timeout)
+            .field("join_timeout", &self.rejoin.join_timeout)
             .finish_non_exhaustive()
     }
 }
@@ -1036,4 +1193,9 @@ pub enum JoinError {
     /// rejoin [Channel::topic].
     #[error("server rejected join")]
     Rejected(Arc<Payload>),
+    /// The [Channel::payload] is binary, but the
+    /// [Serializer](crate::serializer::Serializer) of the [Socket](crate::Socket) can't encode
+    /// binary payloads.
+    #[error("binary payloads are not supported by the serializer")]
+    BinaryPayloadUnsupported,
 }
@@ -0,0 +1,183 @@
+use std::collections::VecDeque;
+
+use crate::rust::channel::listener::SendCommand;
+
+/// Casts and calls made while the channel is not joined, sent in order once it is, like the
+/// `pushBuffer` of `phoenix.js`.
+pub(crate) struct PushBuffer {
+    /// [ChannelOptions::push_buffer_size](crate::ChannelOptions::push_buffer_size)
+    ///
+    /// * [None] - nothing is buffered, so commands wait in the command queue until joined.
+    capacity: Option<usize>,
+    send_commands: VecDeque<SendCommand>,
+}
+impl PushBuffer {
+    pub(crate) fn new(capacity: Option<usize>) -> Self {
+        Self {
+            capacity,
+            send_commands: VecDeque::with_capacity(capacity.unwrap_or_default()),
+        }
+    }
+
+    /// Whether another [SendCommand] can be pushed, after dropping calls whose caller stopped
+    /// waiting for the reply, such as because its timeout elapsed.
+    pub(crate) fn has_space(&mut self) -> bool {
+        match self.capacity {
+            Some(capacity) => {
+                self.send_commands.retain(|send_command| !is_abandoned(send_command));
+
+                self.send_commands.len() < capacity
+            }
+            None => false,
+        }
+    }
+
+    pub(crate) fn push(&mut self, send_command: SendCommand) {
+        self.send_commands.push_back(send_command);
+    }
+
+    /// The oldest [SendCommand] that still needs to be sent.
+    pub(crate) fn pop(&mut self) -> Option<SendCommand> {
+        while let Some(send_command) = self.send_commands.pop_front() {
+            if !is_abandoned(&send_command) {
+                return Some(send_command);
+            }
+        }
+
+        None
+    }
+}
+
+fn is_abandoned(send_command: &SendCommand) -> bool {
+    match send_command {
+        SendCommand::Cast(_) => false,
+        SendCommand::Call(call) => call.reply_tx.is_closed(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::sync::oneshot;
+
+    use super::*;
+    use crate::ffi::message::PhoenixEvent;
+    use crate::rust::channel::listener::Call;
+    use crate::rust::message::{Event, EventPayload, Payload};
+    use crate::testing::{FakeServer, TIMEOUT};
+    use crate::{CallError, ChannelOptions, Topic};
+
+    fn event_payload(event: &str) -> EventPayload {
+        EventPayload {
+            event: Event::User(event.to_string()),
+            payload: Default::default(),
+        }
+    }
+
+    fn event(send_command: SendCommand) -> Event {
+        match send_command {
+            SendCommand::Cast(event_payload) => event_payload.event,
+            SendCommand::Call(call) => call.event_payload.event,
+        }
+    }
+
+    #[test]
+    fn disabled_has_no_space() {
+        assert!(!PushBuffer::new(None).has_space());
+    }
+
+    #[test]
+    fn pops_in_order_up_to_capacity() {
+        let mut push_buffer = PushBuffer::new(Some(2));
+
+        push_buffer.push(SendCommand::Cast(event_payload("first")));
+        assert!(push_buffer.has_space());
+        push_buffer.push(SendCommand::Cast(event_payload("second")));
+        assert!(!push_buffer.has_space());
+
+        assert_eq!(
+            event(push_buffer.pop().unwrap()),
+            Event::User("first".to_string())
+        );
+        assert_eq!(
+            event(push_buffer.pop().unwrap()),
+            Event::User("second".to_string())
+        );
+        assert!(push_buffer.pop().is_none());
+    }
+
+    #[test]
+    fn abandoned_calls_are_dropped() {
+        let mut push_buffer = PushBuffer::new(Some(1));
+        let (reply_tx, reply_rx) = oneshot::channel();
+        push_buffer.push(SendCommand::Call(Call {
+            event_payload: event_payload("timed_out"),
+            reply_tx,
+        }));
+        assert!(!push_buffer.has_space());
+
+        drop(reply_rx);
+
+        assert!(push_buffer.has_space());
+        assert!(push_buffer.pop().is_none());
+    }
+
+    #[tokio::test]
+    async fn buffered_pushes_are_sent_in_order_after_join() {
+        let server = FakeServer::new();
+        let socket = server.connected_socket().await;
+        let channel = socket
+            .channel_with_options(
+                Topic::from_string("room:lobby".to_string()),
+                None,
+                ChannelOptions::default().with_push_buffer_size(10),
+            )
+            .await
+            .unwrap();
+
+        channel
+            .cast(
+                crate::Event::from_string("first".to_string()),
+                Payload::default().into(),
+            )
+            .await
+            .unwrap();
+        assert!(matches!(
+            channel
+                .call(
+                    crate::Event::from_string("timed_out".to_string()),
+                    Payload::default().into(),
+                    Duration::from_millis(10),
+                )
+                .await,
+            Err(CallError::Timeout)
+        ));
+        channel
+            .cast(
+                crate::Event::from_string("second".to_string()),
+                Payload::default().into(),
+            )
+            .await
+            .unwrap();
+
+        channel.join(TIMEOUT).await.unwrap();
+
+        assert_eq!(
+            server.next_received().await.event,
+            crate::Event::Phoenix {
+                phoenix: PhoenixEvent::Join
+            }
+        );
+        assert_eq!(
+            server.next_received().await.event,
+            crate::Event::from_string("first".to_string())
+        );
+        assert_eq!(
+            server.next_received().await.event,
+            crate::Event::from_string("second".to_string())
+        );
+
+        socket.shutdown().await.unwrap();
+    }
+}
@@ -0,0 +1,589 @@
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use log::error;
+use tokio::sync::{broadcast, mpsc};
+
+use crate::ffi;
+use crate::ffi::channel::options::EventDelivery;
+use crate::ffi::channel::EventsError;
+use crate::rust::message::{Event, EventPayload};
+
+/// Unique across all channels, so that [Channel::off](crate::Channel::off) with
+/// [Events](crate::Events) from another channel can't remove this channel's subscriptions.
+static NEXT_REFERENCE: AtomicU64 = AtomicU64::new(0);
+
+/// The subscriptions from [Channel::events](crate::Channel::events),
+/// [Channel::on](crate::Channel::on) and [Channel::on_matching](crate::Channel::on_matching),
+/// each with its own buffer, so that a slow subscriber only misses or holds up its own events.
+pub(crate) struct Subscriptions {
+    /// [ChannelOptions::event_buffer_size](crate::ChannelOptions::event_buffer_size)
+    capacity: usize,
+    /// [ChannelOptions::event_delivery](crate::ChannelOptions::event_delivery)
+    delivery: EventDelivery,
+    subscriptions: Mutex<Vec<Subscription>>,
+}
+impl Subscriptions {
+    pub(crate) fn new(capacity: usize, delivery: EventDelivery) -> Self {
+        Self {
+            capacity,
+            delivery,
+            subscriptions: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Subscribes to the [EventPayload]s matching `filter` until
+    /// [Subscriptions::unsubscribe] is called with the returned reference or the receiver is
+    /// dropped.
+    pub(crate) fn subscribe(&self, filter: Filter) -> (u64, EventReceiver) {
+        let reference = NEXT_REFERENCE.fetch_add(1, Ordering::Relaxed);
+        let (sender, receiver) = match self.delivery {
+            EventDelivery::Lossy => {
+                let (sender, receiver) = broadcast::channel(self.capacity);
+
+                (Sender::Broadcast(sender), EventReceiver::Broadcast(receiver))
+            }
+            EventDelivery::Backpressure => {
+                let (sender, receiver) = mpsc::channel(self.capacity);
+
+                (Sender::Bounded(sender), EventReceiver::Bounded(receiver))
+            }
+            EventDelivery::Unbounded => {
+                let (sender, receiver) = mpsc::unbounded_channel();
+
+                (Sender::Unbounded(sender), EventReceiver::Unbounded(receiver))
+            }
+        };
+
+        self.subscriptions.lock().unwrap().push(Subscription {
+            reference,
+            filter: Arc::new(filter),
+            sender,
+        });
+
+        (reference, receiver)
+    }
+
+    /// Drops the sender for the subscription, so its receiver gets the buffered [EventPayload]s
+    /// and then none.
+    pub(crate) fn unsubscribe(&self, reference: u64) {
+        self.subscriptions
+            .lock()
+            .unwrap()
+            .retain(|subscription| subscription.reference != reference);
+    }
+
+    /// Sends `event_payload` to each matching subscription, waiting for space with
+    /// [EventDelivery::Backpressure], and removes those whose receivers were dropped or whose
+    /// predicate panicked.
+    pub(crate) async fn send(&self, event_payload: &EventPayload) {
+        // Predicates run outside the lock, so they can subscribe and unsubscribe without
+        // deadlocking and a panicking predicate can't poison it.
+        let subscriptions = self
+            .subscriptions
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|subscription| {
+                (
+                    subscription.reference,
+                    subscription.filter.clone(),
+                    subscription.sender.clone(),
+                )
+            })
+            .collect::<Vec<_>>();
+        // Only converted for predicates, which take the public event type.
+        let mut ffi_event = None;
+        let mut senders = Vec::new();
+        let mut panicked_references = Vec::new();
+
+        for (reference, filter, sender) in subscriptions {
+            let matches = match filter.as_ref() {
+                Filter::All => true,
+                Filter::Event(event) => event == &event_payload.event,
+                Filter::Matching(predicate) => {
+                    let ffi_event = ffi_event.get_or_insert_with(|| {
+                        ffi::message::Event::from(event_payload.event.clone())
+                    });
+
+                    match panic::catch_unwind(AssertUnwindSafe(|| predicate(ffi_event))) {
+                        Ok(matches) => matches,
+                        Err(_) => {
+                            error!(
+                                "Channel::on_matching predicate panicked on {}, so it is unsubscribed",
+                                ffi_event
+                            );
+                            panicked_references.push(reference);
+
+                            false
+                        }
+                    }
+                }
+            };
+
+            if matches {
+                senders.push((reference, sender));
+            }
+        }
+
+        self.remove(&panicked_references);
+        self.send_to(senders, Delivery::EventPayload(event_payload.clone()))
+            .await
+    }
+
+    /// Tells every subscription that `missed_event_count` [EventPayload]s were dropped before
+    /// they could be matched, such as when the socket's broadcasts lagged.
+    pub(crate) async fn missed(&self, missed_event_count: u64) {
+        let senders = self
+            .subscriptions
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|subscription| (subscription.reference, subscription.sender.clone()))
+            .collect();
+
+        self.send_to(senders, Delivery::Missed(missed_event_count))
+            .await
+    }
+
+    /// Sends outside the lock, as [Sender::Bounded] may wait for the receiver.
+    async fn send_to(&self, senders: Vec<(u64, Sender)>, delivery: Delivery) {
+        let mut closed_references = Vec::new();
+
+        for (reference, sender) in senders {
+            if !sender.send(delivery.clone()).await {
+                closed_references.push(reference);
+            }
+        }
+
+        self.remove(&closed_references);
+    }
+
+    fn remove(&self, references: &[u64]) {
+        if !references.is_empty() {
+            self.subscriptions
+                .lock()
+                .unwrap()
+                .retain(|subscription| !references.contains(&subscription.reference));
+        }
+    }
+}
+
+struct Subscription {
+    reference: u64,
+    /// Shared, so that [Subscriptions::send] can match outside the lock.
+    filter: Arc<Filter>,
+    sender: Sender,
+}
+
+/// Which [EventPayload]s a subscription receives.
+pub(crate) enum Filter {
+    /// [Channel::events](crate::Channel::events)
+    All,
+    /// [Channel::on](crate::Channel::on)
+    Event(Event),
+    /// [Channel::on_matching](crate::Channel::on_matching)
+    Matching(Box<dyn Fn(&ffi::message::Event) -> bool + Send + Sync>),
+}
+
+/// What a subscription's buffer holds, so that [Subscriptions::missed] is reported in order.
+#[derive(Clone)]
+pub(crate) enum Delivery {
+    EventPayload(EventPayload),
+    Missed(u64),
+}
+
+/// The sending half for each [EventDelivery].
+#[derive(Clone)]
+enum Sender {
+    Broadcast(broadcast::Sender<Delivery>),
+    Bounded(mpsc::Sender<Delivery>),
+    Unbounded(mpsc::UnboundedSender<Delivery>),
+}
+impl Sender {
+    /// Whether the receiver is still there.
+    async fn send(&self, delivery: Delivery) -> bool {
+        match self {
+            Sender::Broadcast(sender) => sender.send(delivery).is_ok(),
+            Sender::Bounded(sender) => sender.send(delivery).await.is_ok(),
+            Sender::Unbounded(sender) => sender.send(delivery).is_ok(),
+        }
+    }
+}
+
+/// The receiving half for each [EventDelivery], used by [Events](crate::Events) and
+/// [TypedEvents](crate::TypedEvents).
+pub(crate) enum EventReceiver {
+    Broadcast(broadcast::Receiver<Delivery>),
+    Bounded(mpsc::Receiver<Delivery>),
+    Unbounded(mpsc::UnboundedReceiver<Delivery>),
+}
+impl EventReceiver {
+    pub(crate) async fn recv(&mut self) -> Result<EventPayload, EventsError> {
+        let delivery = match self {
+            EventReceiver::Broadcast(receiver) => receiver.recv().await?,
+            EventReceiver::Bounded(receiver) => {
+                receiver.recv().await.ok_or(EventsError::NoMoreEvents)?
+            }
+            EventReceiver::Unbounded(receiver) => {
+                receiver.recv().await.ok_or(EventsError::NoMoreEvents)?
+            }
+        };
+
+        match delivery {
+            Delivery::EventPayload(event_payload) => Ok(event_payload),
+            Delivery::Missed(missed_event_count) => {
+                Err(EventsError::MissedEvents { missed_event_count })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::FutureExt;
+    use serde_json::json;
+
+    use super::*;
+    use crate::rust::message::Payload;
+    use crate::testing::{json_payload, FakeServer, TIMEOUT};
+    use crate::{ChannelOptions, Topic};
+
+    fn event_payload(event: &str) -> EventPayload {
+        EventPayload {
+            event: Event::User(event.to_string()),
+            payload: json!({ "event": event }).into(),
+        }
+    }
+
+    fn try_recv(receiver: &mut EventReceiver) -> Option<Result<EventPayload, EventsError>> {
+        receiver.recv().now_or_never()
+    }
+
+    #[tokio::test]
+    async fn event_subscription_only_receives_event() {
+        let subscriptions = Subscriptions::new(10, EventDelivery::Lossy);
+        let (_, mut receiver) =
+            subscriptions.subscribe(Filter::Event(Event::User("new_msg".to_string())));
+
+        subscriptions.send(&event_payload("typing")).await;
+        subscriptions.send(&event_payload("new_msg")).await;
+
+        assert_eq!(
+            receiver.recv().await.unwrap().event,
+            Event::User("new_msg".to_string())
+        );
+        assert!(try_recv(&mut receiver).is_none());
+    }
+
+    #[tokio::test]
+    async fn matching_subscription_receives_matching_events() {
+        let subscriptions = Subscriptions::new(10, EventDelivery::Lossy);
+        let (_, mut receiver) = subscriptions.subscribe(Filter::Matching(Box::new(|event| {
+            event.to_string().starts_with("presence_")
+        })));
+
+        subscriptions.send(&event_payload("presence_state")).await;
+        subscriptions.send(&event_payload("new_msg")).await;
+        subscriptions.send(&event_payload("presence_diff")).await;
+
+        assert_eq!(
+            receiver.recv().await.unwrap().event,
+            Event::User("presence_state".to_string())
+        );
+        assert_eq!(
+            receiver.recv().await.unwrap().event,
+            Event::User("presence_diff".to_string())
+        );
+        assert!(try_recv(&mut receiver).is_none());
+    }
+
+    #[tokio::test]
+    async fn predicate_can_subscribe() {
+        let subscriptions = Arc::new(Subscriptions::new(10, EventDelivery::Lossy));
+        let predicate_subscriptions = subscriptions.clone();
+        let (_, mut receiver) =
+            subscriptions.subscribe(Filter::Matching(Box::new(move |_| {
+                predicate_subscriptions.subscribe(Filter::All);
+
+                true
+            })));
+
+        subscriptions.send(&event_payload("new_msg")).await;
+
+        assert!(receiver.recv().await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn panicking_predicate_is_unsubscribed() {
+        let subscriptions = Subscriptions::new(10, EventDelivery::Lossy);
+        let (_, mut panicking_receiver) =
+            subscriptions.subscribe(Filter::Matching(Box::new(|_| panic!("predicate"))));
+        let (_, mut receiver) = subscriptions.subscribe(Filter::All);
+
+        subscriptions.send(&event_payload("first")).await;
+        subscriptions.send(&event_payload("second")).await;
+
+        assert!(matches!(
+            panicking_receiver.recv().await,
+            Err(EventsError::NoMoreEvents)
+        ));
+        assert_eq!(
+            receiver.recv().await.unwrap().event,
+            Event::User("first".to_string())
+        );
+        assert_eq!(
+            receiver.recv().await.unwrap().event,
+            Event::User("second".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn subscriptions_lag_independently() {
+        let subscriptions = Subscriptions::new(1, EventDelivery::Lossy);
+        let (_, mut slow_receiver) =
+            subscriptions.subscribe(Filter::Event(Event::User("new_msg".to_string())));
+        let (_, mut fast_receiver) =
+            subscriptions.subscribe(Filter::Event(Event::User("typing".to_string())));
+
+        subscriptions.send(&event_payload("new_msg")).await;
+        subscriptions.send(&event_payload("new_msg")).await;
+        subscriptions.send(&event_payload("typing")).await;
+
+        assert!(matches!(
+            slow_receiver.recv().await,
+            Err(EventsError::MissedEvents {
+                missed_event_count: 1
+            })
+        ));
+        assert_eq!(
+            fast_receiver.recv().await.unwrap().event,
+            Event::User("typing".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn backpressure_waits_for_receiver() {
+        let subscriptions = Subscriptions::new(1, EventDelivery::Backpressure);
+        let (_, mut receiver) = subscriptions.subscribe(Filter::All);
+
+        subscriptions.send(&event_payload("first")).await;
+
+        let second = event_payload("second");
+        let mut second_sent = Box::pin(subscriptions.send(&second));
+        assert!((&mut second_sent).now_or_never().is_none());
+
+        assert_eq!(
+            receiver.recv().await.unwrap().event,
+            Event::User("first".to_string())
+        );
+        second_sent.await;
+        assert_eq!(
+            receiver.recv().await.unwrap().event,
+            Event::User("second".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn unbounded_never_misses_events() {
+        let subscriptions = Subscriptions::new(1, EventDelivery::Unbounded);
+        let (_, mut receiver) = subscriptions.subscribe(Filter::All);
+
+        for i in 0..100 {
+            subscriptions.send(&event_payload(&i.to_string())).await;
+        }
+
+        for i in 0..100 {
+            assert_eq!(
+                receiver.recv().await.unwrap().event,
+                Event::User(i.to_string())
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn missed_is_received_in_order() {
+        let subscriptions = Subscriptions::new(10, EventDelivery::Backpressure);
+        let (_, mut receiver) =
+            subscriptions.subscribe(Filter::Event(Event::User("new_msg".to_string())));
+
+        subscriptions.send(&event_payload("new_msg")).await;
+        subscriptions.missed(3).await;
+        subscriptions.send(&event_payload("new_msg")).await;
+
+        assert!(receiver.recv().await.is_ok());
+        assert!(matches!(
+            receiver.recv().await,
+            Err(EventsError::MissedEvents {
+                missed_event_count: 3
+            })
+        ));
+        assert!(receiver.recv().await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn unsubscribe_closes_after_buffered_events() {
+        for delivery in [
+            EventDelivery::Lossy,
+            EventDelivery::Backpressure,
+            EventDelivery::Unbounded,
+        ] {
+            let subscriptions = Subscriptions::new(10, delivery);
+            let (reference, mut receiver) =
+                subscriptions.subscribe(Filter::Event(Event::User("new_msg".to_string())));
+
+            subscriptions.send(&event_payload("new_msg")).await;
+            subscriptions.unsubscribe(reference);
+            subscriptions.send(&event_payload("new_msg")).await;
+
+            assert!(receiver.recv().await.is_ok());
+            assert!(matches!(
+                receiver.recv().await,
+                Err(EventsError::NoMoreEvents)
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn dropped_receivers_are_removed() {
+        for delivery in [
+            EventDelivery::Lossy,
+            EventDelivery::Backpressure,
+            EventDelivery::Unbounded,
+        ] {
+            let subscriptions = Subscriptions::new(10, delivery);
+            let (_, receiver) = subscriptions.subscribe(Filter::All);
+            drop(receiver);
+
+            subscriptions.send(&event_payload("new_msg")).await;
+
+            assert!(subscriptions.subscriptions.lock().unwrap().is_empty());
+        }
+    }
+
+    #[tokio::test]
+    async fn channel_on_and_off() {
+        let server = FakeServer::new();
+        let (socket, channel) = server.joined_channel("room:lobby").await;
+        let new_msgs = channel.on(ffi::message::Event::from_string("new_msg".to_string()));
+
+        server.broadcast("room:lobby", "typing", json_payload(json!({})));
+        server.broadcast("room:lobby", "new_msg", json_payload(json!({ "body": "hi" })));
+
+        let event_payload = new_msgs.event().await.unwrap();
+        assert_eq!(
+            event_payload.event,
+            ffi::message::Event::from_string("new_msg".to_string())
+        );
+
+        channel.off(new_msgs.clone());
+        server.broadcast("room:lobby", "new_msg", json_payload(json!({ "body": "bye" })));
+
+        assert!(matches!(
+            new_msgs.event().await,
+            Err(EventsError::NoMoreEvents)
+        ));
+
+        socket.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn channel_with_backpressure_delivers_every_event() {
+        let server = FakeServer::new();
+        let socket = server.connected_socket().await;
+        let channel = socket
+            .channel_with_options(
+                Topic::from_string("room:lobby".to_string()),
+                None,
+                ChannelOptions::default()
+                    .with_event_buffer_size(1)
+                    .with_event_delivery(EventDelivery::Backpressure),
+            )
+            .await
+            .unwrap();
+        let events = channel.events();
+        channel.join(TIMEOUT).await.unwrap();
+
+        for i in 0..5 {
+            server.broadcast("room:lobby", "new_msg", json_payload(json!({ "i": i })));
+        }
+
+        for i in 0..5 {
+            let event_payload = events.event().await.unwrap();
+            assert_eq!(
+                Payload::from(event_payload.payload),
+                Payload::from(json!({ "i": i }))
+            );
+        }
+
+        socket.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn stalled_backpressure_channel_does_not_stall_socket() {
+        let server = FakeServer::new();
+        let socket = server.connected_socket().await;
+        let stalled = socket
+            .channel_with_options(
+                Topic::from_string("room:stalled".to_string()),
+                None,
+                ChannelOptions::default()
+                    .with_event_buffer_size(1)
+                    .with_event_delivery(EventDelivery::Backpressure),
+            )
+            .await
+            .unwrap();
+        // Not received from until the end, so the channel waits on it after the first event.
+        let stalled_events = stalled.events();
+        stalled.join(TIMEOUT).await.unwrap();
+        let lobby = socket
+            .channel(Topic::from_string("room:lobby".to_string()), None)
+            .await
+            .unwrap();
+        let lobby_events = lobby.events();
+        lobby.join(TIMEOUT).await.unwrap();
+
+        for i in 0..100 {
+            server.push("room:stalled", "new_msg", json_payload(json!({ "i": i })));
+        }
+        server.broadcast("room:lobby", "new_msg", json_payload(json!({ "body": "hi" })));
+
+        let event_payload = tokio::time::timeout(TIMEOUT, lobby_events.event())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            Payload::from(event_payload.payload),
+            Payload::from(json!({ "body": "hi" }))
+        );
+
+        for i in 0..100 {
+            server.broadcast("room:stalled", "new_msg", json_payload(json!({ "j": i })));
+        }
+
+        // Every push is still delivered, but the broadcasts the socket couldn't queue while the
+        // channel waited are reported as missed.
+        let mut pushes = 0;
+        let mut missed = false;
+
+        while pushes < 100 || !missed {
+            match tokio::time::timeout(TIMEOUT, stalled_events.event())
+                .await
+                .unwrap()
+            {
+                Ok(event_payload) => {
+                    if Payload::from(event_payload.payload)
+                        == Payload::from(json!({ "i": pushes }))
+                    {
+                        pushes += 1;
+                    }
+                }
+                Err(EventsError::MissedEvents { .. }) => missed = true,
+                Err(error) => panic!("unexpected {:?}", error),
+            }
+        }
+
+        socket.shutdown().await.unwrap();
+    }
+}
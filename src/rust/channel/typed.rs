@@ -0,0 +1,249 @@
+//! [Channel](crate::Channel) payloads serialized from and deserialized to Rust types with [serde],
+//! without going through [JSON](crate::JSON).
+//!
+//! ```
+//! # use std::time::Duration;
+//! #
+//! # use serde::{Deserialize, Serialize};
+//! #
+//! # use phoenix_channels_client::{CallError, Channel, Event, TypedError};
+//! #
+//! #[derive(Serialize)]
+//! struct Ping {
+//!     count: u32,
+//! }
+//!
+//! #[derive(Deserialize)]
+//! struct Pong {
+//!     count: u32,
+//! }
+//!
+//! async fn ping(channel: &Channel, count: u32) -> Result<u32, TypedError<CallError>> {
+//!     let pong: Pong = channel
+//!         .call_typed(
+//!             Event::from_string("ping".to_string()),
+//!             &Ping { count },
+//!             Duration::from_secs(5),
+//!         )
+//!         .await?;
+//!
+//!     Ok(pong.count)
+//! }
+//! ```
+
+use std::marker::PhantomData;
+
+use serde::de::DeserializeOwned;
+use tokio::sync::Mutex;
+
+use crate::ffi::channel::EventsError;
+use crate::ffi::message::Event;
+use crate::rust::channel::subscriptions::EventReceiver;
+use crate::rust::message::EventPayload;
+
+/// Errors from [Channel::cast_typed](crate::Channel::cast_typed),
+/// [Channel::call_typed](crate::Channel::call_typed) and [TypedEvents::event], which wrap the `E`
+/// of their untyped counterpart.
+#[derive(Debug, thiserror::Error)]
+pub enum TypedError<E> {
+    /// Error from the untyped counterpart.
+    #[error(transparent)]
+    Channel(#[from] E),
+    /// The payload couldn't be serialized to JSON.
+    #[error("failed to serialize payload: {0}")]
+    Serialization(serde_json::Error),
+    /// The JSON payload couldn't be deserialized to the expected type or the payload was binary.
+    #[error("failed to deserialize payload: {0}")]
+    Deserialization(serde_json::Error),
+}
+
+/// The [TypedEventPayload::event] sent by the server along with the [TypedEventPayload::payload]
+/// deserialized from the data sent for that [TypedEventPayload::event].
+#[derive(Clone, Debug)]
+pub struct TypedEventPayload<T> {
+    /// The [Event] name.
+    pub event: Event,
+    /// The data sent for the [TypedEventPayload::event].
+    pub payload: T,
+}
+
+/// Waits for events sent from the server and deserializes their payloads to `T`, like
+/// [Events](crate::Events).
+pub struct TypedEvents<T> {
+    receiver: Mutex<EventReceiver>,
+    payload: PhantomData<fn() -> T>,
+}
+impl<T: DeserializeOwned> TypedEvents<T> {
+    /// Wait for next [TypedEventPayload] sent from the server.
+    ///
+    /// An event with a payload that can't be deserialized to `T`, such as a
+    /// [PhoenixEvent](crate::PhoenixEvent) with an empty payload, is returned as a
+    /// [TypedError::Deserialization] and the next call waits for the event after it.
+    pub async fn event(&self) -> Result<TypedEventPayload<T>, TypedError<EventsError>> {
+        let EventPayload { event, payload } = self
+            .receiver
+            .lock()
+            .await
+            .recv()
+            .await?;
+        let payload = payload
+            .deserialize()
+            .map_err(TypedError::Deserialization)?;
+
+        Ok(TypedEventPayload {
+            event: event.into(),
+            payload,
+        })
+    }
+}
+impl<T> From<EventReceiver> for TypedEvents<T> {
+    fn from(receiver: EventReceiver) -> Self {
+        Self {
+            receiver: Mutex::new(receiver),
+            payload: PhantomData,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+    use serde_json::json;
+
+    use crate::testing::{json_payload, FakeServer, ScriptedReply, TIMEOUT};
+    use crate::CallError;
+
+    use super::*;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Message {
+        body: String,
+    }
+
+    #[tokio::test]
+    async fn cast_typed_serializes_payload() {
+        let server = FakeServer::new();
+        let (socket, channel) = server.joined_channel("room:lobby").await;
+        server.next_received().await;
+
+        channel
+            .cast_typed(
+                Event::from_string("say".to_string()),
+                &Message {
+                    body: "hi".to_string(),
+                },
+            )
+            .await
+            .unwrap();
+
+        let received = server.next_received().await;
+        assert_eq!(received.event, Event::from_string("say".to_string()));
+        assert_eq!(received.payload, json_payload(json!({ "body": "hi" })));
+
+        socket.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn call_typed_deserializes_reply() {
+        let server = FakeServer::new();
+        server.reply_to_call(
+            "room:lobby",
+            "echo",
+            ScriptedReply::Ok(json_payload(json!({ "body": "hi" }))),
+        );
+        let (socket, channel) = server.joined_channel("room:lobby").await;
+
+        let reply: Message = channel
+            .call_typed(
+                Event::from_string("echo".to_string()),
+                &json!({ "body": "hi" }),
+                TIMEOUT,
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            reply,
+            Message {
+                body: "hi".to_string()
+            }
+        );
+
+        socket.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn call_typed_with_mismatched_reply_errors() {
+        let server = FakeServer::new();
+        server.reply_to_call(
+            "room:lobby",
+            "echo",
+            ScriptedReply::Ok(json_payload(json!({ "count": 1 }))),
+        );
+        let (socket, channel) = server.joined_channel("room:lobby").await;
+
+        match channel
+            .call_typed::<_, Message>(Event::from_string("echo".to_string()), &(), TIMEOUT)
+            .await
+        {
+            Err(TypedError::Deserialization(_)) => (),
+            other => panic!("reply deserialized: {:?}", other),
+        }
+
+        socket.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn call_typed_with_error_reply_is_channel_error() {
+        let server = FakeServer::new();
+        server.reply_to_call(
+            "room:lobby",
+            "echo",
+            ScriptedReply::Error(json_payload(json!({ "reason": "no" }))),
+        );
+        let (socket, channel) = server.joined_channel("room:lobby").await;
+
+        match channel
+            .call_typed::<_, Message>(Event::from_string("echo".to_string()), &(), TIMEOUT)
+            .await
+        {
+            Err(TypedError::Channel(CallError::Reply { .. })) => (),
+            other => panic!("reply not an error: {:?}", other),
+        }
+
+        socket.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn events_as_deserializes_payloads() {
+        let server = FakeServer::new();
+        let (socket, channel) = server.joined_channel("room:lobby").await;
+        let events = channel.events_as::<Message>();
+
+        server.broadcast(
+            "room:lobby",
+            "new_msg",
+            json_payload(json!({ "extra": true })),
+        );
+        server.broadcast(
+            "room:lobby",
+            "new_msg",
+            json_payload(json!({ "body": "hi" })),
+        );
+
+        assert!(matches!(
+            events.event().await,
+            Err(TypedError::Deserialization(_))
+        ));
+
+        let TypedEventPayload { event, payload } = events.event().await.unwrap();
+        assert_eq!(event, Event::from_string("new_msg".to_string()));
+        assert_eq!(
+            payload,
+            Message {
+                body: "hi".to_string()
+            }
+        );
+
+        socket.shutdown().await.unwrap();
+    }
+}
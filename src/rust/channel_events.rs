@@ -0,0 +1,132 @@
+//! Enums of the events a [Channel](crate::Channel) sends or receives, each with its own payload
+//! type, so that the protocol of a Phoenix channel module is checked at compile time instead of
+//! by matching [Event::User] names by hand.
+//!
+//! With the `derive` feature, [ChannelEvents] can be derived for enums whose variants are either
+//! unit variants, for events without a payload, or newtype variants whose field implements
+//! [serde::Serialize] and [serde::Deserialize].  Each variant's event name is its name in
+//! `snake_case`, unless renamed with `#[channel_event(rename = "...")]`.
+//!
+//! ```
+//! # #[cfg(feature = "derive")]
+//! # {
+//! # use serde::{Deserialize, Serialize};
+//! #
+//! # use phoenix_channels_client::{ChannelEvents, Event, EventPayload};
+//! #
+//! #[derive(Debug, PartialEq, Serialize, Deserialize)]
+//! struct Message {
+//!     body: String,
+//! }
+//!
+//! #[derive(Debug, PartialEq, ChannelEvents)]
+//! enum RoomEvent {
+//!     #[channel_event(rename = "new_msg")]
+//!     NewMessage(Message),
+//!     Typing,
+//! }
+//!
+//! let (event, payload) = RoomEvent::NewMessage(Message { body: "hi".to_string() })
+//!     .into_event_payload()
+//!     .unwrap();
+//! assert_eq!(event, Event::from_string("new_msg".to_string()));
+//!
+//! assert_eq!(
+//!     RoomEvent::from_event_payload(EventPayload { event, payload }).unwrap(),
+//!     RoomEvent::NewMessage(Message { body: "hi".to_string() })
+//! );
+//! # }
+//! ```
+
+use crate::ffi::channel::EventPayload;
+use crate::ffi::message::{Event, Payload};
+
+/// Converts between a Rust type, usually an enum with a variant per event, and the [Event] and
+/// [Payload] passed to [Channel::cast](crate::Channel::cast) and
+/// [Channel::call](crate::Channel::call) or received from [Events](crate::Events).
+pub trait ChannelEvents: Sized {
+    /// The [Event] and [Payload] to send for `self`.
+    fn into_event_payload(self) -> Result<(Event, Payload), ChannelEventsError>;
+
+    /// Decodes an [EventPayload] received from the server.
+    fn from_event_payload(event_payload: EventPayload) -> Result<Self, ChannelEventsError>;
+}
+
+/// Errors from [ChannelEvents].
+#[derive(Debug, thiserror::Error)]
+pub enum ChannelEventsError {
+    /// The [Event] isn't one of the events of the [ChannelEvents] type.
+    #[error("unknown event {event}")]
+    UnknownEvent {
+        /// The [Event] received.
+        event: Event,
+    },
+    /// The payload for `event` couldn't be serialized to JSON.
+    #[error("failed to serialize {event} payload: {error}")]
+    Serialization {
+        /// The [Event] being sent.
+        event: Event,
+        /// Error from [serde_json].
+        error: serde_json::Error,
+    },
+    /// The [Payload] for `event` couldn't be deserialized to the variant's payload type or was
+    /// binary.
+    #[error("failed to deserialize {event} payload: {error}")]
+    Deserialization {
+        /// The [Event] received.
+        event: Event,
+        /// Error from [serde_json].
+        error: serde_json::Error,
+    },
+}
+
+/// Used by the code generated by `#[derive(ChannelEvents)]`.
+#[doc(hidden)]
+pub mod private {
+    use serde::de::DeserializeOwned;
+    use serde::Serialize;
+
+    use crate::rust;
+
+    use super::*;
+
+    pub fn encode<T: Serialize + ?Sized>(
+        name: &str,
+        payload: &T,
+    ) -> Result<(Event, Payload), ChannelEventsError> {
+        let event = Event::User {
+            user: name.to_string(),
+        };
+
+        match serde_json::to_value(payload) {
+            Ok(value) => Ok((event, rust::message::Payload::from(value).into())),
+            Err(error) => Err(ChannelEventsError::Serialization { event, error }),
+        }
+    }
+
+    pub fn encode_empty(name: &str) -> Result<(Event, Payload), ChannelEventsError> {
+        Ok((
+            Event::User {
+                user: name.to_string(),
+            },
+            rust::message::Payload::default().into(),
+        ))
+    }
+
+    pub fn decode<T: DeserializeOwned>(
+        event: Event,
+        payload: Payload,
+    ) -> Result<T, ChannelEventsError> {
+        rust::message::Payload::from(payload)
+            .deserialize()
+            .map_err(|error| ChannelEventsError::Deserialization { event, error })
+    }
+
+    /// The name of [Event::User], as [Event::Phoenix] are never one of the events.
+    pub fn user_event_name(event: &Event) -> Option<&str> {
+        match event {
+            Event::User { user } => Some(user.as_str()),
+            Event::Phoenix { .. } => None,
+        }
+    }
+}
@@ -5,10 +5,13 @@ use serde::{Deserialize, Serialize};
 
 use crate::rust::reference::Reference;
 
+/// Identifies a single join of a [Channel](crate::Channel), so that messages for an earlier join
+/// of the same topic can be told apart.
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 #[repr(transparent)]
 pub struct JoinReference(Reference);
 impl JoinReference {
+    /// A new, unique [JoinReference].
     pub fn new() -> Self {
         Self(Reference::new())
     }
@@ -22,6 +25,11 @@ impl Debug for JoinReference {
         }
     }
 }
+impl Default for JoinReference {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 impl Display for JoinReference {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", self.0)
@@ -0,0 +1,531 @@
+//! Merges the rendered diffs sent by `Phoenix.LiveView` into the rendered tree and renders the
+//! tree to HTML, as `Rendered` does in `phoenix_live_view.js`.
+
+use std::collections::HashMap;
+
+use serde_json::{Map, Value};
+
+/// The event `Phoenix.LiveView` sends with a diff when the LiveView re-renders on its own.
+pub(crate) const DIFF_EVENT: &str = "diff";
+/// The event pushed for client events, such as `phx-click`, whose reply may contain a diff.
+pub(crate) const EVENT_EVENT: &str = "event";
+/// The event `Phoenix.LiveView` sends for `redirect/2`.
+pub(crate) const REDIRECT_EVENT: &str = "redirect";
+/// The event `Phoenix.LiveView` sends for `push_navigate/2`.
+pub(crate) const LIVE_REDIRECT_EVENT: &str = "live_redirect";
+/// The event `Phoenix.LiveView` sends for `push_patch/2`.
+pub(crate) const LIVE_PATCH_EVENT: &str = "live_patch";
+
+const COMPONENTS: &str = "c";
+const DYNAMICS: &str = "d";
+const EVENTS: &str = "e";
+const REPLY: &str = "r";
+const STATIC: &str = "s";
+const TEMPLATES: &str = "p";
+const TITLE: &str = "t";
+
+/// A diff that was merged into a [Rendered].
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct Merged {
+    /// The diff without [Merged::title], [Merged::events] or [Merged::reply].
+    pub(crate) diff: Map<String, Value>,
+    /// The IDs of the components in the diff, in order.
+    pub(crate) changed_components: Vec<u32>,
+    /// The new page title set with `page_title`.
+    pub(crate) title: Option<String>,
+    /// The events pushed with `push_event/3` as `(event, payload)`.
+    pub(crate) events: Vec<(String, Value)>,
+    /// The reply from a `handle_event/3` that returned `{:reply, map, socket}`.
+    pub(crate) reply: Option<Value>,
+}
+impl Merged {
+    fn extract(mut diff: Map<String, Value>) -> Self {
+        let title = match diff.remove(TITLE) {
+            Some(Value::String(title)) => Some(title),
+            _ => None,
+        };
+        let events = match diff.remove(EVENTS) {
+            Some(Value::Array(events)) => events
+                .into_iter()
+                .filter_map(|event| match event {
+                    Value::Array(mut event_payload) if event_payload.len() == 2 => {
+                        let payload = event_payload.pop().unwrap();
+
+                        match event_payload.pop().unwrap() {
+                            Value::String(event) => Some((event, payload)),
+                            _ => None,
+                        }
+                    }
+                    _ => None,
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+        let reply = diff.remove(REPLY);
+
+        Self {
+            diff,
+            changed_components: Vec::new(),
+            title,
+            events,
+            reply,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub(crate) enum RenderedError {
+    #[error("rendered is not an object")]
+    NotAnObject,
+    #[error("component {0} is not an object")]
+    InvalidComponent(String),
+    #[error("component {0} shares statics with a missing component")]
+    MissingComponent(String),
+}
+
+/// The rendered tree of a LiveView: static strings under `s` interleaved with the dynamics under
+/// `0`, `1`, etc., and the components by ID under `c`.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Rendered {
+    tree: Map<String, Value>,
+}
+impl Rendered {
+    /// The tree from the `rendered` of a join reply.
+    pub(crate) fn new(rendered: Value) -> Result<(Self, Merged), RenderedError> {
+        let mut rendered_tree = Self { tree: Map::new() };
+        let merged = rendered_tree.merge_diff(rendered)?;
+
+        Ok((rendered_tree, merged))
+    }
+
+    pub(crate) fn tree(&self) -> &Map<String, Value> {
+        &self.tree
+    }
+
+    /// Merges `diff` into the tree: parts with statics replace the existing part, while parts
+    /// without statics only update the dynamics they contain.  Components that share statics
+    /// with another component have the statics copied.
+    ///
+    /// The tree is unchanged if it errors.
+    pub(crate) fn merge_diff(&mut self, diff: Value) -> Result<Merged, RenderedError> {
+        let mut merged = match diff {
+            Value::Object(diff) => Merged::extract(diff),
+            _ => return Err(RenderedError::NotAnObject),
+        };
+        let mut diff = merged.diff.clone();
+        let new_components = match diff.remove(COMPONENTS) {
+            Some(Value::Object(new_components)) => new_components,
+            _ => Map::new(),
+        };
+
+        // A diff with statics replaces the whole tree, including the components.
+        let replace = diff.contains_key(STATIC);
+        let no_components = Map::new();
+        let old_components = match self.tree.get(COMPONENTS) {
+            Some(Value::Object(old_components)) if !replace => old_components,
+            _ => &no_components,
+        };
+        let mut cache = HashMap::new();
+        let mut resolved_components = Vec::with_capacity(new_components.len());
+
+        for cid in new_components.keys() {
+            let component = find_component(cid, &new_components, old_components, &mut cache, 0)?;
+            resolved_components.push((cid.clone(), component));
+        }
+
+        if replace {
+            self.tree = diff;
+        } else {
+            do_mutable_merge(&mut self.tree, diff);
+        }
+
+        let components = match self
+            .tree
+            .entry(COMPONENTS)
+            .or_insert_with(|| Value::Object(Map::new()))
+        {
+            Value::Object(components) => components,
+            components => {
+                *components = Value::Object(Map::new());
+                components.as_object_mut().unwrap()
+            }
+        };
+
+        for (cid, component) in resolved_components {
+            if let Ok(cid) = cid.parse() {
+                merged.changed_components.push(cid);
+            }
+
+            components.insert(cid, Value::Object(component));
+        }
+
+        Ok(merged)
+    }
+
+    /// The HTML the tree renders, without the attributes `phoenix_live_view.js` adds to track
+    /// components.
+    pub(crate) fn to_html(&self) -> String {
+        let empty = Map::new();
+        let components = match self.tree.get(COMPONENTS) {
+            Some(Value::Object(components)) => components,
+            _ => &empty,
+        };
+        let mut buffer = String::new();
+        to_buffer(&self.tree, components, None, &mut buffer);
+
+        buffer
+    }
+}
+
+fn do_mutable_merge(target: &mut Map<String, Value>, source: Map<String, Value>) {
+    for (key, value) in source {
+        match (target.get_mut(&key), value) {
+            (Some(Value::Object(target_value)), Value::Object(value))
+                if !value.contains_key(STATIC) =>
+            {
+                do_mutable_merge(target_value, value)
+            }
+            (_, value) => {
+                target.insert(key, value);
+            }
+        }
+    }
+}
+
+/// Merges a copy of `target` with `source`.
+fn clone_merge(target: &Map<String, Value>, source: &Map<String, Value>) -> Map<String, Value> {
+    let mut merged = target.clone();
+    do_mutable_merge(&mut merged, source.clone());
+
+    merged
+}
+
+/// The component `cid` from `new_components` after merging it with the component it shares
+/// statics with or with its previous version in `old_components`, like `cachedFindComponent` in
+/// `phoenix_live_view.js`.
+///
+/// A positive `s` shares the statics of a component in the same diff, while a negative `s` shares
+/// the statics of a component already in the tree.
+fn find_component(
+    cid: &str,
+    new_components: &Map<String, Value>,
+    old_components: &Map<String, Value>,
+    cache: &mut HashMap<String, Map<String, Value>>,
+    depth: usize,
+) -> Result<Map<String, Value>, RenderedError> {
+    if let Some(component) = cache.get(cid) {
+        return Ok(component.clone());
+    }
+
+    // Each component can only share statics with another, so deeper chains are cycles.
+    if depth > new_components.len() {
+        return Err(RenderedError::MissingComponent(cid.to_string()));
+    }
+
+    let component_diff = match new_components.get(cid) {
+        Some(Value::Object(component_diff)) => component_diff,
+        Some(_) => return Err(RenderedError::InvalidComponent(cid.to_string())),
+        None => return Err(RenderedError::MissingComponent(cid.to_string())),
+    };
+
+    let component = match component_diff.get(STATIC).and_then(Value::as_i64) {
+        Some(shared_cid) => {
+            let shared_component = if shared_cid > 0 {
+                find_component(
+                    &shared_cid.to_string(),
+                    new_components,
+                    old_components,
+                    cache,
+                    depth + 1,
+                )
+                .map_err(|_| RenderedError::MissingComponent(cid.to_string()))?
+            } else {
+                match old_components.get(&(-shared_cid).to_string()) {
+                    Some(Value::Object(shared_component)) => shared_component.clone(),
+                    _ => return Err(RenderedError::MissingComponent(cid.to_string())),
+                }
+            };
+            let statics = shared_component.get(STATIC).cloned();
+            let mut component = clone_merge(&shared_component, component_diff);
+
+            match statics {
+                Some(statics) => component.insert(STATIC.to_string(), statics),
+                None => component.remove(STATIC),
+            };
+
+            component
+        }
+        None => match old_components.get(cid) {
+            Some(Value::Object(old_component)) if !component_diff.contains_key(STATIC) => {
+                clone_merge(old_component, component_diff)
+            }
+            _ => component_diff.clone(),
+        },
+    };
+
+    cache.insert(cid.to_string(), component.clone());
+
+    Ok(component)
+}
+
+/// The statics of a part, which are the index of a template in `templates` when the part is in
+/// a comprehension that shares its templates.
+fn statics<'a>(
+    part: &'a Map<String, Value>,
+    templates: Option<&'a Map<String, Value>>,
+) -> &'a [Value] {
+    let statics = match part.get(STATIC) {
+        Some(Value::Number(template)) => {
+            templates.and_then(|templates| templates.get(&template.to_string()))
+        }
+        statics => statics,
+    };
+
+    match statics {
+        Some(Value::Array(statics)) => statics,
+        _ => &[],
+    }
+}
+
+fn to_buffer(
+    part: &Map<String, Value>,
+    components: &Map<String, Value>,
+    templates: Option<&Map<String, Value>>,
+    buffer: &mut String,
+) {
+    let templates = templates.or(match part.get(TEMPLATES) {
+        Some(Value::Object(templates)) => Some(templates),
+        _ => None,
+    });
+    let statics = statics(part, templates);
+
+    match part.get(DYNAMICS) {
+        Some(Value::Array(rows)) => {
+            for row in rows {
+                let dynamics = match row {
+                    Value::Array(dynamics) => dynamics.as_slice(),
+                    _ => &[],
+                };
+
+                interleave(
+                    statics,
+                    |index| dynamics.get(index),
+                    components,
+                    templates,
+                    buffer,
+                );
+            }
+        }
+        _ => interleave(
+            statics,
+            |index| part.get(&index.to_string()),
+            components,
+            templates,
+            buffer,
+        ),
+    }
+}
+
+/// Appends `statics` to `buffer` with the dynamic at each index between them.
+fn interleave<'a, F>(
+    statics: &[Value],
+    dynamic: F,
+    components: &Map<String, Value>,
+    templates: Option<&Map<String, Value>>,
+    buffer: &mut String,
+) where
+    F: Fn(usize) -> Option<&'a Value>,
+{
+    for (index, r#static) in statics.iter().enumerate() {
+        if index > 0 {
+            if let Some(dynamic) = dynamic(index - 1) {
+                dynamic_to_buffer(dynamic, components, templates, buffer);
+            }
+        }
+
+        if let Value::String(r#static) = r#static {
+            buffer.push_str(r#static);
+        }
+    }
+}
+
+fn dynamic_to_buffer(
+    dynamic: &Value,
+    components: &Map<String, Value>,
+    templates: Option<&Map<String, Value>>,
+    buffer: &mut String,
+) {
+    match dynamic {
+        // Components are rendered in their own tree, so they don't use the comprehension's
+        // templates.
+        Value::Number(cid) => {
+            if let Some(Value::Object(component)) = components.get(&cid.to_string()) {
+                to_buffer(component, components, None, buffer)
+            }
+        }
+        Value::Object(part) => to_buffer(part, components, templates, buffer),
+        Value::String(string) => buffer.push_str(string),
+        Value::Null | Value::Bool(_) | Value::Array(_) => (),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn rendered(rendered: Value) -> Rendered {
+        Rendered::new(rendered).unwrap().0
+    }
+
+    #[test]
+    fn renders_statics_interleaved_with_dynamics() {
+        let rendered = rendered(json!({
+            "0": "Hello",
+            "1": { "0": "world", "s": ["<b>", "</b>"] },
+            "s": ["<p>", " ", "!</p>"]
+        }));
+
+        assert_eq!(rendered.to_html(), "<p>Hello <b>world</b>!</p>");
+    }
+
+    #[test]
+    fn diff_without_statics_updates_dynamics() {
+        let mut rendered = rendered(json!({
+            "0": "Hello",
+            "1": { "0": "world", "s": ["<b>", "</b>"] },
+            "s": ["<p>", " ", "!</p>"]
+        }));
+
+        let merged = rendered
+            .merge_diff(json!({ "1": { "0": "there" } }))
+            .unwrap();
+
+        assert_eq!(rendered.to_html(), "<p>Hello <b>there</b>!</p>");
+        assert!(merged.changed_components.is_empty());
+    }
+
+    #[test]
+    fn diff_with_statics_replaces_part() {
+        let mut rendered = rendered(json!({
+            "0": { "0": "world", "1": "!", "s": ["<b>", "", "</b>"] },
+            "s": ["<p>", "</p>"]
+        }));
+
+        rendered
+            .merge_diff(json!({ "0": { "0": "gone", "s": ["<i>", "</i>"] } }))
+            .unwrap();
+
+        assert_eq!(rendered.to_html(), "<p><i>gone</i></p>");
+        assert_eq!(
+            rendered.tree()["0"],
+            json!({ "0": "gone", "s": ["<i>", "</i>"] })
+        );
+    }
+
+    #[test]
+    fn comprehension_dynamics_are_replaced() {
+        let mut rendered = rendered(json!({
+            "0": { "d": [["a"], ["b"]], "s": ["<li>", "</li>"] },
+            "s": ["<ul>", "</ul>"]
+        }));
+        assert_eq!(rendered.to_html(), "<ul><li>a</li><li>b</li></ul>");
+
+        rendered
+            .merge_diff(json!({ "0": { "d": [["c"]] } }))
+            .unwrap();
+
+        assert_eq!(rendered.to_html(), "<ul><li>c</li></ul>");
+    }
+
+    #[test]
+    fn comprehension_templates_are_shared() {
+        let rendered = rendered(json!({
+            "0": {
+                "d": [[{ "0": "x", "s": 0 }], [{ "0": "y", "s": 0 }]],
+                "p": { "0": ["<b>", "</b>"] },
+                "s": ["<li>", "</li>"]
+            },
+            "s": ["<ul>", "</ul>"]
+        }));
+
+        assert_eq!(
+            rendered.to_html(),
+            "<ul><li><b>x</b></li><li><b>y</b></li></ul>"
+        );
+    }
+
+    #[test]
+    fn components_share_statics() {
+        let mut rendered = rendered(json!({
+            "0": 1,
+            "c": { "1": { "0": "a", "s": ["<i>", "</i>"] } },
+            "s": ["<div>", "</div>"]
+        }));
+        assert_eq!(rendered.to_html(), "<div><i>a</i></div>");
+
+        let merged = rendered
+            .merge_diff(json!({
+                "0": { "0": 1, "1": 2, "2": 3, "s": ["", "", "", ""] },
+                "c": {
+                    "1": { "0": "b" },
+                    "2": { "0": "c", "s": -1 },
+                    "3": { "0": "d", "s": 2 }
+                }
+            }))
+            .unwrap();
+
+        assert_eq!(merged.changed_components, vec![1, 2, 3]);
+        assert_eq!(rendered.to_html(), "<div><i>b</i><i>c</i><i>d</i></div>");
+        assert_eq!(
+            rendered.tree()["c"]["3"],
+            json!({ "0": "d", "s": ["<i>", "</i>"] })
+        );
+    }
+
+    #[test]
+    fn title_events_and_reply_are_extracted() {
+        let mut rendered = rendered(json!({ "0": "a", "s": ["", ""] }));
+
+        let merged = rendered
+            .merge_diff(json!({
+                "0": "b",
+                "t": "New title",
+                "e": [["highlight", { "id": 1 }]],
+                "r": { "ok": true }
+            }))
+            .unwrap();
+
+        assert_eq!(
+            merged,
+            Merged {
+                diff: json!({ "0": "b" }).as_object().unwrap().clone(),
+                changed_components: vec![],
+                title: Some("New title".to_string()),
+                events: vec![("highlight".to_string(), json!({ "id": 1 }))],
+                reply: Some(json!({ "ok": true })),
+            }
+        );
+        assert_eq!(rendered.to_html(), "b");
+    }
+
+    #[test]
+    fn invalid_diffs_error_without_changing_tree() {
+        let mut rendered = rendered(json!({ "0": "a", "s": ["", ""] }));
+
+        assert_eq!(
+            rendered.merge_diff(json!([])),
+            Err(RenderedError::NotAnObject)
+        );
+        assert_eq!(
+            rendered.merge_diff(json!({ "0": "b", "c": { "1": { "s": -2 } } })),
+            Err(RenderedError::MissingComponent("1".to_string()))
+        );
+        assert_eq!(
+            rendered.merge_diff(json!({ "c": { "1": { "s": 2 }, "2": { "s": 1 } } })),
+            Err(RenderedError::MissingComponent("1".to_string()))
+        );
+        assert_eq!(rendered.to_html(), "a");
+    }
+}
@@ -5,11 +5,13 @@ use std::sync::Arc;
 
 use bytes::{BufMut, Bytes};
 use flexstr::SharedStr;
+use serde::de::DeserializeOwned;
 use serde_json::Value;
 use tokio_tungstenite::tungstenite::Message as SocketMessage;
 
 use crate::ffi;
 use crate::ffi::message::PhoenixEvent;
+use crate::ffi::serializer::SerializerVersion;
 use crate::ffi::topic::Topic;
 use crate::rust::join_reference::JoinReference;
 use crate::rust::reference::Reference;
@@ -45,7 +47,7 @@ pub struct MessageEncodingError;
 /// event, and payload are intended to be consumed directly. The topic name is only
 /// used when joining a channel, and the references are part of the multiplexing scheme.
 #[doc(hidden)]
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum Message {
     Control(Control),
     Broadcast(Broadcast),
@@ -62,11 +64,21 @@ impl Message {
             Self::Push(ref msg) => &msg.event_payload.payload,
         }
     }
+
+    /// Returns a mutable reference to the data associated with this message.
+    pub fn payload_mut(&mut self) -> &mut Payload {
+        match self {
+            Self::Control(ref mut msg) => &mut msg.payload,
+            Self::Broadcast(ref mut msg) => &mut msg.event_payload.payload,
+            Self::Reply(ref mut msg) => &mut msg.payload,
+            Self::Push(ref mut msg) => &mut msg.event_payload.payload,
+        }
+    }
 }
 
 /// Represents a reply to a message
 #[doc(hidden)]
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Control {
     /// The event for this message type is always a `PhoenixEvent`
     pub event: Event,
@@ -85,7 +97,7 @@ pub struct Broadcast {
 
 /// Represents a reply to a message
 #[doc(hidden)]
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Reply {
     pub topic: Arc<Topic>,
     /// The event for this message type is always `PhoenixEvent::Reply`
@@ -103,7 +115,7 @@ pub struct Reply {
 
 /// Represents a message being sent by a specific member of a channel, to the other members of the channel
 #[doc(hidden)]
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Push {
     pub topic: Arc<Topic>,
     pub event_payload: EventPayload,
@@ -138,10 +150,20 @@ impl From<Push> for EventPayload {
 impl Message {
     /// Encodes this message for transport over WebSocket
     pub fn encode(self) -> Result<SocketMessage, MessageEncodingError> {
-        if self.payload().is_binary() {
-            Ok(SocketMessage::Binary(self.encode_binary()?))
-        } else {
-            Ok(SocketMessage::Text(self.encode_json()?))
+        self.encode_with(SerializerVersion::V2)
+    }
+
+    /// Encodes this message for transport over WebSocket in the format of `serializer_version`.
+    /// [SerializerVersion::V1] can't encode binary payloads.
+    pub fn encode_with(
+        self,
+        serializer_version: SerializerVersion,
+    ) -> Result<SocketMessage, MessageEncodingError> {
+        match (serializer_version, self.payload().is_binary()) {
+            (SerializerVersion::V1, true) => Err(MessageEncodingError),
+            (SerializerVersion::V1, false) => Ok(SocketMessage::Text(self.encode_json_v1()?)),
+            (SerializerVersion::V2, true) => Ok(SocketMessage::Binary(self.encode_binary()?)),
+            (SerializerVersion::V2, false) => Ok(SocketMessage::Text(self.encode_json()?)),
         }
     }
 
@@ -256,21 +278,60 @@ impl Message {
 
     /// Decodes the given WebSocket message as `Message`
     pub fn decode(message: SocketMessage) -> Result<Self, MessageDecodingError> {
-        match message {
-            SocketMessage::Text(ref text) => Self::decode_json(text.as_str()),
-            SocketMessage::Binary(ref bytes) => Self::decode_binary(bytes.as_slice()),
-            other => panic!("invalid message type: {:#?}", &other),
+        Self::decode_with(message, SerializerVersion::V2)
+    }
+
+    /// Decodes the given WebSocket message in the format of `serializer_version` as `Message`
+    pub fn decode_with(
+        message: SocketMessage,
+        serializer_version: SerializerVersion,
+    ) -> Result<Self, MessageDecodingError> {
+        match (serializer_version, message) {
+            (SerializerVersion::V1, SocketMessage::Text(ref text)) => {
+                Self::decode_json_v1(text.as_str())
+            }
+            (SerializerVersion::V1, SocketMessage::Binary(_)) => Err(MessageDecodingError::Invalid(
+                "the V1 serializer has no binary format".to_string(),
+            )),
+            (SerializerVersion::V2, SocketMessage::Text(ref text)) => {
+                Self::decode_json(text.as_str())
+            }
+            (SerializerVersion::V2, SocketMessage::Binary(ref bytes)) => {
+                Self::decode_binary(bytes.as_slice())
+            }
+            (_, other) => panic!("invalid message type: {:#?}", &other),
         }
     }
 
     fn encode_json(self) -> Result<String, MessageEncodingError> {
-        let value = Value::Array(match self {
+        serde_json::to_string(&Value::Array(self.into_json_fields().to_vec()))
+            .map_err(|_| MessageEncodingError)
+    }
+
+    /// Encodes the same fields as [Message::encode_json] as a map, the representation used by the
+    /// V1 Phoenix serializer.
+    fn encode_json_v1(self) -> Result<String, MessageEncodingError> {
+        let [join_reference, reference, topic, event, payload] = self.into_json_fields();
+        let value = serde_json::json!({
+            "topic": topic,
+            "event": event,
+            "payload": payload,
+            "ref": reference,
+            "join_ref": join_reference,
+        });
+
+        serde_json::to_string(&value).map_err(|_| MessageEncodingError)
+    }
+
+    /// `[join_ref, ref, topic, event, payload]`, which must be JSON.
+    pub(crate) fn into_json_fields(self) -> [Value; 5] {
+        match self {
             Self::Control(Control {
                 event,
                 payload,
                 reference: Option::None,
             }) => {
-                vec![
+                [
                     Value::Null,
                     Value::Null,
                     serde_json::to_value("phoenix").unwrap(),
@@ -283,7 +344,7 @@ impl Message {
                 payload,
                 reference: Some(reference),
             }) => {
-                vec![
+                [
                     Value::Null,
                     serde_json::to_value(reference).unwrap(),
                     serde_json::to_value("phoenix").unwrap(),
@@ -295,7 +356,7 @@ impl Message {
                 topic,
                 event_payload,
             }) => {
-                vec![
+                [
                     Value::Null,
                     Value::Null,
                     serde_json::to_value(topic.as_ref()).unwrap(),
@@ -311,7 +372,7 @@ impl Message {
                 reference,
                 ..
             }) => {
-                vec![
+                [
                     serde_json::to_value(join_reference).unwrap(),
                     serde_json::to_value(reference).unwrap(),
                     serde_json::to_value(topic.as_ref()).unwrap(),
@@ -325,7 +386,7 @@ impl Message {
                 topic,
                 event_payload,
             }) => {
-                vec![
+                [
                     serde_json::to_value(join_reference).unwrap(),
                     serde_json::to_value(reference).unwrap(),
                     serde_json::to_value(topic.as_ref()).unwrap(),
@@ -333,9 +394,7 @@ impl Message {
                     Value::clone(event_payload.payload.value().unwrap()),
                 ]
             }
-        });
-
-        serde_json::to_string(&value).map_err(|_| MessageEncodingError)
+        }
     }
 
     /// Parses a JSON-encoded message (using the Phoenix JSON v2 serializer representation)
@@ -343,111 +402,7 @@ impl Message {
         let value = serde_json::from_str(json)?;
         match value {
             // This is the representation used by the v2 Phoenix serializer
-            Value::Array(mut fields) if fields.len() == 5 => {
-                let payload = fields.pop().unwrap();
-                let event = match fields.pop().unwrap() {
-                    Value::String(s) => Event::from(s),
-                    other => {
-                        return Err(MessageDecodingError::Invalid(format!(
-                            "expected event to be a string, got: {:#?}",
-                            &other
-                        )))
-                    }
-                };
-                let topic = match fields.pop().unwrap() {
-                    Value::String(s) => Topic::from_string(s),
-                    other => {
-                        return Err(MessageDecodingError::Invalid(format!(
-                            "expected topic to be a string, got: {:#?}",
-                            &other
-                        )))
-                    }
-                };
-                let reference = match fields.pop().unwrap() {
-                    Value::Null => None,
-                    Value::String(s) => Some(s.into()),
-                    other => {
-                        return Err(MessageDecodingError::Invalid(format!(
-                            "expected ref to be a string or null, got: {:#?}",
-                            &other
-                        )))
-                    }
-                };
-                let join_reference: Option<JoinReference> = match fields.pop().unwrap() {
-                    Value::Null => None,
-                    Value::String(s) => Some(s.into()),
-                    other => {
-                        return Err(MessageDecodingError::Invalid(format!(
-                            "expected join_ref to be a string or null, got: {:#?}",
-                            &other
-                        )))
-                    }
-                };
-                match (join_reference, reference) {
-                    (None, None) => Ok(Message::Broadcast(Broadcast {
-                        topic,
-                        event_payload: EventPayload {
-                            event,
-                            payload: payload.into(),
-                        },
-                    })),
-                    (Some(join_reference), None) => Ok(Message::Push(Push {
-                        topic,
-                        event_payload: EventPayload {
-                            event,
-                            payload: payload.into(),
-                        },
-                        join_reference,
-                        reference: None,
-                    })),
-                    (Some(join_reference), Some(reference)) => match event {
-                        Event::Phoenix(PhoenixEvent::Reply) => {
-                            let (status, payload) = match payload {
-                                Value::Object(mut map) => {
-                                    match (map.remove("status"), map.remove("response")) {
-                                        (Some(Value::String(s)), Some(payload)) => {
-                                            (s.into(), payload)
-                                        }
-                                        other => return Err(MessageDecodingError::Invalid(format!("expected reply payload to be an object with status/response keys, got: {:#?}", &other))),
-                                    }
-                                }
-                                other => return Err(MessageDecodingError::Invalid(format!("expected reply payload to be an object with status/response keys, got: {:#?}", &other))),
-                            };
-
-                            Ok(Message::Reply(Reply {
-                                topic,
-                                event,
-                                payload: payload.into(),
-                                join_reference,
-                                reference,
-                                status,
-                            }))
-                        }
-                        event => Ok(Message::Push(Push {
-                            topic,
-                            event_payload: EventPayload {
-                                event,
-                                payload: payload.into(),
-                            },
-                            join_reference,
-                            reference: Some(reference),
-                        })),
-                    },
-                    (None, reference) => match event {
-                        event @ Event::Phoenix(_) => Ok(Message::Control(Control {
-                            event,
-                            payload: payload.into(),
-                            reference,
-                        })),
-                        other => {
-                            return Err(MessageDecodingError::Invalid(format!(
-                                "unrecognized control event: {:?}",
-                                &other
-                            )))
-                        }
-                    },
-                }
-            }
+            Value::Array(fields) if fields.len() == 5 => Self::from_json_array(fields),
             other => {
                 return Err(MessageDecodingError::Invalid(format!(
                     "expected v2 serializer format, an array of fields, got: {:#?}",
@@ -457,6 +412,165 @@ impl Message {
         }
     }
 
+    /// The [Message] for the `[join_ref, ref, topic, event, payload]` `fields` of the V2
+    /// serializer.
+    pub(crate) fn from_json_array(mut fields: Vec<Value>) -> Result<Self, MessageDecodingError> {
+        let payload = fields.pop().unwrap();
+        let event = match fields.pop().unwrap() {
+            Value::String(s) => Event::from(s),
+            other => {
+                return Err(MessageDecodingError::Invalid(format!(
+                    "expected event to be a string, got: {:#?}",
+                    &other
+                )))
+            }
+        };
+        let topic = match fields.pop().unwrap() {
+            Value::String(s) => Topic::from_string(s),
+            other => {
+                return Err(MessageDecodingError::Invalid(format!(
+                    "expected topic to be a string, got: {:#?}",
+                    &other
+                )))
+            }
+        };
+        let reference = match fields.pop().unwrap() {
+            Value::Null => None,
+            Value::String(s) => Some(s.into()),
+            other => {
+                return Err(MessageDecodingError::Invalid(format!(
+                    "expected ref to be a string or null, got: {:#?}",
+                    &other
+                )))
+            }
+        };
+        let join_reference: Option<JoinReference> = match fields.pop().unwrap() {
+            Value::Null => None,
+            Value::String(s) => Some(s.into()),
+            other => {
+                return Err(MessageDecodingError::Invalid(format!(
+                    "expected join_ref to be a string or null, got: {:#?}",
+                    &other
+                )))
+            }
+        };
+        Self::from_json_fields(join_reference, reference, topic, event, payload)
+    }
+
+    /// Parses a JSON-encoded message using the Phoenix JSON v1 serializer representation, a map
+    /// with `topic`, `event`, `payload`, `ref` and `join_ref` keys.
+    fn decode_json_v1(json: &str) -> Result<Self, MessageDecodingError> {
+        #[derive(serde::Deserialize)]
+        struct V1Message {
+            topic: String,
+            event: String,
+            payload: Value,
+            #[serde(rename = "ref", default)]
+            reference: Option<String>,
+            #[serde(default)]
+            join_ref: Option<String>,
+        }
+
+        let V1Message {
+            topic,
+            event,
+            payload,
+            reference,
+            join_ref,
+        } = serde_json::from_str(json)?;
+        let reference: Option<Reference> = reference.map(From::from);
+        // V1 servers don't send `join_ref`, but they reply to joins and send `phx_close` and
+        // `phx_error` with the ref of the join, which the client also sends as `join_ref`.
+        let join_reference = match join_ref {
+            Some(join_ref) => Some(join_ref.into()),
+            None if topic != "phoenix" => reference.as_ref().map(|r| r.to_string().into()),
+            None => None,
+        };
+
+        Self::from_json_fields(
+            join_reference,
+            reference,
+            Topic::from_string(topic),
+            event.into(),
+            payload,
+        )
+    }
+
+    /// The [Message] for fields decoded in the order of the V2 serializer.  Replies must have a
+    /// map `payload` with `status` and `response` keys.
+    fn from_json_fields(
+        join_reference: Option<JoinReference>,
+        reference: Option<Reference>,
+        topic: Arc<Topic>,
+        event: Event,
+        payload: Value,
+    ) -> Result<Self, MessageDecodingError> {
+        match (join_reference, reference) {
+            (None, None) => Ok(Message::Broadcast(Broadcast {
+                topic,
+                event_payload: EventPayload {
+                    event,
+                    payload: payload.into(),
+                },
+            })),
+            (Some(join_reference), None) => Ok(Message::Push(Push {
+                topic,
+                event_payload: EventPayload {
+                    event,
+                    payload: payload.into(),
+                },
+                join_reference,
+                reference: None,
+            })),
+            (Some(join_reference), Some(reference)) => match event {
+                Event::Phoenix(PhoenixEvent::Reply) => {
+                    let (status, payload) = match payload {
+                        Value::Object(mut map) => {
+                            match (map.remove("status"), map.remove("response")) {
+                                (Some(Value::String(s)), Some(payload)) => {
+                                    (s.into(), payload)
+                                }
+                                other => return Err(MessageDecodingError::Invalid(format!("expected reply payload to be an object with status/response keys, got: {:#?}", &other))),
+                            }
+                        }
+                        other => return Err(MessageDecodingError::Invalid(format!("expected reply payload to be an object with status/response keys, got: {:#?}", &other))),
+                    };
+
+                    Ok(Message::Reply(Reply {
+                        topic,
+                        event,
+                        payload: payload.into(),
+                        join_reference,
+                        reference,
+                        status,
+                    }))
+                }
+                event => Ok(Message::Push(Push {
+                    topic,
+                    event_payload: EventPayload {
+                        event,
+                        payload: payload.into(),
+                    },
+                    join_reference,
+                    reference: Some(reference),
+                })),
+            },
+            (None, reference) => match event {
+                event @ Event::Phoenix(_) => Ok(Message::Control(Control {
+                    event,
+                    payload: payload.into(),
+                    reference,
+                })),
+                other => {
+                    return Err(MessageDecodingError::Invalid(format!(
+                        "unrecognized control event: {:?}",
+                        &other
+                    )))
+                }
+            },
+        }
+    }
+
     /// Parses a binary-encoded message, as used by the Phoenix v2 JSON serializer
     fn decode_binary(mut bytes: &[u8]) -> Result<Self, MessageDecodingError> {
         if bytes.is_empty() {
@@ -721,6 +835,17 @@ impl Payload {
             _ => None,
         }
     }
+
+    /// Deserializes the JSON payload to `T` without cloning it.  Binary payloads can't be
+    /// deserialized.
+    pub fn deserialize<T: DeserializeOwned>(&self) -> Result<T, serde_json::Error> {
+        match self {
+            Self::Value(value) => T::deserialize(value.as_ref()),
+            Self::Binary(_) => Err(serde::de::Error::custom(
+                "binary payloads can't be deserialized",
+            )),
+        }
+    }
 }
 impl From<Value> for Payload {
     #[inline]
@@ -997,6 +1122,135 @@ mod tests {
         )
     }
 
+    #[test]
+    fn message_encode_with_v1_with_push_with_json_payload() {
+        let text = match Message::Push(Push {
+            topic: Topic::from_string("channel_topic".to_string()),
+            event_payload: EventPayload {
+                event: Event::User("event_name".to_string()),
+                payload: json_payload(),
+            },
+            join_reference: "join_reference".into(),
+            reference: Some("reference".into()),
+        })
+        .encode_with(SerializerVersion::V1)
+        .unwrap()
+        {
+            SocketMessage::Text(text) => text,
+            other => panic!("V1 message isn't text: {:?}", other),
+        };
+
+        assert_eq!(
+            serde_json::from_str::<Value>(&text).unwrap(),
+            json!({
+                "topic": "channel_topic",
+                "event": "event_name",
+                "payload": { "key": "value" },
+                "ref": "reference",
+                "join_ref": "join_reference"
+            })
+        );
+    }
+
+    #[test]
+    fn message_encode_with_v1_with_binary_payload_errors() {
+        assert!(Message::Push(Push {
+            topic: Topic::from_string("channel_topic".to_string()),
+            event_payload: EventPayload {
+                event: Event::User("event_name".to_string()),
+                payload: binary_payload(),
+            },
+            join_reference: "join_reference".into(),
+            reference: None,
+        })
+        .encode_with(SerializerVersion::V1)
+        .is_err());
+    }
+
+    #[test]
+    fn message_decode_with_v1_with_reply_without_join_ref() {
+        match Message::decode_with(
+            SocketMessage::Text(
+                json!({
+                    "topic": "channel_topic",
+                    "event": "phx_reply",
+                    "payload": { "status": "ok", "response": { "key": "value" } },
+                    "ref": "reference"
+                })
+                .to_string(),
+            ),
+            SerializerVersion::V1,
+        )
+        .unwrap()
+        {
+            Message::Reply(reply) => {
+                assert_eq!(reply.join_reference, "reference".into());
+                assert_eq!(reply.reference, "reference".into());
+                assert_eq!(reply.status, ReplyStatus::Ok);
+                assert_eq!(reply.payload.value(), json_payload().value());
+            }
+            other => panic!("not a reply: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn message_decode_with_v1_with_heartbeat_reply() {
+        match Message::decode_with(
+            SocketMessage::Text(
+                json!({
+                    "topic": "phoenix",
+                    "event": "phx_reply",
+                    "payload": { "status": "ok", "response": {} },
+                    "ref": "heartbeat:reference"
+                })
+                .to_string(),
+            ),
+            SerializerVersion::V1,
+        )
+        .unwrap()
+        {
+            Message::Control(control) => {
+                assert_eq!(control.reference, Some("heartbeat:reference".into()))
+            }
+            other => panic!("not a control: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn message_decode_with_v1_with_push_without_ref() {
+        match Message::decode_with(
+            SocketMessage::Text(
+                json!({
+                    "topic": "channel_topic",
+                    "event": "event_name",
+                    "payload": { "key": "value" },
+                    "ref": null
+                })
+                .to_string(),
+            ),
+            SerializerVersion::V1,
+        )
+        .unwrap()
+        {
+            Message::Broadcast(broadcast) => {
+                assert_eq!(broadcast.topic.to_string(), "channel_topic");
+                assert_eq!(
+                    broadcast.event_payload.event,
+                    Event::User("event_name".to_string())
+                );
+            }
+            other => panic!("not a broadcast: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn message_decode_with_v1_with_binary_errors() {
+        assert!(
+            Message::decode_with(SocketMessage::Binary(vec![2, 0, 0]), SerializerVersion::V1)
+                .is_err()
+        );
+    }
+
     fn binary_payload() -> Payload {
         vec![0, 1, 2, 3].into()
     }
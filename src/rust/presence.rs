@@ -0,0 +1,395 @@
+//! Merges the `presence_state` and `presence_diff` events sent by `Phoenix.Presence` into the
+//! metas for each presence key, as `syncState` and `syncDiff` do in `phoenix.js`.
+
+use std::collections::BTreeMap;
+
+use serde::Deserialize;
+use serde_json::Value;
+
+/// The event `Phoenix.Presence` sends with all presences after a join.
+pub(crate) const STATE_EVENT: &str = "presence_state";
+/// The event `Phoenix.Presence` sends with the presences that joined and left since the last
+/// event.
+pub(crate) const DIFF_EVENT: &str = "presence_diff";
+
+/// The metas of a presence key, each a JSON object with a unique `phx_ref`.
+pub(crate) type Metas = Vec<Value>;
+
+/// A join or leave of metas for a presence key, like the `onJoin` and `onLeave` callbacks of
+/// `phoenix.js`.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Change {
+    Join {
+        key: String,
+        /// The metas before the join, empty if `key` wasn't present.
+        current_metas: Metas,
+        joined_metas: Metas,
+    },
+    Leave {
+        key: String,
+        /// The metas after the leave, empty if `key` is no longer present.
+        remaining_metas: Metas,
+        left_metas: Metas,
+    },
+}
+
+/// The merged presences of a channel.
+pub(crate) struct PresenceSync {
+    state: BTreeMap<String, Metas>,
+    /// Diffs received before the state of the current join, which are applied to it once it is
+    /// received.  [None] once the state is received.
+    pending_diffs: Option<Vec<Diff>>,
+}
+impl PresenceSync {
+    pub(crate) fn new() -> Self {
+        Self {
+            state: BTreeMap::new(),
+            pending_diffs: Some(Vec::new()),
+        }
+    }
+
+    /// The metas for each presence key, ordered by key.
+    pub(crate) fn list(&self) -> impl Iterator<Item = (&String, &Metas)> {
+        self.state.iter()
+    }
+
+    pub(crate) fn get(&self, key: &str) -> Option<&Metas> {
+        self.state.get(key)
+    }
+
+    /// Holds back diffs until the state of the new join, as they are relative to it rather than
+    /// to the current state, like `inPendingSyncState` in `phoenix.js`.
+    pub(crate) fn rejoined(&mut self) {
+        self.pending_diffs = Some(Vec::new());
+    }
+
+    /// Replaces the presences with the payload of a [STATE_EVENT], then applies any diffs received
+    /// before it.
+    pub(crate) fn state_received(
+        &mut self,
+        payload: &Value,
+    ) -> Result<Vec<Change>, serde_json::Error> {
+        let new_state = Presences::deserialize(payload)?.into_metas();
+        let mut changes = self.sync_state(new_state);
+
+        for diff in self.pending_diffs.take().unwrap_or_default() {
+            changes.extend(self.sync_diff(diff));
+        }
+
+        Ok(changes)
+    }
+
+    /// Applies the payload of a [DIFF_EVENT], unless no state was received since the join, in
+    /// which case it is applied after the state and [None] is returned.
+    pub(crate) fn diff_received(
+        &mut self,
+        payload: &Value,
+    ) -> Result<Option<Vec<Change>>, serde_json::Error> {
+        let diff = DiffJson::deserialize(payload)?.into();
+
+        match &mut self.pending_diffs {
+            Some(pending_diffs) => {
+                pending_diffs.push(diff);
+
+                Ok(None)
+            }
+            None => Ok(Some(self.sync_diff(diff))),
+        }
+    }
+
+    fn sync_state(&mut self, new_state: BTreeMap<String, Metas>) -> Vec<Change> {
+        let mut joins = BTreeMap::new();
+        let mut leaves = BTreeMap::new();
+
+        for (key, current_metas) in &self.state {
+            if !new_state.contains_key(key) {
+                leaves.insert(key.clone(), current_metas.clone());
+            }
+        }
+
+        for (key, new_metas) in new_state {
+            match self.state.get(&key) {
+                Some(current_metas) => {
+                    let joined_metas = without_refs_in(&new_metas, current_metas);
+                    let left_metas = without_refs_in(current_metas, &new_metas);
+
+                    if !joined_metas.is_empty() {
+                        joins.insert(key.clone(), joined_metas);
+                    }
+
+                    if !left_metas.is_empty() {
+                        leaves.insert(key, left_metas);
+                    }
+                }
+                None => {
+                    joins.insert(key, new_metas);
+                }
+            }
+        }
+
+        self.sync_diff(Diff { joins, leaves })
+    }
+
+    fn sync_diff(&mut self, diff: Diff) -> Vec<Change> {
+        let mut changes = Vec::with_capacity(diff.joins.len() + diff.leaves.len());
+
+        for (key, joined_metas) in diff.joins {
+            let current_metas = self.state.get(&key).cloned().unwrap_or_default();
+            let mut metas = without_refs_in(&current_metas, &joined_metas);
+            metas.extend(joined_metas.iter().cloned());
+            self.state.insert(key.clone(), metas);
+
+            changes.push(Change::Join {
+                key,
+                current_metas,
+                joined_metas,
+            });
+        }
+
+        for (key, left_metas) in diff.leaves {
+            let remaining_metas = match self.state.get_mut(&key) {
+                Some(current_metas) => {
+                    current_metas.retain(|meta| !contains_ref(&left_metas, phx_ref(meta)));
+
+                    current_metas.clone()
+                }
+                None => continue,
+            };
+
+            if remaining_metas.is_empty() {
+                self.state.remove(&key);
+            }
+
+            changes.push(Change::Leave {
+                key,
+                remaining_metas,
+                left_metas,
+            });
+        }
+
+        changes
+    }
+}
+
+/// The `phx_ref` of a meta.
+pub(crate) fn phx_ref(meta: &Value) -> Option<&str> {
+    meta.get("phx_ref").and_then(Value::as_str)
+}
+
+fn contains_ref(metas: &[Value], phx_ref_to_find: Option<&str>) -> bool {
+    metas.iter().any(|meta| phx_ref(meta) == phx_ref_to_find)
+}
+
+/// The `metas` whose `phx_ref` is not in `other_metas`.
+fn without_refs_in(metas: &[Value], other_metas: &[Value]) -> Metas {
+    metas
+        .iter()
+        .filter(|meta| !contains_ref(other_metas, phx_ref(meta)))
+        .cloned()
+        .collect()
+}
+
+struct Diff {
+    joins: BTreeMap<String, Metas>,
+    leaves: BTreeMap<String, Metas>,
+}
+impl From<DiffJson> for Diff {
+    fn from(diff_json: DiffJson) -> Self {
+        Self {
+            joins: diff_json.joins.into_metas(),
+            leaves: diff_json.leaves.into_metas(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct DiffJson {
+    #[serde(default)]
+    joins: Presences,
+    #[serde(default)]
+    leaves: Presences,
+}
+
+/// Presences keyed by presence key, as sent by `Phoenix.Presence`.
+#[derive(Default, Deserialize)]
+#[serde(transparent)]
+struct Presences(BTreeMap<String, PresenceJson>);
+impl Presences {
+    fn into_metas(self) -> BTreeMap<String, Metas> {
+        self.0
+            .into_iter()
+            .map(|(key, presence)| (key, presence.metas))
+            .collect()
+    }
+}
+
+#[derive(Deserialize)]
+struct PresenceJson {
+    metas: Metas,
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn synced(state: Value) -> PresenceSync {
+        let mut presence_sync = PresenceSync::new();
+        presence_sync.state_received(&state).unwrap();
+
+        presence_sync
+    }
+
+    fn list(presence_sync: &PresenceSync) -> Value {
+        Value::Object(
+            presence_sync
+                .list()
+                .map(|(key, metas)| (key.clone(), json!({ "metas": metas })))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn state_joins_new_keys_and_leaves_missing_keys() {
+        let mut presence_sync = synced(json!({
+            "u1": { "metas": [{ "id": 1, "phx_ref": "1" }] },
+            "u2": { "metas": [{ "id": 2, "phx_ref": "2" }] }
+        }));
+
+        let changes = presence_sync
+            .state_received(&json!({
+                "u1": { "metas": [{ "id": 1, "phx_ref": "1" }, { "id": 1, "phx_ref": "1.2" }] },
+                "u3": { "metas": [{ "id": 3, "phx_ref": "3" }] }
+            }))
+            .unwrap();
+
+        assert_eq!(
+            changes,
+            vec![
+                Change::Join {
+                    key: "u1".to_string(),
+                    current_metas: vec![json!({ "id": 1, "phx_ref": "1" })],
+                    joined_metas: vec![json!({ "id": 1, "phx_ref": "1.2" })],
+                },
+                Change::Join {
+                    key: "u3".to_string(),
+                    current_metas: vec![],
+                    joined_metas: vec![json!({ "id": 3, "phx_ref": "3" })],
+                },
+                Change::Leave {
+                    key: "u2".to_string(),
+                    remaining_metas: vec![],
+                    left_metas: vec![json!({ "id": 2, "phx_ref": "2" })],
+                },
+            ]
+        );
+        assert_eq!(
+            list(&presence_sync),
+            json!({
+                "u1": { "metas": [{ "id": 1, "phx_ref": "1" }, { "id": 1, "phx_ref": "1.2" }] },
+                "u3": { "metas": [{ "id": 3, "phx_ref": "3" }] }
+            })
+        );
+    }
+
+    #[test]
+    fn diff_merges_joins_and_removes_leaves() {
+        let mut presence_sync = synced(json!({
+            "u1": { "metas": [{ "id": 1, "phx_ref": "1" }] },
+            "u2": { "metas": [{ "id": 2, "phx_ref": "2" }, { "id": 2, "phx_ref": "2.2" }] }
+        }));
+
+        let changes = presence_sync
+            .diff_received(&json!({
+                "joins": { "u1": { "metas": [{ "id": 1, "phx_ref": "1.2" }] } },
+                "leaves": { "u2": { "metas": [{ "id": 2, "phx_ref": "2" }] } }
+            }))
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(
+            changes,
+            vec![
+                Change::Join {
+                    key: "u1".to_string(),
+                    current_metas: vec![json!({ "id": 1, "phx_ref": "1" })],
+                    joined_metas: vec![json!({ "id": 1, "phx_ref": "1.2" })],
+                },
+                Change::Leave {
+                    key: "u2".to_string(),
+                    remaining_metas: vec![json!({ "id": 2, "phx_ref": "2.2" })],
+                    left_metas: vec![json!({ "id": 2, "phx_ref": "2" })],
+                },
+            ]
+        );
+        assert_eq!(
+            list(&presence_sync),
+            json!({
+                "u1": { "metas": [{ "id": 1, "phx_ref": "1" }, { "id": 1, "phx_ref": "1.2" }] },
+                "u2": { "metas": [{ "id": 2, "phx_ref": "2.2" }] }
+            })
+        );
+    }
+
+    #[test]
+    fn leaving_last_meta_removes_key() {
+        let mut presence_sync = synced(json!({ "u1": { "metas": [{ "phx_ref": "1" }] } }));
+
+        presence_sync
+            .diff_received(&json!({
+                "joins": {},
+                "leaves": { "u1": { "metas": [{ "phx_ref": "1" }] } }
+            }))
+            .unwrap();
+
+        assert!(presence_sync.get("u1").is_none());
+    }
+
+    #[test]
+    fn leave_for_unknown_key_is_ignored() {
+        let mut presence_sync = synced(json!({}));
+
+        let changes = presence_sync
+            .diff_received(&json!({ "leaves": { "u1": { "metas": [{ "phx_ref": "1" }] } } }))
+            .unwrap()
+            .unwrap();
+
+        assert!(changes.is_empty());
+    }
+
+    #[test]
+    fn diffs_before_state_are_applied_after_it() {
+        let mut presence_sync = PresenceSync::new();
+
+        assert_eq!(
+            presence_sync
+                .diff_received(&json!({
+                    "joins": { "u2": { "metas": [{ "phx_ref": "2" }] } },
+                    "leaves": { "u1": { "metas": [{ "phx_ref": "1" }] } }
+                }))
+                .unwrap(),
+            None
+        );
+        assert_eq!(list(&presence_sync), json!({}));
+
+        presence_sync
+            .state_received(&json!({ "u1": { "metas": [{ "phx_ref": "1" }] } }))
+            .unwrap();
+
+        assert_eq!(
+            list(&presence_sync),
+            json!({ "u2": { "metas": [{ "phx_ref": "2" }] } })
+        );
+    }
+
+    #[test]
+    fn malformed_payloads_error() {
+        let mut presence_sync = PresenceSync::new();
+
+        assert!(presence_sync.state_received(&json!([])).is_err());
+        assert!(presence_sync
+            .diff_received(&json!({ "joins": { "u1": {} } }))
+            .is_err());
+    }
+}
@@ -4,10 +4,12 @@ use flexstr::{shared_fmt, SharedStr, ToSharedStr};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+/// Identifies a message sent by the client, so that the server's reply can be matched to it.
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 #[repr(transparent)]
 pub struct Reference(pub SharedStr);
 impl Reference {
+    /// A new, unique [Reference].
     pub fn new() -> Self {
         Self::prefixed("")
     }
@@ -22,6 +24,7 @@ impl Reference {
         ))
     }
 
+    /// A new, unique [Reference] for a heartbeat.
     pub fn heartbeat() -> Self {
         Self::prefixed("heartbeat:")
     }
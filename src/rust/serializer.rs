@@ -0,0 +1,78 @@
+//! How [Message]s are encoded to and decoded from frames by the built-in web socket and long poll
+//! transports, which must match the serializer configured for the endpoint's socket on the server.
+//!
+//! [SerializerVersion]s are the Phoenix JSON serializers, selected with
+//! [SocketOptions::serializer_version](crate::SocketOptions::serializer_version).  Any other wire
+//! format, such as the [MessagePackSerializer] in the `msgpack` feature, can implement
+//! [Serializer] and be passed to
+//! [Socket::spawn_with_serializer](crate::Socket::spawn_with_serializer).
+//!
+//! ```
+//! # use tokio_tungstenite::tungstenite;
+//! #
+//! # use phoenix_channels_client::serializer::{Serializer, SerializerVersion};
+//! # use phoenix_channels_client::transport::{Message, MessageDecodingError, MessageEncodingError};
+//! #
+//! /// The V2 JSON serializer for an endpoint that only accepts its custom `vsn`.
+//! struct CustomVsn(&'static str);
+//! impl Serializer for CustomVsn {
+//!     fn vsn(&self) -> &str {
+//!         self.0
+//!     }
+//!
+//!     fn encode(&self, message: Message) -> Result<tungstenite::Message, MessageEncodingError> {
+//!         SerializerVersion::V2.encode(message)
+//!     }
+//!
+//!     fn decode(&self, frame: tungstenite::Message) -> Result<Message, MessageDecodingError> {
+//!         SerializerVersion::V2.decode(frame)
+//!     }
+//! }
+//! ```
+
+#[cfg(feature = "msgpack")]
+mod msgpack;
+
+use tokio_tungstenite::tungstenite;
+
+pub use crate::ffi::serializer::SerializerVersion;
+use crate::rust::message::{Message, MessageDecodingError, MessageEncodingError};
+#[cfg(feature = "msgpack")]
+pub use msgpack::MessagePackSerializer;
+
+/// Encodes each [Message] sent by the client to a frame and decodes each data frame received from
+/// the server to a [Message].
+pub trait Serializer: Send + Sync + 'static {
+    /// The `vsn` param sent when connecting, which the endpoint uses to pick its serializer.
+    fn vsn(&self) -> &str;
+
+    /// Whether binary [Payload](crate::Payload)s can be encoded.  When they can't, calls, casts and
+    /// joins with binary payloads are rejected before they are sent.
+    fn supports_binary_payloads(&self) -> bool {
+        true
+    }
+
+    /// Encodes `message` sent by the client.
+    fn encode(&self, message: Message) -> Result<tungstenite::Message, MessageEncodingError>;
+
+    /// Decodes a text or binary `frame` sent by the server.
+    fn decode(&self, frame: tungstenite::Message) -> Result<Message, MessageDecodingError>;
+}
+
+impl Serializer for SerializerVersion {
+    fn vsn(&self) -> &str {
+        SerializerVersion::vsn(self)
+    }
+
+    fn supports_binary_payloads(&self) -> bool {
+        SerializerVersion::supports_binary_payloads(self)
+    }
+
+    fn encode(&self, message: Message) -> Result<tungstenite::Message, MessageEncodingError> {
+        message.encode_with(*self)
+    }
+
+    fn decode(&self, frame: tungstenite::Message) -> Result<Message, MessageDecodingError> {
+        Message::decode_with(frame, *self)
+    }
+}
@@ -0,0 +1,314 @@
+use std::mem;
+
+use bytes::Bytes;
+use rmpv::Value as MsgPackValue;
+use serde_json::{Map, Number, Value};
+use tokio_tungstenite::tungstenite;
+
+use crate::rust::message::{Message, MessageDecodingError, MessageEncodingError, Payload};
+use crate::rust::serializer::Serializer;
+
+/// Encodes each message as a MessagePack array of `[join_ref, ref, topic, event, payload]` in a
+/// binary frame, in the same order as the V2 JSON serializer.  Binary [Payload]s are encoded as
+/// MessagePack binaries, so they don't need a separate frame format.
+///
+/// The endpoint's socket needs a matching serializer, such as a `Phoenix.Socket.Serializer` using
+/// `Msgpax`, selected by the [MessagePackSerializer::vsn].
+///
+/// ```
+/// # use url::Url;
+/// #
+/// # use phoenix_channels_client::serializer::MessagePackSerializer;
+/// # use phoenix_channels_client::{PhoenixError, Socket, SocketOptions};
+/// #
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), PhoenixError> {
+/// let url = Url::parse("ws://127.0.0.1:9002/socket/websocket")?;
+/// let socket = Socket::spawn_with_serializer(
+///     url,
+///     SocketOptions::default(),
+///     MessagePackSerializer::new("2.0.0-msgpack"),
+/// )?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct MessagePackSerializer {
+    vsn: String,
+}
+impl MessagePackSerializer {
+    /// Sends `vsn` as the `vsn` param when connecting.
+    pub fn new(vsn: impl Into<String>) -> Self {
+        Self { vsn: vsn.into() }
+    }
+}
+impl Default for MessagePackSerializer {
+    /// Sends `vsn=2.0.0`, for endpoints that replaced the V2 JSON serializer.
+    fn default() -> Self {
+        Self::new("2.0.0")
+    }
+}
+impl Serializer for MessagePackSerializer {
+    fn vsn(&self) -> &str {
+        &self.vsn
+    }
+
+    fn encode(&self, mut message: Message) -> Result<tungstenite::Message, MessageEncodingError> {
+        let binary = match mem::replace(message.payload_mut(), Value::Null.into()) {
+            Payload::Value(value) => {
+                *message.payload_mut() = Payload::Value(value);
+
+                None
+            }
+            Payload::Binary(bytes) => Some(bytes),
+        };
+
+        let mut fields = message
+            .into_json_fields()
+            .into_iter()
+            .map(from_json)
+            .collect::<Vec<_>>();
+
+        if let Some(bytes) = binary {
+            fields[4] = MsgPackValue::Binary(bytes.to_vec());
+        }
+
+        let mut buffer = Vec::new();
+        rmpv::encode::write_value(&mut buffer, &MsgPackValue::Array(fields))
+            .map_err(|_| MessageEncodingError)?;
+
+        Ok(tungstenite::Message::Binary(buffer))
+    }
+
+    fn decode(&self, frame: tungstenite::Message) -> Result<Message, MessageDecodingError> {
+        let bytes = match frame {
+            tungstenite::Message::Binary(bytes) => bytes,
+            other => {
+                return Err(MessageDecodingError::Invalid(format!(
+                    "expected a binary MessagePack frame, got: {:#?}",
+                    &other
+                )))
+            }
+        };
+
+        let mut fields = match rmpv::decode::read_value(&mut bytes.as_slice()) {
+            Ok(MsgPackValue::Array(fields)) if fields.len() == 5 => fields,
+            Ok(other) => {
+                return Err(MessageDecodingError::Invalid(format!(
+                    "expected an array of fields, got: {:#?}",
+                    &other
+                )))
+            }
+            Err(error) => return Err(MessageDecodingError::Invalid(error.to_string())),
+        };
+
+        // Binaries have no JSON representation, so they're taken out before the fields are
+        // converted and put back into the decoded message's payload.
+        let is_reply = fields[3].as_str() == Some("phx_reply");
+        let binary = match &mut fields[4] {
+            MsgPackValue::Binary(bytes) => Some(mem::take(bytes)),
+            MsgPackValue::Map(entries) if is_reply => entries
+                .iter_mut()
+                .find(|(key, _)| key.as_str() == Some("response"))
+                .and_then(|(_, response)| match response {
+                    MsgPackValue::Binary(bytes) => Some(mem::take(bytes)),
+                    _ => None,
+                }),
+            _ => None,
+        };
+
+        let fields = fields
+            .into_iter()
+            .map(into_json)
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut message = Message::from_json_array(fields)?;
+
+        if let Some(bytes) = binary {
+            *message.payload_mut() = Payload::Binary(Bytes::from(bytes));
+        }
+
+        Ok(message)
+    }
+}
+
+fn from_json(value: Value) -> MsgPackValue {
+    match value {
+        Value::Null => MsgPackValue::Nil,
+        Value::Bool(boolean) => MsgPackValue::Boolean(boolean),
+        Value::Number(number) => {
+            if let Some(integer) = number.as_u64() {
+                MsgPackValue::from(integer)
+            } else if let Some(integer) = number.as_i64() {
+                MsgPackValue::from(integer)
+            } else {
+                MsgPackValue::F64(number.as_f64().unwrap_or_default())
+            }
+        }
+        Value::String(string) => MsgPackValue::from(string),
+        Value::Array(values) => MsgPackValue::Array(values.into_iter().map(from_json).collect()),
+        Value::Object(map) => MsgPackValue::Map(
+            map.into_iter()
+                .map(|(key, value)| (MsgPackValue::from(key), from_json(value)))
+                .collect(),
+        ),
+    }
+}
+
+/// Binaries become `null`, as they're only supported as the whole payload or reply response.
+fn into_json(value: MsgPackValue) -> Result<Value, MessageDecodingError> {
+    let json = match value {
+        MsgPackValue::Nil | MsgPackValue::Binary(_) => Value::Null,
+        MsgPackValue::Boolean(boolean) => Value::Bool(boolean),
+        MsgPackValue::Integer(integer) => match (integer.as_u64(), integer.as_i64()) {
+            (Some(integer), _) => Value::from(integer),
+            (None, Some(integer)) => Value::from(integer),
+            (None, None) => unreachable!("MessagePack integers are u64 or i64"),
+        },
+        MsgPackValue::F32(float) => float_into_json(float.into())?,
+        MsgPackValue::F64(float) => float_into_json(float)?,
+        MsgPackValue::String(string) => match string.into_str() {
+            Some(string) => Value::String(string),
+            None => {
+                return Err(MessageDecodingError::Invalid(
+                    "expected MessagePack string to be utf-8".to_string(),
+                ))
+            }
+        },
+        MsgPackValue::Array(values) => Value::Array(
+            values
+                .into_iter()
+                .map(into_json)
+                .collect::<Result<_, _>>()?,
+        ),
+        MsgPackValue::Map(entries) => {
+            let mut map = Map::with_capacity(entries.len());
+
+            for (key, value) in entries {
+                let key = match key {
+                    MsgPackValue::String(key) if key.is_str() => key.into_str().unwrap(),
+                    other => {
+                        return Err(MessageDecodingError::Invalid(format!(
+                            "expected MessagePack map key to be a string, got: {:#?}",
+                            &other
+                        )))
+                    }
+                };
+
+                map.insert(key, into_json(value)?);
+            }
+
+            Value::Object(map)
+        }
+        MsgPackValue::Ext(type_id, _) => {
+            return Err(MessageDecodingError::Invalid(format!(
+                "unsupported MessagePack extension type {}",
+                type_id
+            )))
+        }
+    };
+
+    Ok(json)
+}
+
+fn float_into_json(float: f64) -> Result<Value, MessageDecodingError> {
+    Number::from_f64(float).map(Value::Number).ok_or_else(|| {
+        MessageDecodingError::Invalid(format!("{} has no JSON representation", float))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::ffi::message::PhoenixEvent;
+    use crate::ffi::topic::Topic;
+    use crate::rust::message::{Event, EventPayload, Push};
+
+    use super::*;
+
+    #[test]
+    fn encode_then_decode_push_with_json_payload() {
+        let serializer = MessagePackSerializer::default();
+        let frame = serializer
+            .encode(push(json!({ "key": "value", "count": -1, "ratio": 0.5 }).into()))
+            .unwrap();
+
+        assert!(frame.is_binary());
+
+        match serializer.decode(frame).unwrap() {
+            Message::Push(push) => {
+                assert_eq!(push.topic, Topic::from_string("channel_topic".to_string()));
+                assert_eq!(push.event_payload.event, Event::User("event_name".to_string()));
+                assert_eq!(
+                    push.event_payload.payload,
+                    json!({ "key": "value", "count": -1, "ratio": 0.5 }).into()
+                );
+                assert_eq!(push.join_reference, "join_reference".into());
+                assert_eq!(push.reference, Some("reference".into()));
+            }
+            other => panic!("decoded {:?} instead of a push", other),
+        }
+    }
+
+    #[test]
+    fn encode_then_decode_push_with_binary_payload() {
+        let serializer = MessagePackSerializer::default();
+        let frame = serializer.encode(push(vec![0, 1, 2, 3].into())).unwrap();
+
+        match serializer.decode(frame).unwrap() {
+            Message::Push(push) => {
+                assert_eq!(push.event_payload.payload, vec![0, 1, 2, 3].into())
+            }
+            other => panic!("decoded {:?} instead of a push", other),
+        }
+    }
+
+    #[test]
+    fn decode_reply_with_binary_response() {
+        let frame = encode(MsgPackValue::Array(vec![
+            "join_reference".into(),
+            "reference".into(),
+            "channel_topic".into(),
+            "phx_reply".into(),
+            MsgPackValue::Map(vec![
+                ("status".into(), "ok".into()),
+                ("response".into(), MsgPackValue::Binary(vec![0, 1, 2, 3])),
+            ]),
+        ]));
+
+        match MessagePackSerializer::default().decode(frame).unwrap() {
+            Message::Reply(reply) => {
+                assert_eq!(reply.event, Event::Phoenix(PhoenixEvent::Reply));
+                assert_eq!(reply.status, "ok".to_string().into());
+                assert_eq!(reply.payload, vec![0, 1, 2, 3].into());
+            }
+            other => panic!("decoded {:?} instead of a reply", other),
+        }
+    }
+
+    #[test]
+    fn decode_text_frame_errors() {
+        assert!(MessagePackSerializer::default()
+            .decode(tungstenite::Message::Text("[]".to_string()))
+            .is_err());
+    }
+
+    fn push(payload: Payload) -> Message {
+        Message::Push(Push {
+            topic: Topic::from_string("channel_topic".to_string()),
+            event_payload: EventPayload {
+                event: Event::User("event_name".to_string()),
+                payload,
+            },
+            join_reference: "join_reference".into(),
+            reference: Some("reference".into()),
+        })
+    }
+
+    fn encode(value: MsgPackValue) -> tungstenite::Message {
+        let mut buffer = Vec::new();
+        rmpv::encode::write_value(&mut buffer, &value).unwrap();
+
+        tungstenite::Message::Binary(buffer)
+    }
+}
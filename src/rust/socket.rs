@@ -1,4 +1,6 @@
 pub(crate) mod listener;
+pub(crate) mod long_poll;
+pub(crate) mod transport;
 
 use std::panic;
 use std::sync::Arc;
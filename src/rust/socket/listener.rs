@@ -13,17 +13,21 @@ use futures::SinkExt;
 use futures::StreamExt;
 use log::{debug, error};
 use serde_json::Value;
-use tokio::net::TcpStream;
 use tokio::sync::{broadcast, mpsc, oneshot};
 use tokio::task::JoinHandle;
 use tokio::time;
 use tokio::time::{Instant, Interval, Sleep};
-use tokio_tungstenite::{tungstenite, MaybeTlsStream, WebSocketStream};
+use tokio_tungstenite::tungstenite;
+use tokio_tungstenite::tungstenite::http::HeaderMap;
 use url::Url;
 
+use crate::ffi::backoff::Backoff;
+use crate::ffi::channel::options::ChannelOptions;
 use crate::ffi::channel::Channel;
 use crate::ffi::message::PhoenixEvent;
-use crate::ffi::socket::Socket;
+use crate::ffi::socket::options::SocketOptions;
+use crate::ffi::socket::params::DynamicParams;
+use crate::ffi::socket::{ReconnectReason, Socket};
 use crate::ffi::topic::Topic;
 use crate::rust::channel::listener::{JoinedChannelReceivers, LeaveError};
 use crate::rust::channel::CallError;
@@ -33,34 +37,62 @@ use crate::rust::message::{
 };
 use crate::rust::reference::Reference;
 use crate::rust::socket::{ConnectError, ShutdownError};
+use crate::rust::transport::{ConnectRequest, Connector, Transport, TransportError};
 use crate::rust::{channel, socket};
 
-pub(crate) struct Listener {
+/// The [Socket]'s ends of what it shares with its [Listener], grouped so that adding one doesn't
+/// grow [Listener::spawn].
+pub(crate) struct SocketHandles {
+    pub(crate) socket_status: ObservableStatus,
+    pub(crate) channel_spawn_rx: mpsc::Receiver<ChannelSpawn>,
+    pub(crate) state_command_rx: mpsc::Receiver<StateCommand>,
+    pub(crate) channel_state_command_rx: mpsc::Receiver<ChannelStateCommand>,
+    pub(crate) channel_send_command_rx: mpsc::Receiver<ChannelSendCommand>,
+    /// [Socket::unrouted_broadcast_tx]
+    pub(crate) unrouted_broadcast_tx: broadcast::Sender<Broadcast>,
+}
+
+pub(crate) struct Listener<T: Transport> {
     url: Arc<Url>,
+    /// Opens a new `T` for each connect and reconnect.
+    connector: Box<dyn Connector<Transport = T>>,
+    /// [SocketOptions::headers] and [SocketOptions::subprotocols]
+    upgrade_headers: HeaderMap,
+    /// [Socket::set_auth_token] and [Socket::set_params_provider]
+    dynamic_params: Arc<DynamicParams>,
+    heartbeat_interval: Duration,
+    max_missed_heartbeats: u32,
+    connect_timeout: Option<Duration>,
+    reconnect_backoff: Arc<dyn Backoff>,
+    max_reconnect_attempts: Option<u32>,
     channel_spawn_rx: mpsc::Receiver<ChannelSpawn>,
     state_command_rx: mpsc::Receiver<StateCommand>,
     channel_state_command_rx: mpsc::Receiver<ChannelStateCommand>,
     channel_send_command_rx: mpsc::Receiver<ChannelSendCommand>,
     connectivity_tx: broadcast::Sender<Connectivity>,
-    state: Option<State>,
+    /// [Socket::unrouted_broadcast_tx]
+    unrouted_broadcast_tx: broadcast::Sender<Broadcast>,
+    state: Option<State<T>>,
     socket_status: ObservableStatus,
 }
-impl Listener {
+impl<T: Transport> Listener<T> {
     pub(crate) fn spawn(
         url: Arc<Url>,
-        socket_status: ObservableStatus,
-        channel_spawn_rx: mpsc::Receiver<ChannelSpawn>,
-        state_command_rx: mpsc::Receiver<StateCommand>,
-        channel_state_command_rx: mpsc::Receiver<ChannelStateCommand>,
-        channel_send_command_rx: mpsc::Receiver<ChannelSendCommand>,
+        connector: Box<dyn Connector<Transport = T>>,
+        upgrade_headers: HeaderMap,
+        options: &SocketOptions,
+        reconnect_backoff: Arc<dyn Backoff>,
+        dynamic_params: Arc<DynamicParams>,
+        socket_handles: SocketHandles,
     ) -> JoinHandle<Result<(), ShutdownError>> {
         let listener = Self::init(
             url,
-            socket_status,
-            channel_spawn_rx,
-            state_command_rx,
-            channel_state_command_rx,
-            channel_send_command_rx,
+            connector,
+            upgrade_headers,
+            options,
+            reconnect_backoff,
+            dynamic_params,
+            socket_handles,
         );
 
         tokio::spawn(listener.listen())
@@ -68,22 +100,40 @@ impl Listener {
 
     fn init(
         url: Arc<Url>,
-        socket_status: ObservableStatus,
-        channel_spawn_rx: mpsc::Receiver<ChannelSpawn>,
-        state_command_rx: mpsc::Receiver<StateCommand>,
-        channel_state_command_rx: mpsc::Receiver<ChannelStateCommand>,
-        channel_send_command_rx: mpsc::Receiver<ChannelSendCommand>,
+        connector: Box<dyn Connector<Transport = T>>,
+        upgrade_headers: HeaderMap,
+        options: &SocketOptions,
+        reconnect_backoff: Arc<dyn Backoff>,
+        dynamic_params: Arc<DynamicParams>,
+        socket_handles: SocketHandles,
     ) -> Self {
+        let SocketHandles {
+            socket_status,
+            channel_spawn_rx,
+            state_command_rx,
+            channel_state_command_rx,
+            channel_send_command_rx,
+            unrouted_broadcast_tx,
+        } = socket_handles;
         let (connectivity_tx, _) = broadcast::channel(1);
 
         Self {
             url,
+            connector,
+            upgrade_headers,
+            dynamic_params,
+            heartbeat_interval: options.heartbeat_interval_or_default(),
+            max_missed_heartbeats: options.max_missed_heartbeats,
+            connect_timeout: options.connect_timeout,
+            reconnect_backoff,
+            max_reconnect_attempts: options.max_reconnect_attempts,
             socket_status,
             channel_spawn_rx,
             state_command_rx,
             channel_state_command_rx,
             channel_send_command_rx,
             connectivity_tx,
+            unrouted_broadcast_tx,
             state: Some(State::NeverConnected),
         }
     }
@@ -96,7 +146,9 @@ impl Listener {
             let current_discriminant = mem::discriminant(&current_state);
 
             let next_state = match current_state {
-                State::NeverConnected { .. } | State::Disconnected { .. } => tokio::select! {
+                State::NeverConnected { .. }
+                | State::ReconnectAttemptsExhausted(_)
+                | State::Disconnected { .. } => tokio::select! {
                     Some(channel_spawn) = self.channel_spawn_rx.recv() => self.spawn_channel(current_state, channel_spawn).await,
                     Some(state_command) = self.state_command_rx.recv() => self.update_state(current_state, state_command).await,
                     else => break Ok(())
@@ -117,9 +169,9 @@ impl Listener {
                 },
                 State::WaitingToReconnect {
                     ref mut sleep,
-                    reconnect,
+                    ref reconnect,
                 } => tokio::select! {
-                    () = sleep => self.reconnect(reconnect).await,
+                    () = sleep => self.reconnect(reconnect.clone()).await,
                     Some(channel_spawn) = self.channel_spawn_rx.recv() => self.spawn_channel(current_state, channel_spawn).await,
                     Some(state_command) = self.state_command_rx.recv() => self.update_state(current_state, state_command).await,
                     else => break Ok(())
@@ -145,16 +197,20 @@ impl Listener {
         result
     }
 
-    async fn spawn_channel(&self, state: State, channel_spawn: ChannelSpawn) -> State {
+    async fn spawn_channel(&self, state: State<T>, channel_spawn: ChannelSpawn) -> State<T> {
         let ChannelSpawn {
             socket,
             topic,
             payload,
+            options,
             sender,
         } = channel_spawn;
 
         let channel_state = match &state {
-            State::NeverConnected | State::Disconnected | State::WaitingToReconnect { .. } => {
+            State::NeverConnected
+            | State::WaitingToReconnect { .. }
+            | State::ReconnectAttemptsExhausted(_)
+            | State::Disconnected => {
                 channel::listener::State::WaitingForSocketToConnect { rejoin: None }
             }
             State::Connected(_) => channel::listener::State::WaitingToJoin,
@@ -164,7 +220,15 @@ impl Listener {
 
         let connectivity_rx = self.connectivity_tx.subscribe();
 
-        let channel = Channel::spawn(socket, connectivity_rx, topic, payload, channel_state).await;
+        let channel = Channel::spawn(
+            socket,
+            connectivity_rx,
+            topic,
+            payload,
+            options,
+            channel_state,
+        )
+        .await;
 
         if let Err(channel) = sender.send(channel) {
             channel.shutdown().await.ok();
@@ -173,7 +237,7 @@ impl Listener {
         state
     }
 
-    async fn update_state(&self, state: State, state_command: StateCommand) -> State {
+    async fn update_state(&self, state: State<T>, state_command: StateCommand) -> State<T> {
         match state_command {
             StateCommand::Connect(connect) => self.connect(state, connect).await,
             StateCommand::Disconnect { disconnected_tx } => {
@@ -183,11 +247,15 @@ impl Listener {
         }
     }
 
-    async fn connect(&self, state: State, connect: Connect) -> State {
+    async fn connect(&self, state: State<T>, connect: Connect) -> State<T> {
         let (connect_result, next_state) = match state {
-            State::NeverConnected | State::Disconnected => {
+            State::NeverConnected | State::ReconnectAttemptsExhausted(_) | State::Disconnected => {
                 match self
-                    .socket_connect(connect.created_at, connect.reconnect())
+                    .socket_connect(
+                        connect.created_at,
+                        connect.timeout,
+                        self.reconnect_after(&connect),
+                    )
                     .await
                 {
                     Ok(state) => (Ok(()), state),
@@ -208,15 +276,20 @@ impl Listener {
         next_state
     }
 
-    async fn disconnect(&self, state: State, disconnected_tx: oneshot::Sender<()>) -> State {
+    async fn disconnect(
+        &self,
+        state: State<T>,
+        disconnected_tx: oneshot::Sender<()>,
+    ) -> State<T> {
         let next_state = match state {
             State::NeverConnected { .. }
             | State::WaitingToReconnect { .. }
             | State::Disconnected { .. }
             | State::ShuttingDown
             | State::ShutDown => state,
+            State::ReconnectAttemptsExhausted(_) => State::Disconnected,
             State::Connected(mut connected) => {
-                if let Err(error) = connected.socket.close(None).await {
+                if let Err(error) = connected.socket.close().await {
                     debug!("Web socket error while disconnecting: {}", error);
                 };
 
@@ -229,29 +302,41 @@ impl Listener {
         next_state
     }
 
-    fn disconnect_connected(&self, mut connected: Connected) -> State {
+    fn disconnect_connected(&self, mut connected: Connected<T>) -> State<T> {
         self.send_disconnected(&mut connected, Disconnected::Disconnect);
 
         State::Disconnected
     }
 
-    fn shutdown_connected(&self, mut connected: Connected) -> State {
+    fn shutdown_connected(&self, mut connected: Connected<T>) -> State<T> {
         self.send_disconnected(&mut connected, Disconnected::Shutdown);
 
         State::ShuttingDown
     }
 
-    fn wait_to_reconnect_connected(&self, mut connected: Connected) -> State {
+    fn wait_to_reconnect_connected(&self, connected: Connected<T>) -> State<T> {
+        self.wait_to_reconnect_connected_because(connected, ReconnectReason::ConnectionLost)
+    }
+
+    fn wait_to_reconnect_connected_because(
+        &self,
+        mut connected: Connected<T>,
+        reason: ReconnectReason,
+    ) -> State<T> {
         self.send_disconnected(&mut connected, Disconnected::Reconnect);
 
         Reconnect {
             connect_timeout: connected.connect_timeout,
             attempts: 0,
+            previous_sleep: Duration::ZERO,
+            backoff: self.reconnect_backoff.clone(),
+            max_attempts: self.max_reconnect_attempts,
+            reason,
         }
         .wait()
     }
 
-    fn send_disconnected(&self, connected: &mut Connected, disconnected: Disconnected) {
+    fn send_disconnected(&self, connected: &mut Connected<T>, disconnected: Disconnected) {
         let connectivity = Connectivity::Disconnected(disconnected);
         debug!("Sending connectivity change: {:?}", connectivity);
         self.connectivity_tx.send(connectivity).ok();
@@ -296,96 +381,71 @@ impl Listener {
 
     async fn handle_socket_result(
         &self,
-        connected: Connected,
-        result: Result<tungstenite::Message, tungstenite::Error>,
-    ) -> Result<State, ShutdownError> {
+        connected: Connected<T>,
+        result: Result<Message, TransportError>,
+    ) -> Result<State<T>, ShutdownError> {
         match result {
             Ok(message) => Ok(self.handle_socket_message(connected, message).await),
             Err(error) => self.handle_socket_error(connected, error).await,
         }
     }
 
-    async fn handle_socket_message(
-        &self,
-        mut connected: Connected,
-        message: tungstenite::Message,
-    ) -> State {
+    async fn handle_socket_message(&self, mut connected: Connected<T>, message: Message) -> State<T> {
         match message {
-            tungstenite::Message::Ping(data) => {
-                debug!("client received ping");
-                match connected
-                    .socket
-                    .send(tungstenite::Message::Pong(data))
-                    .await
-                {
-                    Ok(()) => State::Connected(connected),
-                    Err(_) => self.wait_to_reconnect_connected(connected),
+            Message::Control(control) => {
+                debug!("received control message: {:#?}", &control);
+                if let Event::Phoenix(PhoenixEvent::Reply) = control.event {
+                    if control.reference == connected.sent_heartbeat_reference {
+                        // Reset heartbeat timeout
+                        debug!("received heartbeat reply, resetting heartbeat timeout");
+                        connected.sent_heartbeat_reference = None;
+                        connected.missed_heartbeats = 0;
+                        connected.heartbeat.reset();
+                    }
                 }
             }
-            tungstenite::Message::Pong(_) => {
-                debug!("client received pong");
-
-                State::Connected(connected)
-            }
-            tungstenite::Message::Close(close_frame) => {
-                let infix = match &close_frame {
-                    Some(close_frame) => {
-                        format!(" ({})", close_frame)
-                    }
-                    None => "".to_string(),
-                };
-
+            Message::Reply(reply) if reply.event == PhoenixEvent::Heartbeat => {
                 debug!(
-                    "Client received close{} from server. Waiting to reconnect.",
-                    infix
+                    "received heartbeat reply not in control format: {:#?}",
+                    &reply
                 );
-
-                connected.socket.close(close_frame).await.ok();
-
-                self.wait_to_reconnect_connected(connected)
             }
-            msg @ tungstenite::Message::Binary(_) | msg @ tungstenite::Message::Text(_) => {
-                match Message::decode(msg) {
-                    Ok(Message::Control(control)) => {
-                        debug!("received control message: {:#?}", &control);
-                        if let Event::Phoenix(PhoenixEvent::Reply) = control.event {
-                            if control.reference == connected.sent_heartbeat_reference {
-                                // Reset heartbeat timeout
-                                debug!("received heartbeat reply, resetting heartbeat timeout");
-                                connected.sent_heartbeat_reference = None;
-                                connected.heartbeat.reset();
-                            }
-                        }
-                    }
-                    Ok(Message::Reply(reply)) if reply.event == PhoenixEvent::Heartbeat => {
-                        debug!(
-                            "received heartbeat reply not in control format: {:#?}",
-                            &reply
-                        );
-                    }
-                    Ok(Message::Reply(reply)) => {
-                        connected.handle_reply(reply);
-                    }
-                    Ok(Message::Push(push)) => {
-                        connected.handle_push(push).await;
-                    }
-                    Ok(Message::Broadcast(broadcast)) => connected.handle_broadcast(broadcast),
-                    Err(err) => {
-                        debug!("dropping invalid message received from server, due to error decoding: {}", &err);
-                    }
-                }
-
-                State::Connected(connected)
+            Message::Reply(reply) => {
+                connected.handle_reply(reply);
+            }
+            Message::Push(push) => {
+                connected.handle_push(push);
+            }
+            Message::Broadcast(broadcast) => {
+                connected.handle_broadcast(broadcast, &self.unrouted_broadcast_tx)
             }
-            tungstenite::Message::Frame(_) => unreachable!(),
         }
+
+        State::Connected(connected)
     }
 
     async fn handle_socket_error(
         &self,
-        connected: Connected,
-        error: tungstenite::Error,
-    ) -> Result<State, ShutdownError> {
+        connected: Connected<T>,
+        error: TransportError,
+    ) -> Result<State<T>, ShutdownError> {
+        let error = match error {
+            TransportError::ConnectionClosed => {
+                debug!("connection closed");
+
+                return Ok(self.wait_to_reconnect_connected(connected));
+            }
+            TransportError::Io(_) => {
+                error!(
+                    "io error encountered while reading from transport: {:?}",
+                    &error
+                );
+
+                return Ok(self.wait_to_reconnect_connected(connected));
+            }
+            TransportError::WebSocket(error) => error,
+        };
+
         match error {
             tungstenite::Error::ConnectionClosed => {
                 debug!("connection closed");
@@ -449,19 +509,21 @@ impl Listener {
 
     async fn update_channel_state(
         &self,
-        connected: Connected,
+        connected: Connected<T>,
         channel_state_command: ChannelStateCommand,
-    ) -> State {
+    ) -> State<T> {
         match channel_state_command {
             ChannelStateCommand::Join(join) => self.start_join(connected, join).await,
             ChannelStateCommand::Leave(leave) => self.leave(connected, leave).await,
         }
     }
 
-    async fn start_join(&self, mut connected: Connected, join: Join) -> State {
+    async fn start_join(&self, mut connected: Connected<T>, join: Join) -> State<T> {
         let topic = join.topic.clone();
         let join_reference = join.join_reference.clone();
-        let reference = Reference::new();
+        // Like `phoenix.js`, the join's ref is its join ref, so that servers using the V1
+        // serializer, which doesn't send `join_ref`, can be matched by ref alone.
+        let reference = Reference::from(join_reference.to_string());
 
         debug!(
             "attempting to join topic '{}' as {} with ref {}",
@@ -479,9 +541,7 @@ impl Listener {
             reference: Some(reference),
         });
 
-        let data = message.encode().unwrap();
-
-        match connected.socket.send(data).await {
+        match connected.socket.send(message).await {
             Ok(()) => {
                 let deadline = join.deadline;
 
@@ -516,7 +576,7 @@ impl Listener {
         }
     }
 
-    async fn leave(&self, mut connected: Connected, leave: Leave) -> State {
+    async fn leave(&self, mut connected: Connected<T>, leave: Leave) -> State<T> {
         debug!(
             "client is leaving channel '{}' ({})",
             &leave.topic, &leave.join_reference
@@ -531,9 +591,7 @@ impl Listener {
             join_reference: leave.join_reference.clone(),
             reference: None,
         });
-        let data = message.encode().unwrap();
-
-        let (next_state, left_result) = match connected.socket.send(data).await {
+        let (next_state, left_result) = match connected.socket.send(message).await {
             Ok(()) => {
                 if let Some(JoinedChannelSenders { left: left_tx, .. }) =
                     connected.remove_joined_channel_txs(leave.topic, leave.join_reference)
@@ -545,7 +603,7 @@ impl Listener {
             }
             Err(web_socket_error) => (
                 self.wait_to_reconnect_connected(connected),
-                Err(LeaveError::WebSocketError(Arc::new(web_socket_error))),
+                Err(LeaveError::WebSocketError(Arc::new(web_socket_error.into()))),
             ),
         };
 
@@ -554,14 +612,18 @@ impl Listener {
         next_state
     }
 
-    async fn send(&self, connected: Connected, channel_send_command: ChannelSendCommand) -> State {
+    async fn send(
+        &self,
+        connected: Connected<T>,
+        channel_send_command: ChannelSendCommand,
+    ) -> State<T> {
         match channel_send_command {
             ChannelSendCommand::Call(call) => self.call(connected, call).await,
             ChannelSendCommand::Cast(cast) => self.cast(connected, cast).await,
         }
     }
 
-    async fn call(&self, mut connected: Connected, call: Call) -> State {
+    async fn call(&self, mut connected: Connected<T>, call: Call) -> State<T> {
         let Call {
             topic,
             join_reference,
@@ -584,9 +646,7 @@ impl Listener {
             join_reference: join_reference.clone(),
             reference: Some(reference.clone()),
         });
-        let data = message.encode().unwrap();
-
-        match connected.socket.send(data).await {
+        match connected.socket.send(message).await {
             Ok(()) => {
                 connected
                     .reply_tx_by_reference_by_join_reference_by_topic
@@ -600,7 +660,9 @@ impl Listener {
             }
             Err(web_socket_error) => {
                 reply_tx
-                    .send(Err(channel::CallError::WebSocketError(web_socket_error)))
+                    .send(Err(channel::CallError::WebSocketError(
+                        web_socket_error.into(),
+                    )))
                     .ok();
 
                 self.wait_to_reconnect_connected(connected)
@@ -608,7 +670,7 @@ impl Listener {
         }
     }
 
-    async fn cast(&self, mut connected: Connected, cast: Cast) -> State {
+    async fn cast(&self, mut connected: Connected<T>, cast: Cast) -> State<T> {
         let Cast {
             topic,
             join_reference,
@@ -626,20 +688,38 @@ impl Listener {
             join_reference,
             reference: Some(reference),
         });
-        let data = message.encode().unwrap();
-
-        match connected.socket.send(data).await {
+        match connected.socket.send(message).await {
             Ok(()) => State::Connected(connected),
             Err(_) => self.wait_to_reconnect_connected(connected),
         }
     }
 
-    async fn heartbeat(&self, mut connected: Connected) -> State {
+    async fn heartbeat(&self, mut connected: Connected<T>) -> State<T> {
         match connected.sent_heartbeat_reference {
             Some(_) => {
-                debug!("a heartbeat interval passed with no reply to our previous heartbeat, extending deadline..");
+                connected.missed_heartbeats += 1;
 
-                State::Connected(connected)
+                if connected.missed_heartbeats < self.max_missed_heartbeats {
+                    debug!("a heartbeat interval passed with no reply to our previous heartbeat, extending deadline..");
+
+                    State::Connected(connected)
+                } else {
+                    debug!(
+                        "{} heartbeat intervals passed with no reply to our previous heartbeat, closing connection..",
+                        connected.missed_heartbeats
+                    );
+
+                    // The connection is likely half-open, so don't wait on the close handshake
+                    // longer than a heartbeat interval.
+                    time::timeout(self.heartbeat_interval, connected.socket.close())
+                        .await
+                        .ok();
+
+                    self.wait_to_reconnect_connected_because(
+                        connected,
+                        ReconnectReason::HeartbeatTimeout,
+                    )
+                }
             }
             None => {
                 // Send heartbeat
@@ -662,27 +742,67 @@ impl Listener {
         }
     }
 
-    async fn reconnect(&self, reconnect: Reconnect) -> State {
-        match self.socket_connect(Instant::now(), reconnect).await {
+    async fn reconnect(&self, reconnect: Reconnect) -> State<T> {
+        match self
+            .socket_connect(Instant::now(), reconnect.connect_timeout, reconnect)
+            .await
+        {
             Ok(state) => state,
             Err((_connect_error, reconnect)) => reconnect.wait(),
         }
     }
 
+    /// How the [Socket] reconnects after it connects for `connect`.
+    fn reconnect_after(&self, connect: &Connect) -> Reconnect {
+        Reconnect {
+            connect_timeout: self.connect_timeout.unwrap_or(connect.timeout),
+            attempts: 0,
+            previous_sleep: Duration::ZERO,
+            backoff: self.reconnect_backoff.clone(),
+            max_attempts: self.max_reconnect_attempts,
+            reason: ReconnectReason::ConnectFailed,
+        }
+    }
+
     async fn socket_connect(
         &self,
         created_at: Instant,
+        timeout: Duration,
         reconnect: Reconnect,
-    ) -> Result<State, (ConnectError, Reconnect)> {
-        match time::timeout_at(
-            created_at + reconnect.connect_timeout,
-            tokio_tungstenite::connect_async(self.url.as_ref()),
-        )
-        .await
-        {
+    ) -> Result<State<T>, (ConnectError, Reconnect)> {
+        let connect = async {
+            let connect_params = self.dynamic_params.connect_params().await;
+            let url = match &connect_params {
+                Some(connect_params) => connect_params.url(&self.url),
+                None => self.url.as_ref().clone(),
+            };
+            let mut headers = self.upgrade_headers.clone();
+
+            if let Some(connect_params) = &connect_params {
+                connect_params
+                    .insert_headers(&mut headers)
+                    .map_err(tungstenite::Error::from)?;
+            }
+
+            self.connector
+                .connect(ConnectRequest {
+                    url,
+                    headers,
+                    auth_token: self
+                        .dynamic_params
+                        .auth_token
+                        .load_full()
+                        .map(|auth_token| auth_token.as_ref().clone()),
+                })
+                .await
+        };
+
+        let connect_result = time::timeout_at(created_at + timeout, connect).await;
+
+        match connect_result {
             Ok(connect_result) => match connect_result {
-                Ok((socket, _response)) => {
-                    let duration = Duration::from_secs(30);
+                Ok(socket) => {
+                    let duration = self.heartbeat_interval;
                     let mut heartbeat =
                         time::interval_at((Instant::now() + duration).into(), duration);
                     heartbeat.set_missed_tick_behavior(time::MissedTickBehavior::Skip);
@@ -693,6 +813,7 @@ impl Listener {
                         socket,
                         heartbeat,
                         sent_heartbeat_reference: None,
+                        missed_heartbeats: 0,
                         join_by_reference_by_topic: Default::default(),
                         join_timeouts: Default::default(),
                         broadcast_by_topic: Default::default(),
@@ -702,7 +823,7 @@ impl Listener {
                     }))
                 }
                 Err(error) => {
-                    let arc_error = Arc::new(error);
+                    let arc_error = Arc::new(tungstenite::Error::from(error));
                     debug!("Error connecting to {}: {}", self.url, arc_error);
                     self.socket_status.error(arc_error.clone());
 
@@ -713,15 +834,16 @@ impl Listener {
         }
     }
 
-    async fn shutdown(&self, state: State) -> State {
+    async fn shutdown(&self, state: State<T>) -> State<T> {
         match state {
             State::Connected(mut connected) => {
                 debug!("socket is shutting down");
-                connected.socket.close(None).await.ok();
+                connected.socket.close().await.ok();
                 self.shutdown_connected(connected)
             }
             State::NeverConnected { .. }
             | State::WaitingToReconnect { .. }
+            | State::ReconnectAttemptsExhausted(_)
             | State::Disconnected { .. }
             | State::ShuttingDown
             | State::ShutDown => State::ShuttingDown,
@@ -730,11 +852,11 @@ impl Listener {
 }
 
 #[must_use]
-enum State {
+enum State<T> {
     /// [Socket::connect] has never been called.
     NeverConnected,
     /// [Socket::connect] was called and server responded the socket is connected.
-    Connected(Connected),
+    Connected(Connected<T>),
     /// [Socket::connect] was called previously, but the [Socket] was disconnected by the server and
     /// [Socket] needs to wait to reconnect.
     WaitingToReconnect {
@@ -744,6 +866,9 @@ enum State {
         /// reconnecting has been attempted already.
         reconnect: Reconnect,
     },
+    /// The [Socket] was disconnected, but [SocketOptions::max_reconnect_attempts] automatic
+    /// reconnects all failed, so it won't reconnect until [Socket::connect] is called.
+    ReconnectAttemptsExhausted(ReconnectReason),
     /// [Socket::disconnect] was called and the server responded that the socket as disconnected.
     Disconnected,
     /// [Socket::shutdown] was called, but the async task hasn't exited yet.
@@ -751,19 +876,22 @@ enum State {
     /// The async task has exited.
     ShutDown,
 }
-impl State {
+impl<T> State<T> {
     pub fn status(&self) -> Status {
         match self {
             State::NeverConnected => Status::NeverConnected,
             State::Connected(_) => Status::Connected,
-            State::WaitingToReconnect { sleep, .. } => Status::WaitingToReconnect(sleep.deadline()),
+            State::WaitingToReconnect { sleep, reconnect } => {
+                Status::WaitingToReconnect(sleep.deadline(), reconnect.reason)
+            }
+            State::ReconnectAttemptsExhausted(reason) => Status::ReconnectAttemptsExhausted(*reason),
             State::Disconnected => Status::Disconnected,
             State::ShuttingDown => Status::ShuttingDown,
             State::ShutDown => Status::ShutDown,
         }
     }
 }
-impl Debug for State {
+impl<T> Debug for State<T> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
             State::NeverConnected { .. } => write!(f, "NeverConnected"),
@@ -780,6 +908,10 @@ impl Debug for State {
                 )
                 .field("reconnect", reconnect)
                 .finish(),
+            State::ReconnectAttemptsExhausted(reason) => f
+                .debug_tuple("ReconnectAttemptsExhausted")
+                .field(reason)
+                .finish(),
             State::ShutDown => write!(f, "ShutDown"),
         }
     }
@@ -794,7 +926,10 @@ pub enum Status {
     Connected,
     /// [Socket::connect] was called previously, but the [Socket] was disconnected by the server and
     /// [Socket] needs to wait to reconnect.
-    WaitingToReconnect(Instant),
+    WaitingToReconnect(Instant, ReconnectReason),
+    /// The [Socket] was disconnected, but [SocketOptions::max_reconnect_attempts] automatic
+    /// reconnects all failed, so it won't reconnect until [Socket::connect] is called.
+    ReconnectAttemptsExhausted(ReconnectReason),
     /// [Socket::disconnect] was called and the server responded that the socket as disconnected.
     Disconnected,
     /// [Socket::shutdown] was called, but the async task hasn't exited yet.
@@ -812,10 +947,12 @@ pub(crate) type ObservableStatus =
     crate::rust::observable_status::ObservableStatus<Status, Arc<tungstenite::Error>>;
 
 #[must_use]
-struct Connected {
-    socket: WebSocketStream<MaybeTlsStream<TcpStream>>,
+struct Connected<T> {
+    socket: T,
     heartbeat: Interval,
     sent_heartbeat_reference: Option<Reference>,
+    /// How many heartbeat intervals have passed without a reply to `sent_heartbeat_reference`.
+    missed_heartbeats: u32,
     join_by_reference_by_topic: HashMap<Arc<Topic>, HashMap<JoinReference, Join>>,
     join_timeouts: FuturesUnordered<Pin<Box<dyn Future<Output = JoinKey> + Send + Sync + 'static>>>,
     broadcast_by_topic: HashMap<Arc<Topic>, broadcast::Sender<Broadcast>>,
@@ -830,16 +967,22 @@ struct Connected {
     >,
     connect_timeout: Duration,
 }
-impl Connected {
+impl<T> Connected<T> {
     fn handle_reply(&mut self, reply: Reply) {
         // Check if this is a join reply
         if let Some(join) = self.remove_join(reply.topic.clone(), reply.join_reference.clone()) {
             self.finish_join(reply, join);
-        } else if let Some(reply_tx) = self.remove_reply_tx(
-            reply.topic.clone(),
-            reply.join_reference.clone(),
-            reply.reference.clone(),
-        ) {
+        } else if let Some(reply_tx) = self
+            .remove_reply_tx(
+                reply.topic.clone(),
+                reply.join_reference.clone(),
+                reply.reference.clone(),
+            )
+            // V1 serializer replies have no `join_ref`, but refs are unique, so match by ref alone
+            .or_else(|| {
+                self.remove_reply_tx_by_reference(reply.topic.clone(), reply.reference.clone())
+            })
+        {
             debug!(
                 "received reply on topic {} joined as {} to message ref {}, status is {}",
                 &reply.topic, &reply.join_reference, &reply.reference, &reply.status
@@ -882,8 +1025,10 @@ impl Connected {
                     &join.topic, &reply.join_reference, &reply.reference
                 );
 
-                // Create a new channel for receiving direct messages from the server
-                let (push_tx, push_rx) = mpsc::channel(10);
+                // Create a new channel for receiving direct messages from the server.  It is
+                // unbounded, so that a channel waiting on its subscribers never stops this socket
+                // from reading, and `phx_close` and `phx_error` are never dropped.
+                let (push_tx, push_rx) = mpsc::unbounded_channel();
 
                 // Obtain a new broadcast subscription for the joined channel
                 let broadcast_rx = match self.broadcast_by_topic.entry(join.topic.clone()) {
@@ -911,6 +1056,7 @@ impl Connected {
 
                 // Send the channel join event to the channel listener
                 let joined = JoinedChannelReceivers {
+                    reply: reply.payload,
                     push: push_rx,
                     broadcast: broadcast_rx,
                     left: left_rx,
@@ -978,6 +1124,21 @@ impl Connected {
         Some(reply_tx)
     }
 
+    fn remove_reply_tx_by_reference(
+        &mut self,
+        topic: Arc<Topic>,
+        reference: Reference,
+    ) -> Option<oneshot::Sender<Result<Payload, channel::CallError>>> {
+        let join_reference = self
+            .reply_tx_by_reference_by_join_reference_by_topic
+            .get(&topic)?
+            .iter()
+            .find(|(_, reply_tx_by_reference)| reply_tx_by_reference.contains_key(&reference))
+            .map(|(join_reference, _)| join_reference.clone())?;
+
+        self.remove_reply_tx(topic, join_reference, reference)
+    }
+
     fn remove_by_reference_by_topic<V, R>(
         value_by_reference_by_topic: &mut HashMap<Arc<Topic>, HashMap<R, V>>,
         topic: Arc<Topic>,
@@ -1007,7 +1168,7 @@ impl Connected {
         }
     }
 
-    async fn handle_push(&self, push: Push) {
+    fn handle_push(&self, push: Push) {
         debug!("received push: {:#?}", &push);
 
         if let Some(JoinedChannelSenders { push: push_tx, .. }) = self
@@ -1015,14 +1176,21 @@ impl Connected {
             .get(&push.topic)
             .and_then(|push_tx_by_reference| push_tx_by_reference.get(&push.join_reference.clone()))
         {
-            push_tx.send(push).await.ok();
+            push_tx.send(push).ok();
         }
     }
 
-    fn handle_broadcast(&self, broadcast: Broadcast) {
+    fn handle_broadcast(
+        &self,
+        broadcast: Broadcast,
+        unrouted_broadcast_tx: &broadcast::Sender<Broadcast>,
+    ) {
         debug!("received broadcast: {:#?}", &broadcast);
         if let Some(broadcaster) = self.broadcast_by_topic.get(&broadcast.topic) {
             broadcaster.send(broadcast).ok();
+        } else {
+            // Fails when nothing is subscribed to topics that weren't joined
+            unrouted_broadcast_tx.send(broadcast).ok();
         }
     }
 
@@ -1037,7 +1205,7 @@ impl Connected {
         }
     }
 }
-impl Debug for Connected {
+impl<T> Debug for Connected<T> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         let mut join_references_by_topic: HashMap<&Topic, Vec<&JoinReference>> = HashMap::new();
 
@@ -1071,6 +1239,7 @@ impl Debug for Connected {
             // skip `socket` because it's too noisy
             // skip `heartbeat` because it's to noisy
             .field("sent_heartbeat_reference", &self.sent_heartbeat_reference)
+            .field("missed_heartbeats", &self.missed_heartbeats)
             .field(
                 "join_by_reference_by_topic",
                 &self.join_by_reference_by_topic,
@@ -1087,37 +1256,67 @@ impl Debug for Connected {
     }
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Clone)]
 struct Reconnect {
     connect_timeout: Duration,
     /// Enough for > 1 week of attempts; u8 would only be 42 minutes of attempts.
     attempts: u16,
+    /// The sleep before the previous attempt.
+    previous_sleep: Duration,
+    /// [SocketOptions::reconnect_backoff]
+    backoff: Arc<dyn Backoff>,
+    /// [SocketOptions::max_reconnect_attempts]
+    max_attempts: Option<u32>,
+    /// Why the [Socket] is reconnecting; kept across failed attempts.
+    reason: ReconnectReason,
 }
 impl Reconnect {
-    fn wait(self) -> State {
+    fn wait<T>(self) -> State<T> {
+        if self.is_exhausted() {
+            debug!("giving up reconnecting after {} attempts", self.attempts);
+
+            return State::ReconnectAttemptsExhausted(self.reason);
+        }
+
+        let sleep_duration = self.sleep_duration();
+
         State::WaitingToReconnect {
-            sleep: Box::pin(time::sleep(self.sleep_duration())),
-            reconnect: self.next(),
+            sleep: Box::pin(time::sleep(sleep_duration)),
+            reconnect: self.next(sleep_duration),
         }
     }
 
-    fn next(self) -> Self {
+    fn is_exhausted(&self) -> bool {
+        self.max_attempts
+            .map_or(false, |max_attempts| u32::from(self.attempts) >= max_attempts)
+    }
+
+    fn next(self, previous_sleep: Duration) -> Self {
         Self {
-            connect_timeout: self.connect_timeout,
             attempts: self.attempts.saturating_add(1),
+            previous_sleep,
+            ..self
         }
     }
 
     fn sleep_duration(&self) -> Duration {
-        self.connect_timeout * self.sleep_duration_multiplier()
+        self.backoff.sleep_duration(
+            u32::from(self.attempts),
+            self.connect_timeout,
+            self.previous_sleep,
+        )
     }
-
-    fn sleep_duration_multiplier(&self) -> u32 {
-        Self::SLEEP_DURATION_MULTIPLIERS
-            [(self.attempts as usize).min(Self::SLEEP_DURATION_MULTIPLIERS.len() - 1)]
+}
+impl Debug for Reconnect {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Reconnect")
+            .field("connect_timeout", &self.connect_timeout)
+            .field("attempts", &self.attempts)
+            .field("previous_sleep", &self.previous_sleep)
+            .field("max_attempts", &self.max_attempts)
+            .field("reason", &self.reason)
+            .finish_non_exhaustive()
     }
-
-    const SLEEP_DURATION_MULTIPLIERS: &'static [u32] = &[0, 1, 2, 5, 10];
 }
 
 struct JoinKey {
@@ -1127,7 +1326,7 @@ struct JoinKey {
 
 #[derive(Debug)]
 struct JoinedChannelSenders {
-    push: mpsc::Sender<Push>,
+    push: mpsc::UnboundedSender<Push>,
     left: oneshot::Sender<()>,
 }
 
@@ -1151,6 +1350,7 @@ pub(crate) struct ChannelSpawn {
     pub socket: Arc<Socket>,
     pub topic: Arc<Topic>,
     pub payload: Option<Payload>,
+    pub options: ChannelOptions,
     pub sender: oneshot::Sender<Channel>,
 }
 
@@ -1241,21 +1441,12 @@ pub struct Connect {
     pub timeout: Duration,
     pub connected_tx: oneshot::Sender<Result<(), ConnectError>>,
 }
-impl Connect {
-    fn reconnect(&self) -> Reconnect {
-        Reconnect {
-            connect_timeout: self.timeout,
-            attempts: 0,
-        }
-    }
-}
 
 #[inline]
-fn heartbeat_message(reference: Reference) -> tungstenite::Message {
-    Message::encode(Message::Control(Control {
+fn heartbeat_message(reference: Reference) -> Message {
+    Message::Control(Control {
         event: Event::Phoenix(PhoenixEvent::Heartbeat),
         reference: Some(reference),
         payload: Value::Null.into(),
-    }))
-    .unwrap()
+    })
 }
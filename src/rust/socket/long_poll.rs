@@ -0,0 +1,837 @@
+//! A client for `Phoenix.Transports.LongPoll`, used when web socket upgrades don't get through to
+//! the server, such as behind proxies that strip the `Upgrade` header.
+//!
+//! The server keeps a session process for each long poll client, identified by the `token`
+//! returned from the first `GET`.  Messages from the server are received by repeatedly polling
+//! with `GET` and messages to the server are published one at a time with `POST`.
+
+use std::io;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+use futures::channel::mpsc;
+use futures::{Sink, Stream, StreamExt};
+use log::debug;
+use serde::Deserialize;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::task::JoinHandle;
+use tokio_tungstenite::tungstenite;
+use tokio_tungstenite::tungstenite::http::{HeaderMap, Response, StatusCode};
+use url::{Position, Url};
+
+/// The header `phoenix.js` uses to send the auth token when long polling, as there is no
+/// `Sec-WebSocket-Protocol` header to carry it.
+pub(crate) const AUTH_TOKEN_HEADER: &str = "x-phoenix-authtoken";
+
+/// A long poll session with the server.
+///
+/// Acts like a [tokio_tungstenite::WebSocketStream] that only carries text messages: received
+/// messages are read from the [Stream] and messages are published through the [Sink].
+pub(crate) struct LongPoll {
+    session: Arc<Session>,
+    received_rx: mpsc::UnboundedReceiver<Result<tungstenite::Message, tungstenite::Error>>,
+    publish_tx: mpsc::UnboundedSender<String>,
+    poll_task: JoinHandle<()>,
+    publish_task: JoinHandle<()>,
+    closed: bool,
+}
+impl LongPoll {
+    /// Opens a new long poll session with the server at the web socket `url`.
+    pub(crate) async fn connect(url: &Url, headers: HeaderMap) -> Result<Self, LongPollError> {
+        let endpoint = endpoint(url);
+        let response = http_request("GET", &endpoint, &headers, None).await?;
+        let reply = response.reply()?;
+
+        let token = match reply {
+            Reply {
+                status: 410,
+                token: Some(token),
+                ..
+            } => token,
+            Reply { status, .. } => {
+                return Err(LongPollError::Rejected(HttpResponse {
+                    status,
+                    body: response.body,
+                }))
+            }
+        };
+
+        let session = Arc::new(Session {
+            endpoint,
+            token: Mutex::new(token),
+            headers,
+        });
+        let (received_tx, received_rx) = mpsc::unbounded();
+        let (publish_tx, publish_rx) = mpsc::unbounded();
+
+        let poll_task = tokio::spawn(Self::poll(session.clone(), received_tx.clone()));
+        let publish_task = tokio::spawn(Self::publish(session.clone(), publish_rx, received_tx));
+
+        Ok(Self {
+            session,
+            received_rx,
+            publish_tx,
+            poll_task,
+            publish_task,
+            closed: false,
+        })
+    }
+
+    async fn poll(
+        session: Arc<Session>,
+        received_tx: mpsc::UnboundedSender<Result<tungstenite::Message, tungstenite::Error>>,
+    ) {
+        let error = loop {
+            match session.request("GET", None).await {
+                Ok(Reply {
+                    status: 200,
+                    messages,
+                    ..
+                }) => {
+                    for message in messages {
+                        if received_tx
+                            .unbounded_send(Ok(tungstenite::Message::Text(message)))
+                            .is_err()
+                        {
+                            return;
+                        }
+                    }
+                }
+                // The server's poll window passed without any messages
+                Ok(Reply { status: 204, .. }) => (),
+                // The server's session process is gone, so a new session is needed.
+                Ok(Reply { status: 410, .. }) => break tungstenite::Error::ConnectionClosed,
+                Ok(Reply { status, .. }) => {
+                    break tungstenite::Error::Io(io::Error::new(
+                        io::ErrorKind::Other,
+                        format!("long poll failed with status {}", status),
+                    ))
+                }
+                Err(error) => break tungstenite::Error::Io(error),
+            }
+        };
+
+        received_tx.unbounded_send(Err(error)).ok();
+    }
+
+    async fn publish(
+        session: Arc<Session>,
+        mut publish_rx: mpsc::UnboundedReceiver<String>,
+        received_tx: mpsc::UnboundedSender<Result<tungstenite::Message, tungstenite::Error>>,
+    ) {
+        while let Some(body) = publish_rx.next().await {
+            let error = match session.request("POST", Some(&body)).await {
+                Ok(Reply { status: 200, .. }) => continue,
+                Ok(Reply { status: 410, .. }) => tungstenite::Error::ConnectionClosed,
+                Ok(Reply { status, .. }) => tungstenite::Error::Io(io::Error::new(
+                    io::ErrorKind::Other,
+                    format!("long poll publish failed with status {}", status),
+                )),
+                Err(error) => tungstenite::Error::Io(error),
+            };
+
+            received_tx.unbounded_send(Err(error)).ok();
+
+            break;
+        }
+    }
+
+    /// Stops polling and tells the server to close the session, without waiting for it to do so.
+    fn close_session(&mut self) {
+        if self.closed {
+            return;
+        }
+
+        self.closed = true;
+        self.poll_task.abort();
+        self.publish_task.abort();
+        self.received_rx.close();
+
+        let session = self.session.clone();
+
+        tokio::spawn(async move {
+            if let Err(error) = session.request("DELETE", None).await {
+                debug!("Error closing long poll session: {}", error);
+            }
+        });
+    }
+}
+impl Stream for LongPoll {
+    type Item = Result<tungstenite::Message, tungstenite::Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.received_rx.poll_next_unpin(cx)
+    }
+}
+impl Sink<tungstenite::Message> for LongPoll {
+    type Error = tungstenite::Error;
+
+    fn poll_ready(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        if self.closed {
+            Poll::Ready(Err(tungstenite::Error::AlreadyClosed))
+        } else {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    fn start_send(mut self: Pin<&mut Self>, message: tungstenite::Message) -> Result<(), Self::Error> {
+        if self.closed {
+            return Err(tungstenite::Error::AlreadyClosed);
+        }
+
+        match message {
+            tungstenite::Message::Text(text) => self
+                .publish_tx
+                .unbounded_send(text)
+                .map_err(|_| tungstenite::Error::ConnectionClosed),
+            tungstenite::Message::Binary(_) => Err(tungstenite::Error::Io(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "binary messages can't be sent when long polling",
+            ))),
+            // The server's session process is monitored instead of pinged
+            tungstenite::Message::Ping(_)
+            | tungstenite::Message::Pong(_)
+            | tungstenite::Message::Frame(_) => Ok(()),
+            tungstenite::Message::Close(_) => {
+                self.close_session();
+
+                Ok(())
+            }
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.close_session();
+
+        Poll::Ready(Ok(()))
+    }
+}
+impl Drop for LongPoll {
+    fn drop(&mut self) {
+        self.poll_task.abort();
+        self.publish_task.abort();
+    }
+}
+
+/// Why a long poll session could not be opened.
+#[derive(Debug)]
+pub(crate) enum LongPollError {
+    /// The HTTP request could not be sent or the response could not be read.
+    Io(io::Error),
+    /// The server did not open a session, such as when `connect` in the socket handler returned
+    /// `:error` or the endpoint does not have `longpoll` enabled.
+    Rejected(HttpResponse),
+}
+impl From<io::Error> for LongPollError {
+    fn from(error: io::Error) -> Self {
+        LongPollError::Io(error)
+    }
+}
+impl From<LongPollError> for tungstenite::Error {
+    fn from(error: LongPollError) -> Self {
+        match error {
+            LongPollError::Io(error) => tungstenite::Error::Io(error),
+            LongPollError::Rejected(HttpResponse { status, body }) => {
+                let mut response = Response::new(Some(body));
+                *response.status_mut() =
+                    StatusCode::from_u16(status).unwrap_or(StatusCode::BAD_GATEWAY);
+
+                tungstenite::Error::Http(response)
+            }
+        }
+    }
+}
+
+/// The long poll endpoint next to the web socket endpoint `url`, the same as `phoenix.js`:
+/// `ws(s)://host/socket/websocket` becomes `http(s)://host/socket/longpoll`.
+pub(crate) fn endpoint(url: &Url) -> Url {
+    let mut endpoint = url.clone();
+
+    let scheme = match url.scheme() {
+        "ws" => Some("http"),
+        "wss" => Some("https"),
+        _ => None,
+    };
+
+    if let Some(scheme) = scheme {
+        // ws and wss are special schemes like http and https, so this can't fail
+        endpoint.set_scheme(scheme).ok();
+    }
+
+    if let Some(path) = url.path().strip_suffix("/websocket") {
+        endpoint.set_path(&format!("{}/longpoll", path));
+    }
+
+    endpoint
+}
+
+struct Session {
+    /// [endpoint] without the session `token`.
+    endpoint: Url,
+    /// The `token` from the latest response, which the server may change on any response, so it
+    /// is replaced each time like `phoenix.js` does.
+    token: Mutex<String>,
+    headers: HeaderMap,
+}
+impl Session {
+    async fn request(&self, method: &str, body: Option<&str>) -> io::Result<Reply> {
+        let mut url = self.endpoint.clone();
+        url.query_pairs_mut()
+            .append_pair("token", &self.token.lock().unwrap());
+
+        let reply = http_request(method, &url, &self.headers, body)
+            .await?
+            .reply()?;
+
+        if let Some(token) = &reply.token {
+            *self.token.lock().unwrap() = token.clone();
+        }
+
+        Ok(reply)
+    }
+}
+
+/// The JSON body of every `Phoenix.Transports.LongPoll` response.  The HTTP status is always
+/// `200`; the real status is in the body.
+#[derive(Debug, Deserialize)]
+struct Reply {
+    status: u16,
+    #[serde(default)]
+    token: Option<String>,
+    /// Already serialized messages.
+    #[serde(default)]
+    messages: Vec<String>,
+}
+
+#[derive(Debug)]
+pub(crate) struct HttpResponse {
+    status: u16,
+    body: Vec<u8>,
+}
+impl HttpResponse {
+    fn reply(&self) -> io::Result<Reply> {
+        if self.status != 200 {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                format!("long poll endpoint responded with HTTP status {}", self.status),
+            ));
+        }
+
+        serde_json::from_slice(&self.body).map_err(|error| invalid_data(error.to_string()))
+    }
+}
+
+/// Sends a single HTTP/1.1 request on a new connection, so that a slow poll never holds up
+/// publishing.
+async fn http_request(
+    method: &str,
+    url: &Url,
+    headers: &HeaderMap,
+    body: Option<&str>,
+) -> io::Result<HttpResponse> {
+    let host = url
+        .host_str()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "URL has no host"))?;
+    let port = url
+        .port_or_known_default()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "URL has no port"))?;
+
+    let mut request = format!(
+        "{} {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\nAccept: application/json\r\n",
+        method,
+        &url[Position::BeforePath..],
+        &url[Position::BeforeHost..Position::AfterPort]
+    )
+    .into_bytes();
+
+    for (name, value) in headers {
+        request.extend_from_slice(name.as_str().as_bytes());
+        request.extend_from_slice(b": ");
+        request.extend_from_slice(value.as_bytes());
+        request.extend_from_slice(b"\r\n");
+    }
+
+    let body = body.unwrap_or_default();
+    request.extend_from_slice(
+        format!(
+            "Content-Type: application/json\r\nContent-Length: {}\r\n\r\n",
+            body.len()
+        )
+        .as_bytes(),
+    );
+    request.extend_from_slice(body.as_bytes());
+
+    let stream = TcpStream::connect((host, port)).await?;
+
+    let response = match url.scheme() {
+        "http" => exchange(stream, &request).await?,
+        "https" => tls_exchange(host, stream, &request).await?,
+        scheme => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("unsupported long poll URL scheme {}", scheme),
+            ))
+        }
+    };
+
+    parse_response(&response)
+}
+
+#[cfg(feature = "native-tls")]
+async fn tls_exchange(host: &str, stream: TcpStream, request: &[u8]) -> io::Result<Vec<u8>> {
+    let connector = tokio_native_tls::native_tls::TlsConnector::new()
+        .map_err(|error| io::Error::new(io::ErrorKind::Other, error))?;
+    let stream = tokio_native_tls::TlsConnector::from(connector)
+        .connect(host, stream)
+        .await
+        .map_err(|error| io::Error::new(io::ErrorKind::Other, error))?;
+
+    exchange(stream, request).await
+}
+
+#[cfg(not(feature = "native-tls"))]
+async fn tls_exchange(_host: &str, _stream: TcpStream, _request: &[u8]) -> io::Result<Vec<u8>> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "https long polling requires the `native-tls` feature",
+    ))
+}
+
+/// Writes `request` and reads the response until the server closes the connection.
+async fn exchange<S>(mut stream: S, request: &[u8]) -> io::Result<Vec<u8>>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    stream.write_all(request).await?;
+    stream.flush().await?;
+
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).await?;
+
+    Ok(response)
+}
+
+fn parse_response(bytes: &[u8]) -> io::Result<HttpResponse> {
+    let mut headers = [httparse::EMPTY_HEADER; 64];
+    let mut response = httparse::Response::new(&mut headers);
+
+    let body_start = match response
+        .parse(bytes)
+        .map_err(|error| invalid_data(error.to_string()))?
+    {
+        httparse::Status::Complete(body_start) => body_start,
+        httparse::Status::Partial => return Err(invalid_data("incomplete HTTP response")),
+    };
+    let status = response
+        .code
+        .ok_or_else(|| invalid_data("HTTP response has no status"))?;
+
+    let mut chunked = false;
+    let mut content_length = None;
+
+    for header in response.headers.iter() {
+        if header.name.eq_ignore_ascii_case("transfer-encoding") {
+            chunked = String::from_utf8_lossy(header.value)
+                .to_ascii_lowercase()
+                .contains("chunked");
+        } else if header.name.eq_ignore_ascii_case("content-length") {
+            content_length = std::str::from_utf8(header.value)
+                .ok()
+                .and_then(|value| value.trim().parse::<usize>().ok());
+        }
+    }
+
+    let body = &bytes[body_start..];
+    let body = if chunked {
+        decode_chunked(body)?
+    } else {
+        match content_length {
+            Some(content_length) => body[..content_length.min(body.len())].to_vec(),
+            None => body.to_vec(),
+        }
+    };
+
+    Ok(HttpResponse { status, body })
+}
+
+fn decode_chunked(mut body: &[u8]) -> io::Result<Vec<u8>> {
+    let mut decoded = Vec::new();
+
+    loop {
+        let (start, size) = match httparse::parse_chunk_size(body) {
+            Ok(httparse::Status::Complete(chunk)) => chunk,
+            _ => return Err(invalid_data("invalid chunked HTTP body")),
+        };
+
+        if size == 0 {
+            return Ok(decoded);
+        }
+
+        let end = start + size as usize;
+        let chunk = body
+            .get(start..end)
+            .ok_or_else(|| invalid_data("truncated chunked HTTP body"))?;
+        decoded.extend_from_slice(chunk);
+
+        // Skip the CRLF after the chunk
+        body = body
+            .get(end + 2..)
+            .ok_or_else(|| invalid_data("truncated chunked HTTP body"))?;
+    }
+}
+
+fn invalid_data<E>(error: E) -> io::Error
+where
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+    use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    use futures::SinkExt;
+    use tokio::net::TcpListener;
+    use tokio::time;
+
+    use super::*;
+    use crate::{BackoffStrategy, Socket, SocketOptions, SocketStatus};
+
+    #[test]
+    fn endpoint_replaces_websocket_with_longpoll() {
+        let url = Url::parse("wss://example.com/socket/websocket?vsn=2.0.0").unwrap();
+
+        assert_eq!(
+            endpoint(&url).as_str(),
+            "https://example.com/socket/longpoll?vsn=2.0.0"
+        );
+    }
+
+    #[test]
+    fn parse_response_decodes_chunked_body() {
+        let response = parse_response(
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nhello\r\n6\r\n world\r\n0\r\n\r\n",
+        )
+        .unwrap();
+
+        assert_eq!(response.status, 200);
+        assert_eq!(response.body, b"hello world");
+    }
+
+    #[tokio::test]
+    async fn messages_are_published_and_polled() {
+        let (url, published) = spawn_long_poll_server().await;
+
+        let mut long_poll = LongPoll::connect(&url, HeaderMap::new()).await.unwrap();
+        long_poll
+            .send(tungstenite::Message::Text("hello".to_string()))
+            .await
+            .unwrap();
+
+        let received = time::timeout(Duration::from_secs(5), long_poll.next())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(received, tungstenite::Message::Text("hello".to_string()));
+        assert!(published.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn binary_messages_are_not_sent() {
+        let (url, published) = spawn_long_poll_server().await;
+
+        let mut long_poll = LongPoll::connect(&url, HeaderMap::new()).await.unwrap();
+
+        assert!(long_poll
+            .send(tungstenite::Message::Binary(vec![1]))
+            .await
+            .is_err());
+        assert!(published.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn token_is_refreshed_from_each_response() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = Url::parse(&format!(
+            "ws://{}/socket/websocket",
+            listener.local_addr().unwrap()
+        ))
+        .unwrap();
+
+        // Changes the token on the first poll and forgets the session for any outdated token
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let (head, _) = read_request(&mut stream).await;
+                let request_line = head.lines().next().unwrap().to_string();
+
+                let body = if !request_line.contains("token=") {
+                    r#"{"status":410,"token":"first"}"#
+                } else if request_line.contains("token=first") {
+                    r#"{"status":200,"token":"second","messages":["one"]}"#
+                } else if request_line.contains("token=second") {
+                    r#"{"status":200,"token":"second","messages":["two"]}"#
+                } else {
+                    r#"{"status":410}"#
+                };
+
+                respond(&mut stream, body).await;
+            }
+        });
+
+        let mut long_poll = LongPoll::connect(&url, HeaderMap::new()).await.unwrap();
+
+        for expected in ["one", "two"] {
+            let received = time::timeout(Duration::from_secs(5), long_poll.next())
+                .await
+                .unwrap()
+                .unwrap()
+                .unwrap();
+
+            assert_eq!(received, tungstenite::Message::Text(expected.to_string()));
+        }
+    }
+
+    #[tokio::test]
+    async fn server_refusing_session_is_rejected() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = Url::parse(&format!(
+            "ws://{}/socket/websocket",
+            listener.local_addr().unwrap()
+        ))
+        .unwrap();
+
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            read_request(&mut stream).await;
+            respond(&mut stream, r#"{"status":403}"#).await;
+        });
+
+        assert!(matches!(
+            LongPoll::connect(&url, HeaderMap::new()).await,
+            Err(LongPollError::Rejected(HttpResponse { status: 403, .. }))
+        ));
+    }
+
+    #[tokio::test]
+    async fn socket_falls_back_to_long_poll_after_failed_upgrades() {
+        let (url, _) = spawn_long_poll_server().await;
+        let socket = Socket::spawn_with_options(
+            url,
+            SocketOptions::default()
+                .with_long_poll_fallback_after(1)
+                .with_reconnect_backoff(BackoffStrategy::Fixed {
+                    sleep: Duration::from_millis(10),
+                }),
+        )
+        .unwrap();
+
+        // The stand-in refuses the web socket upgrade
+        assert!(socket.connect(Duration::from_secs(5)).await.is_err());
+
+        for _ in 0..500 {
+            if socket.status() == SocketStatus::Connected {
+                break;
+            }
+
+            time::sleep(Duration::from_millis(10)).await;
+        }
+
+        assert_eq!(socket.status(), SocketStatus::Connected);
+
+        socket.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn socket_does_not_fall_back_after_web_socket_connected() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = Url::parse(&format!(
+            "ws://{}/socket/websocket?vsn=2.0.0",
+            listener.local_addr().unwrap()
+        ))
+        .unwrap();
+        let down = Arc::new(AtomicBool::new(false));
+        let refused = Arc::new(AtomicU32::new(0));
+        let upgrades = Arc::new(AtomicU32::new(0));
+
+        // Upgrades web sockets, which are closed once `down`, and refuses connections while `down`
+        let server_down = down.clone();
+        let server_refused = refused.clone();
+        let server_upgrades = upgrades.clone();
+        tokio::spawn(async move {
+            loop {
+                let (stream, _) = listener.accept().await.unwrap();
+
+                if server_down.load(Ordering::SeqCst) {
+                    server_refused.fetch_add(1, Ordering::SeqCst);
+
+                    continue;
+                }
+
+                let down = server_down.clone();
+                let upgrades = server_upgrades.clone();
+                tokio::spawn(async move {
+                    let _web_socket = match tokio_tungstenite::accept_async(stream).await {
+                        Ok(web_socket) => web_socket,
+                        Err(_) => return,
+                    };
+                    upgrades.fetch_add(1, Ordering::SeqCst);
+
+                    while !down.load(Ordering::SeqCst) {
+                        time::sleep(Duration::from_millis(10)).await;
+                    }
+                });
+            }
+        });
+
+        let socket = Socket::spawn_with_options(
+            url,
+            SocketOptions::default()
+                .with_long_poll_fallback_after(2)
+                .with_reconnect_backoff(BackoffStrategy::Fixed {
+                    sleep: Duration::from_millis(10),
+                }),
+        )
+        .unwrap();
+        socket.connect(Duration::from_secs(5)).await.unwrap();
+
+        down.store(true, Ordering::SeqCst);
+
+        for _ in 0..500 {
+            if refused.load(Ordering::SeqCst) > 3 {
+                break;
+            }
+
+            time::sleep(Duration::from_millis(10)).await;
+        }
+
+        assert!(refused.load(Ordering::SeqCst) > 3);
+
+        down.store(false, Ordering::SeqCst);
+
+        for _ in 0..500 {
+            if upgrades.load(Ordering::SeqCst) == 2 && socket.status() == SocketStatus::Connected {
+                break;
+            }
+
+            time::sleep(Duration::from_millis(10)).await;
+        }
+
+        assert_eq!(upgrades.load(Ordering::SeqCst), 2);
+        assert_eq!(socket.status(), SocketStatus::Connected);
+
+        socket.shutdown().await.unwrap();
+    }
+
+    /// A stand-in for `Phoenix.Transports.LongPoll` that echoes every published message back on
+    /// the next poll and refuses web socket upgrades.
+    async fn spawn_long_poll_server() -> (Url, Arc<Mutex<VecDeque<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = Url::parse(&format!(
+            "ws://{}/socket/websocket?vsn=2.0.0",
+            listener.local_addr().unwrap()
+        ))
+        .unwrap();
+        let published: Arc<Mutex<VecDeque<String>>> = Default::default();
+        let server_published = published.clone();
+
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let published = server_published.clone();
+
+                tokio::spawn(async move {
+                    let (head, body) = read_request(&mut stream).await;
+                    let request_line = head.lines().next().unwrap().to_string();
+
+                    if !request_line.contains("/socket/longpoll") {
+                        stream
+                            .write_all(b"HTTP/1.1 400 Bad Request\r\nContent-Length: 0\r\n\r\n")
+                            .await
+                            .ok();
+                    } else if !request_line.contains("token=") {
+                        respond(&mut stream, r#"{"status":410,"token":"session","messages":[]}"#)
+                            .await;
+                    } else if request_line.starts_with("POST") {
+                        published.lock().unwrap().push_back(body);
+                        respond(&mut stream, r#"{"status":200}"#).await;
+                    } else if request_line.starts_with("DELETE") {
+                        respond(&mut stream, r#"{"status":200}"#).await;
+                    } else {
+                        for _ in 0..10 {
+                            let message = published.lock().unwrap().pop_front();
+
+                            if let Some(message) = message {
+                                let reply = serde_json::json!({
+                                    "status": 200,
+                                    "token": "session",
+                                    "messages": [message]
+                                });
+                                respond(&mut stream, &reply.to_string()).await;
+
+                                return;
+                            }
+
+                            time::sleep(Duration::from_millis(10)).await;
+                        }
+
+                        respond(&mut stream, r#"{"status":204,"token":"session"}"#).await;
+                    }
+                });
+            }
+        });
+
+        (url, published)
+    }
+
+    async fn read_request(stream: &mut TcpStream) -> (String, String) {
+        let mut bytes = Vec::new();
+        let mut buffer = [0; 1024];
+
+        loop {
+            let read = stream.read(&mut buffer).await.unwrap();
+            bytes.extend_from_slice(&buffer[..read]);
+
+            if let Some(head_end) = bytes.windows(4).position(|window| window == b"\r\n\r\n") {
+                let head = String::from_utf8_lossy(&bytes[..head_end]).to_string();
+                let content_length = head
+                    .lines()
+                    .find_map(|line| line.strip_prefix("Content-Length: "))
+                    .map_or(0, |value| value.parse::<usize>().unwrap());
+                let body_start = head_end + 4;
+
+                while bytes.len() < body_start + content_length {
+                    let read = stream.read(&mut buffer).await.unwrap();
+                    bytes.extend_from_slice(&buffer[..read]);
+                }
+
+                let body = String::from_utf8_lossy(&bytes[body_start..]).to_string();
+
+                return (head, body);
+            }
+        }
+    }
+
+    async fn respond(stream: &mut TcpStream, body: &str) {
+        let response = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        );
+
+        stream.write_all(response.as_bytes()).await.ok();
+        stream.shutdown().await.ok();
+    }
+}
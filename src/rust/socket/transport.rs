@@ -0,0 +1,542 @@
+use std::io;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::Arc;
+use std::task::{ready, Context, Poll};
+
+use base64::engine::general_purpose::STANDARD_NO_PAD;
+use base64::Engine;
+use futures::future::BoxFuture;
+use futures::{Sink, SinkExt, Stream, StreamExt};
+use log::debug;
+use tokio::net::TcpStream;
+use tokio::sync::broadcast;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::http::header::SEC_WEBSOCKET_PROTOCOL;
+use tokio_tungstenite::tungstenite::http::HeaderValue;
+use tokio_tungstenite::{tungstenite, MaybeTlsStream, WebSocketStream};
+
+use crate::ffi::socket::frames::{WireFrame, WireFrameDirection};
+use crate::ffi::socket::options::SocketOptions;
+use crate::ffi::web_socket::protocol::WebSocketMessage;
+use crate::rust::message::{Message, MessageDecodingError};
+use crate::rust::serializer::Serializer;
+use crate::rust::socket::long_poll;
+use crate::rust::socket::long_poll::LongPoll;
+use crate::rust::transport::{ConnectRequest, Connector, Transport, TransportError};
+
+/// Connects with web sockets, falling back to [LongPoll] after
+/// [SocketOptions::long_poll_fallback_after] web socket upgrades failed in a row before any
+/// succeeded.
+pub(crate) struct DefaultConnector {
+    /// [SocketOptions::subprotocols], which are sent before the auth token subprotocol.
+    subprotocols: Vec<String>,
+    /// [SocketOptions::long_poll_fallback_after]
+    long_poll_fallback_after: Option<u32>,
+    /// Web socket upgrades that failed or timed out in a row.
+    failed_web_socket_connects: AtomicU32,
+    /// Whether any web socket upgrade succeeded, after which failures are the server being down
+    /// rather than the network blocking web sockets, so [LongPoll] is never used, like
+    /// `primaryPassedHealthCheck` in `phoenix.js`.
+    web_socket_succeeded: AtomicBool,
+    /// Encodes and decodes the frames of each [DefaultTransport].
+    serializer: Arc<dyn Serializer>,
+    frame_tap: FrameTap,
+}
+impl DefaultConnector {
+    pub(crate) fn new(
+        options: &SocketOptions,
+        serializer: Arc<dyn Serializer>,
+        frame_tap: FrameTap,
+    ) -> Self {
+        Self {
+            subprotocols: options.subprotocols.clone().unwrap_or_default(),
+            long_poll_fallback_after: options.long_poll_fallback_after,
+            failed_web_socket_connects: AtomicU32::new(0),
+            web_socket_succeeded: AtomicBool::new(false),
+            serializer,
+            frame_tap,
+        }
+    }
+
+    /// Whether to connect with [LongPoll] because [SocketOptions::long_poll_fallback_after] web
+    /// socket upgrades failed in a row and none ever succeeded.
+    fn uses_long_poll(&self) -> bool {
+        if self.web_socket_succeeded.load(Ordering::Relaxed) {
+            return false;
+        }
+
+        self.long_poll_fallback_after
+            .map_or(false, |fallback_after| {
+                self.failed_web_socket_connects.load(Ordering::Relaxed) >= fallback_after
+            })
+    }
+
+    async fn connect_long_poll(
+        &self,
+        request: ConnectRequest,
+    ) -> Result<DefaultTransport, TransportError> {
+        let ConnectRequest {
+            url,
+            mut headers,
+            auth_token,
+        } = request;
+
+        debug!(
+            "long polling {} after {} failed web socket upgrades",
+            url,
+            self.failed_web_socket_connects.load(Ordering::Relaxed)
+        );
+
+        // Only web sockets negotiate subprotocols
+        headers.remove(SEC_WEBSOCKET_PROTOCOL);
+
+        if let Some(auth_token) = auth_token {
+            let auth_token = HeaderValue::from_str(&auth_token)
+                .map_err(|error| tungstenite::Error::HttpFormat(error.into()))?;
+            headers.insert(long_poll::AUTH_TOKEN_HEADER, auth_token);
+        }
+
+        let long_poll = LongPoll::connect(&url, headers)
+            .await
+            .map_err(tungstenite::Error::from)?;
+
+        Ok(DefaultTransport {
+            connection: Connection::LongPoll(long_poll),
+            serializer: self.serializer.clone(),
+            frame_tap: self.frame_tap.clone(),
+            close_tapped: false,
+        })
+    }
+
+    async fn connect_web_socket(
+        &self,
+        request: ConnectRequest,
+    ) -> Result<DefaultTransport, TransportError> {
+        let ConnectRequest {
+            url,
+            headers,
+            auth_token,
+        } = request;
+
+        // Counted before connecting, so that timeouts, which drop this future, also count.
+        self.failed_web_socket_connects
+            .fetch_add(1, Ordering::Relaxed);
+
+        // A new upgrade request, with a new `Sec-WebSocket-Key`, for each connection attempt.
+        let mut upgrade_request = url.into_client_request()?;
+        upgrade_request.headers_mut().extend(headers);
+
+        if let Some(auth_token) = auth_token {
+            upgrade_request.headers_mut().insert(
+                SEC_WEBSOCKET_PROTOCOL,
+                auth_token_subprotocols(&self.subprotocols, &auth_token),
+            );
+        }
+
+        let (socket, _response) = tokio_tungstenite::connect_async(upgrade_request).await?;
+
+        self.failed_web_socket_connects.store(0, Ordering::Relaxed);
+        self.web_socket_succeeded.store(true, Ordering::Relaxed);
+
+        Ok(DefaultTransport {
+            connection: Connection::WebSocket(Box::new(socket)),
+            serializer: self.serializer.clone(),
+            frame_tap: self.frame_tap.clone(),
+            close_tapped: false,
+        })
+    }
+}
+impl Connector for DefaultConnector {
+    type Transport = DefaultTransport;
+
+    fn connect(&self, request: ConnectRequest) -> BoxFuture<'_, Result<DefaultTransport, TransportError>> {
+        Box::pin(async move {
+            if self.uses_long_poll() {
+                self.connect_long_poll(request).await
+            } else {
+                self.connect_web_socket(request).await
+            }
+        })
+    }
+}
+
+/// The [Transport](crate::rust::transport::Transport) opened by [DefaultConnector].
+pub(crate) struct DefaultTransport {
+    connection: Connection,
+    /// [DefaultConnector::serializer]
+    serializer: Arc<dyn Serializer>,
+    frame_tap: FrameTap,
+    /// Whether the close frame sent by [DefaultTransport::poll_close] was tapped, as it is polled
+    /// until the close completes.
+    close_tapped: bool,
+}
+enum Connection {
+    WebSocket(Box<WebSocketStream<MaybeTlsStream<TcpStream>>>),
+    /// Used after [SocketOptions::long_poll_fallback_after] web socket upgrades failed.
+    LongPoll(LongPoll),
+}
+impl DefaultTransport {
+    /// The [Message] in `frame`, if it is a data frame.
+    fn decode(&self, frame: tungstenite::Message) -> Option<Result<Message, TransportError>> {
+        let web_socket_message = if self.frame_tap.is_tapped() {
+            Some(WebSocketMessage::from(&frame))
+        } else {
+            None
+        };
+
+        match frame {
+            // tungstenite replies with a pong the next time the socket is read
+            tungstenite::Message::Ping(bytes) => {
+                debug!("client received ping");
+
+                if let Some(web_socket_message) = web_socket_message {
+                    self.frame_tap
+                        .tap(WireFrameDirection::Inbound, None, web_socket_message);
+                    // The pong never passes through this sink, so it is tapped when queued.
+                    self.frame_tap.tap(
+                        WireFrameDirection::Outbound,
+                        None,
+                        WebSocketMessage::Pong { bytes },
+                    );
+                }
+
+                None
+            }
+            tungstenite::Message::Pong(_) => {
+                debug!("client received pong");
+
+                if let Some(web_socket_message) = web_socket_message {
+                    self.frame_tap
+                        .tap(WireFrameDirection::Inbound, None, web_socket_message);
+                }
+
+                None
+            }
+            tungstenite::Message::Close(close_frame) => {
+                let infix = match &close_frame {
+                    Some(close_frame) => {
+                        format!(" ({})", close_frame)
+                    }
+                    None => "".to_string(),
+                };
+
+                debug!(
+                    "Client received close{} from server. Waiting to reconnect.",
+                    infix
+                );
+
+                if let Some(web_socket_message) = web_socket_message {
+                    self.frame_tap
+                        .tap(WireFrameDirection::Inbound, None, web_socket_message);
+                }
+
+                Some(Err(TransportError::ConnectionClosed))
+            }
+            frame @ tungstenite::Message::Binary(_) | frame @ tungstenite::Message::Text(_) => {
+                match self.serializer.decode(frame) {
+                    Ok(message) => {
+                        if let Some(web_socket_message) = web_socket_message {
+                            self.frame_tap.tap(
+                                WireFrameDirection::Inbound,
+                                Some(Ok(message.clone())),
+                                web_socket_message,
+                            );
+                        }
+
+                        Some(Ok(message))
+                    }
+                    Err(err) => {
+                        debug!("dropping invalid message received from server, due to error decoding: {}", &err);
+
+                        if let Some(web_socket_message) = web_socket_message {
+                            self.frame_tap.tap(
+                                WireFrameDirection::Inbound,
+                                Some(Err(err)),
+                                web_socket_message,
+                            );
+                        }
+
+                        None
+                    }
+                }
+            }
+            tungstenite::Message::Frame(_) => unreachable!(),
+        }
+    }
+}
+impl Stream for DefaultTransport {
+    type Item = Result<Message, TransportError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        loop {
+            let frame = match &mut this.connection {
+                Connection::WebSocket(web_socket) => ready!(web_socket.poll_next_unpin(cx)),
+                Connection::LongPoll(long_poll) => ready!(long_poll.poll_next_unpin(cx)),
+            };
+
+            match frame {
+                Some(Ok(frame)) => {
+                    if let Some(result) = this.decode(frame) {
+                        return Poll::Ready(Some(result));
+                    }
+                }
+                Some(Err(error)) => return Poll::Ready(Some(Err(error.into()))),
+                None => return Poll::Ready(None),
+            }
+        }
+    }
+}
+impl Sink<Message> for DefaultTransport {
+    type Error = TransportError;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        match &mut self.get_mut().connection {
+            Connection::WebSocket(web_socket) => web_socket.poll_ready_unpin(cx),
+            Connection::LongPoll(long_poll) => long_poll.poll_ready_unpin(cx),
+        }
+        .map_err(From::from)
+    }
+
+    fn start_send(self: Pin<&mut Self>, message: Message) -> Result<(), Self::Error> {
+        let this = self.get_mut();
+        let tapped_message = if this.frame_tap.is_tapped() {
+            Some(message.clone())
+        } else {
+            None
+        };
+        let frame = this
+            .serializer
+            .encode(message)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+
+        if let Some(message) = tapped_message {
+            this.frame_tap.tap(
+                WireFrameDirection::Outbound,
+                Some(Ok(message)),
+                WebSocketMessage::from(&frame),
+            );
+        }
+
+        match &mut this.connection {
+            Connection::WebSocket(web_socket) => web_socket.start_send_unpin(frame),
+            Connection::LongPoll(long_poll) => long_poll.start_send_unpin(frame),
+        }
+        .map_err(From::from)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        match &mut self.get_mut().connection {
+            Connection::WebSocket(web_socket) => web_socket.poll_flush_unpin(cx),
+            Connection::LongPoll(long_poll) => long_poll.poll_flush_unpin(cx),
+        }
+        .map_err(From::from)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+
+        match &mut this.connection {
+            Connection::WebSocket(web_socket) => {
+                // tungstenite sends the close frame itself, so it is tapped on the first poll.
+                if !this.close_tapped {
+                    this.close_tapped = true;
+                    this.frame_tap.tap(
+                        WireFrameDirection::Outbound,
+                        None,
+                        WebSocketMessage::Close { close_frame: None },
+                    );
+                }
+
+                web_socket.poll_close_unpin(cx)
+            }
+            Connection::LongPoll(long_poll) => long_poll.poll_close_unpin(cx),
+        }
+        .map_err(From::from)
+    }
+}
+
+/// Sends [WireFrame]s to [Socket::frames](crate::Socket::frames).
+#[derive(Clone)]
+pub(crate) struct FrameTap {
+    frame_tx: broadcast::Sender<Arc<WireFrame>>,
+}
+impl FrameTap {
+    pub(crate) fn new(frame_tx: broadcast::Sender<Arc<WireFrame>>) -> Self {
+        Self { frame_tx }
+    }
+
+    /// Whether anything is subscribed to [Socket::frames](crate::Socket::frames), so that frames
+    /// are only copied when they are observed.
+    fn is_tapped(&self) -> bool {
+        self.frame_tx.receiver_count() > 0
+    }
+
+    fn tap(
+        &self,
+        direction: WireFrameDirection,
+        message: Option<Result<Message, MessageDecodingError>>,
+        web_socket_message: WebSocketMessage,
+    ) {
+        self.frame_tx
+            .send(WireFrame::new(direction, message, web_socket_message))
+            .ok();
+    }
+
+    /// Taps `message` sent or received by a [Transport] that has no frames of its own, as the
+    /// frame `serializer` encodes it into.
+    fn tap_message(
+        &self,
+        direction: WireFrameDirection,
+        message: &Message,
+        serializer: &dyn Serializer,
+    ) {
+        if !self.is_tapped() {
+            return;
+        }
+
+        match serializer.encode(message.clone()) {
+            Ok(frame) => self.tap(
+                direction,
+                Some(Ok(message.clone())),
+                WebSocketMessage::from(&frame),
+            ),
+            Err(error) => debug!("not tapping message that can't be encoded: {}", error),
+        }
+    }
+}
+
+/// Wraps the [Connector] passed to
+/// [Socket::spawn_with_connector](crate::Socket::spawn_with_connector), so that the [Message]s
+/// sent and received over its [Transport]s are tapped like the frames of [DefaultTransport].
+pub(crate) struct TappedConnector<C> {
+    connector: C,
+    /// Encodes the [Message]s into the [WireFrame::web_socket_message] of the frames.
+    serializer: Arc<dyn Serializer>,
+    frame_tap: FrameTap,
+}
+impl<C> TappedConnector<C> {
+    pub(crate) fn new(connector: C, serializer: Arc<dyn Serializer>, frame_tap: FrameTap) -> Self {
+        Self {
+            connector,
+            serializer,
+            frame_tap,
+        }
+    }
+}
+impl<C: Connector> Connector for TappedConnector<C> {
+    type Transport = TappedTransport<C::Transport>;
+
+    fn connect(
+        &self,
+        request: ConnectRequest,
+    ) -> BoxFuture<'_, Result<Self::Transport, TransportError>> {
+        Box::pin(async move {
+            let transport = self.connector.connect(request).await?;
+
+            Ok(TappedTransport {
+                transport,
+                serializer: self.serializer.clone(),
+                frame_tap: self.frame_tap.clone(),
+            })
+        })
+    }
+}
+
+/// The [Transport] opened by [TappedConnector].
+pub(crate) struct TappedTransport<T> {
+    transport: T,
+    /// [TappedConnector::serializer]
+    serializer: Arc<dyn Serializer>,
+    frame_tap: FrameTap,
+}
+impl<T: Transport> Stream for TappedTransport<T> {
+    type Item = Result<Message, TransportError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        let item = ready!(this.transport.poll_next_unpin(cx));
+
+        if let Some(Ok(message)) = &item {
+            this.frame_tap.tap_message(
+                WireFrameDirection::Inbound,
+                message,
+                this.serializer.as_ref(),
+            );
+        }
+
+        Poll::Ready(item)
+    }
+}
+impl<T: Transport> Sink<Message> for TappedTransport<T> {
+    type Error = TransportError;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.get_mut().transport.poll_ready_unpin(cx)
+    }
+
+    fn start_send(self: Pin<&mut Self>, message: Message) -> Result<(), Self::Error> {
+        let this = self.get_mut();
+        this.frame_tap.tap_message(
+            WireFrameDirection::Outbound,
+            &message,
+            this.serializer.as_ref(),
+        );
+
+        this.transport.start_send_unpin(message)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.get_mut().transport.poll_flush_unpin(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.get_mut().transport.poll_close_unpin(cx)
+    }
+}
+
+/// The `Sec-WebSocket-Protocol` value carrying `auth_token` the same way as `phoenix.js`:
+/// `phoenix` followed by the token base64 encoded without padding after
+/// [AUTH_TOKEN_SUBPROTOCOL_PREFIX].
+fn auth_token_subprotocols(subprotocols: &[String], auth_token: &str) -> HeaderValue {
+    let auth_token_subprotocol = format!(
+        "{}{}",
+        AUTH_TOKEN_SUBPROTOCOL_PREFIX,
+        STANDARD_NO_PAD.encode(auth_token)
+    );
+    let mut all_subprotocols: Vec<&str> = subprotocols.iter().map(String::as_str).collect();
+
+    if !all_subprotocols.contains(&"phoenix") {
+        all_subprotocols.insert(0, "phoenix");
+    }
+
+    all_subprotocols.push(&auth_token_subprotocol);
+
+    // subprotocols were validated by SocketOptions::validate and base64 is always a valid value
+    HeaderValue::from_str(&all_subprotocols.join(", ")).unwrap()
+}
+
+const AUTH_TOKEN_SUBPROTOCOL_PREFIX: &str = "base64url.bearer.phx.";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn auth_token_subprotocols_starts_with_phoenix() {
+        assert_eq!(
+            auth_token_subprotocols(&[], "secret"),
+            "phoenix, base64url.bearer.phx.c2VjcmV0"
+        );
+    }
+
+    #[test]
+    fn auth_token_subprotocols_keeps_configured_subprotocols() {
+        assert_eq!(
+            auth_token_subprotocols(&["phoenix".to_string(), "v2".to_string()], "secret"),
+            "phoenix, v2, base64url.bearer.phx.c2VjcmV0"
+        );
+    }
+}
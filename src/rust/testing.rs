@@ -0,0 +1,883 @@
+//! An in-process stand-in for a Phoenix endpoint, so that code using [Socket] and
+//! [Channel](crate::Channel) can be tested without a server.  Only available with the `testing`
+//! feature.
+//!
+//! A [FakeServer] opens a [Loopback] for each connection, which encodes and decodes every
+//! [Message] in the same wire format as the built-in web socket transport with the V2 serializer,
+//! regardless of [SocketOptions::serializer_version].  Joins are replied to with `ok` unless
+//! scripted with [FakeServer::reply_to_join], while calls are only replied to when scripted with
+//! [FakeServer::reply_to_call].
+//!
+//! ```
+//! # use phoenix_channels_client::testing::{json_payload, FakeServer, ScriptedReply, TIMEOUT};
+//! # use phoenix_channels_client::Event;
+//! # use serde_json::json;
+//! #
+//! # #[tokio::main]
+//! # async fn main() {
+//! let server = FakeServer::new();
+//! server.reply_to_call(
+//!     "room:lobby",
+//!     "ping",
+//!     ScriptedReply::Ok(json_payload(json!({ "pong": true }))),
+//! );
+//!
+//! let (socket, channel) = server.joined_channel("room:lobby").await;
+//!
+//! let reply = channel
+//!     .call(
+//!         Event::from_string("ping".to_string()),
+//!         json_payload(json!({})),
+//!         TIMEOUT,
+//!     )
+//!     .await
+//!     .unwrap();
+//! assert_eq!(reply.to_string(), r#"{"pong":true}"#);
+//!
+//! socket.shutdown().await.unwrap();
+//! # }
+//! ```
+
+use std::collections::{HashMap, VecDeque};
+use std::io;
+use std::pin::Pin;
+use std::str;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use bytes::{BufMut, Bytes};
+use futures::channel::mpsc;
+use futures::future::BoxFuture;
+use futures::{Sink, Stream, StreamExt};
+use serde_json::{json, Value};
+use tokio_tungstenite::tungstenite;
+use url::Url;
+
+use crate::ffi::channel::Channel;
+use crate::ffi::message::PhoenixEvent;
+use crate::ffi::socket::options::SocketOptions;
+use crate::ffi::socket::{Socket, SocketError};
+use crate::ffi::topic::Topic;
+use crate::rust::join_reference::JoinReference;
+use crate::rust::message::{
+    Control, Event, EventPayload, Message, MessageDecodingError, Payload, Push,
+};
+use crate::rust::transport::{ConnectRequest, Connector, TransportError};
+
+/// The `url` [FakeServer::socket] spawns [Socket]s with.
+pub const URL: &str = "ws://phoenix.test/socket/websocket";
+
+/// How long [FakeServer::connected_socket] and [FakeServer::joined_channel] wait to connect and
+/// join, which is also long enough for calls and waits in tests against a [FakeServer].
+pub const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// `json` as a [Payload](crate::Payload) to script replies, pushes and broadcasts with.
+pub fn json_payload(json: Value) -> crate::Payload {
+    Payload::from(json).into()
+}
+
+/// An in-memory Phoenix endpoint that [Socket]s connect to through [FakeServer::connector].
+///
+/// Clones share the same state, so a clone can be moved into the code under test.
+#[derive(Clone)]
+pub struct FakeServer {
+    state: Arc<Mutex<ServerState>>,
+    received_rx: Arc<tokio::sync::Mutex<mpsc::UnboundedReceiver<Received>>>,
+}
+impl FakeServer {
+    /// A new [FakeServer] that accepts connections.
+    pub fn new() -> Self {
+        let (received_tx, received_rx) = mpsc::unbounded();
+
+        Self {
+            state: Arc::new(Mutex::new(ServerState {
+                accepting_connections: true,
+                next_connection_id: 0,
+                frame_tx_by_connection_id: Default::default(),
+                joins_by_topic: Default::default(),
+                join_replies_by_topic: Default::default(),
+                call_replies_by_topic_event: Default::default(),
+                received_tx,
+            })),
+            received_rx: Arc::new(tokio::sync::Mutex::new(received_rx)),
+        }
+    }
+
+    /// Spawns a [Socket] that connects to this server.
+    pub fn socket(&self) -> Result<Arc<Socket>, SocketError> {
+        self.socket_with_options(SocketOptions::default())
+    }
+
+    /// Spawns a [Socket] tuned by `options` that connects to this server.
+    pub fn socket_with_options(&self, options: SocketOptions) -> Result<Arc<Socket>, SocketError> {
+        let socket =
+            Socket::spawn_with_connector(Url::parse(URL).unwrap(), options, self.connector())?;
+
+        Ok(socket)
+    }
+
+    /// Spawns a [Socket] and waits until it is connected to this server.
+    ///
+    /// # Panics
+    ///
+    /// If the [Socket] doesn't connect within [TIMEOUT].
+    pub async fn connected_socket(&self) -> Arc<Socket> {
+        let socket = self.socket().unwrap();
+        socket.connect(TIMEOUT).await.unwrap();
+
+        socket
+    }
+
+    /// Spawns a [Socket] connected to this server and waits until its [Channel] for `topic` is
+    /// joined.  The join is still returned by [FakeServer::next_received].
+    ///
+    /// # Panics
+    ///
+    /// If the [Socket] doesn't connect or the [Channel] doesn't join within [TIMEOUT].
+    pub async fn joined_channel(&self, topic: &str) -> (Arc<Socket>, Arc<Channel>) {
+        let socket = self.connected_socket().await;
+        let channel = socket
+            .channel(Topic::from_string(topic.to_string()), None)
+            .await
+            .unwrap();
+        channel.join(TIMEOUT).await.unwrap();
+
+        (socket, channel)
+    }
+
+    /// Opens a [Loopback] to this server for each connect, for
+    /// [Socket::spawn_with_connector](crate::Socket::spawn_with_connector).
+    pub fn connector(&self) -> FakeConnector {
+        FakeConnector {
+            state: self.state.clone(),
+        }
+    }
+
+    /// Whether new connections are accepted.  When not, connects fail with
+    /// [io::ErrorKind::ConnectionRefused].
+    pub fn set_accepting_connections(&self, accepting_connections: bool) {
+        self.state.lock().unwrap().accepting_connections = accepting_connections;
+    }
+
+    /// The number of open connections.
+    pub fn connection_count(&self) -> usize {
+        self.state.lock().unwrap().frame_tx_by_connection_id.len()
+    }
+
+    /// Whether any connection has joined `topic`.
+    pub fn is_joined(&self, topic: &str) -> bool {
+        self.state
+            .lock()
+            .unwrap()
+            .joins_by_topic
+            .get(topic)
+            .map_or(false, |joins| !joins.is_empty())
+    }
+
+    /// Replies to the next join of `topic` with `reply` instead of `ok` with `{}`.  Each call
+    /// scripts one more join, in order.
+    pub fn reply_to_join(&self, topic: &str, reply: ScriptedReply) {
+        self.state
+            .lock()
+            .unwrap()
+            .join_replies_by_topic
+            .entry(topic.to_string())
+            .or_default()
+            .push_back(reply);
+    }
+
+    /// Replies to the next call of `event` on `topic` with `reply`.  Each call scripts one more
+    /// call, in order.  Calls that aren't scripted aren't replied to.
+    pub fn reply_to_call(&self, topic: &str, event: &str, reply: ScriptedReply) {
+        self.state
+            .lock()
+            .unwrap()
+            .call_replies_by_topic_event
+            .entry((topic.to_string(), event.to_string()))
+            .or_default()
+            .push_back(reply);
+    }
+
+    /// Pushes `event` with `payload` to each channel that joined `topic`, like `push/3` in a
+    /// Phoenix channel.
+    pub fn push(&self, topic: &str, event: &str, payload: crate::Payload) {
+        let payload: Payload = payload.into();
+        let state = self.state.lock().unwrap();
+
+        for (connection_id, join_reference) in state.joins(topic) {
+            state.send(
+                connection_id,
+                push_frame(Some(&join_reference), topic, event, payload.clone()),
+            );
+        }
+    }
+
+    /// Broadcasts `event` with `payload` to each channel that joined `topic`, like `broadcast/3`
+    /// in a Phoenix channel.
+    pub fn broadcast(&self, topic: &str, event: &str, payload: crate::Payload) {
+        let payload: Payload = payload.into();
+        let state = self.state.lock().unwrap();
+
+        for (connection_id, _) in state.joins(topic) {
+            state.send(connection_id, push_frame(None, topic, event, payload.clone()));
+        }
+    }
+
+    /// Broadcasts `event` with `payload` on `topic` to every connection, whether or not any of
+    /// its channels joined `topic`, like the fastlaned subscription data of Absinthe.
+    pub fn fastlane(&self, topic: &str, event: &str, payload: crate::Payload) {
+        let payload: Payload = payload.into();
+        let state = self.state.lock().unwrap();
+
+        for connection_id in state.frame_tx_by_connection_id.keys() {
+            state.send(*connection_id, push_frame(None, topic, event, payload.clone()));
+        }
+    }
+
+    /// Sends `phx_error` to each channel that joined `topic`, as if its channel process crashed.
+    pub fn error(&self, topic: &str) {
+        self.stop_channel(topic, PhoenixEvent::Error);
+    }
+
+    /// Sends `phx_close` to each channel that joined `topic`, as if its channel process stopped,
+    /// so the channels rejoin.
+    pub fn close(&self, topic: &str) {
+        self.stop_channel(topic, PhoenixEvent::Close);
+    }
+
+    /// Closes every connection, so [Socket]s wait to reconnect.
+    pub fn disconnect(&self) {
+        let mut state = self.state.lock().unwrap();
+
+        // Dropping the senders ends each [Loopback] stream
+        state.frame_tx_by_connection_id.clear();
+        state.joins_by_topic.clear();
+    }
+
+    /// The next [Received] message, waiting until one is sent.
+    pub async fn next_received(&self) -> Received {
+        self.received_rx
+            .lock()
+            .await
+            .next()
+            .await
+            .expect("server state holds the sender")
+    }
+
+    fn stop_channel(&self, topic: &str, event: PhoenixEvent) {
+        let mut state = self.state.lock().unwrap();
+
+        for (connection_id, join_reference) in state.joins_by_topic.remove(topic).unwrap_or_default()
+        {
+            let frame = json!([
+                join_reference,
+                join_reference,
+                topic,
+                event.to_string(),
+                {}
+            ]);
+            state.send(connection_id, tungstenite::Message::Text(frame.to_string()));
+        }
+    }
+}
+impl Default for FakeServer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// How a [FakeServer] replies to a join or call.
+#[derive(Clone, Debug)]
+pub enum ScriptedReply {
+    /// Replies with `{:ok, payload}`.
+    Ok(crate::Payload),
+    /// Replies with `{:error, payload}`, which rejects joins.
+    Error(crate::Payload),
+    /// Doesn't reply, like `{:noreply, socket}`, so the join or call times out.
+    NoReply,
+}
+
+/// A message a [FakeServer] received from a channel, including joins and leaves, but not
+/// heartbeats.
+#[derive(Clone, Debug)]
+pub struct Received {
+    /// The topic of the channel that sent the message.
+    pub topic: String,
+    /// The event the channel sent.
+    pub event: crate::Event,
+    /// The payload sent with [Received::event].
+    pub payload: crate::Payload,
+}
+
+/// Opens [Loopback]s to a [FakeServer].
+pub struct FakeConnector {
+    state: Arc<Mutex<ServerState>>,
+}
+impl Connector for FakeConnector {
+    type Transport = Loopback;
+
+    fn connect(&self, _request: ConnectRequest) -> BoxFuture<'_, Result<Loopback, TransportError>> {
+        let result = self
+            .state
+            .lock()
+            .unwrap()
+            .accept()
+            .map(|(connection_id, frame_rx)| Loopback {
+                state: self.state.clone(),
+                connection_id,
+                frame_rx,
+                closed: false,
+            })
+            .map_err(From::from);
+
+        Box::pin(async move { result })
+    }
+}
+
+/// The client end of a connection to a [FakeServer].
+pub struct Loopback {
+    state: Arc<Mutex<ServerState>>,
+    connection_id: u64,
+    frame_rx: mpsc::UnboundedReceiver<tungstenite::Message>,
+    /// Whether [TransportError::ConnectionClosed] was already returned.
+    closed: bool,
+}
+impl Stream for Loopback {
+    type Item = Result<Message, TransportError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if self.closed {
+            return Poll::Ready(None);
+        }
+
+        match futures::ready!(self.frame_rx.poll_next_unpin(cx)) {
+            Some(frame) => Poll::Ready(Some(
+                Message::decode(frame)
+                    .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error).into()),
+            )),
+            None => {
+                self.closed = true;
+
+                Poll::Ready(Some(Err(TransportError::ConnectionClosed)))
+            }
+        }
+    }
+}
+impl Sink<Message> for Loopback {
+    type Error = TransportError;
+
+    fn poll_ready(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn start_send(self: Pin<&mut Self>, message: Message) -> Result<(), Self::Error> {
+        let frame = message
+            .encode()
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+
+        let mut state = self.state.lock().unwrap();
+
+        if !state.frame_tx_by_connection_id.contains_key(&self.connection_id) {
+            return Err(TransportError::ConnectionClosed);
+        }
+
+        state.receive(self.connection_id, frame)?;
+
+        Ok(())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.state.lock().unwrap().disconnect(self.connection_id);
+
+        Poll::Ready(Ok(()))
+    }
+}
+impl Drop for Loopback {
+    fn drop(&mut self) {
+        if let Ok(mut state) = self.state.lock() {
+            state.disconnect(self.connection_id);
+        }
+    }
+}
+
+struct ServerState {
+    accepting_connections: bool,
+    next_connection_id: u64,
+    /// Sends frames to the [Loopback] of each open connection.
+    frame_tx_by_connection_id: HashMap<u64, mpsc::UnboundedSender<tungstenite::Message>>,
+    /// The connection and [JoinReference] of each channel that joined a topic.
+    joins_by_topic: HashMap<String, Vec<(u64, JoinReference)>>,
+    join_replies_by_topic: HashMap<String, VecDeque<ScriptedReply>>,
+    call_replies_by_topic_event: HashMap<(String, String), VecDeque<ScriptedReply>>,
+    received_tx: mpsc::UnboundedSender<Received>,
+}
+impl ServerState {
+    fn accept(&mut self) -> io::Result<(u64, mpsc::UnboundedReceiver<tungstenite::Message>)> {
+        if !self.accepting_connections {
+            return Err(io::ErrorKind::ConnectionRefused.into());
+        }
+
+        let connection_id = self.next_connection_id;
+        self.next_connection_id += 1;
+
+        let (frame_tx, frame_rx) = mpsc::unbounded();
+        self.frame_tx_by_connection_id.insert(connection_id, frame_tx);
+
+        Ok((connection_id, frame_rx))
+    }
+
+    fn disconnect(&mut self, connection_id: u64) {
+        self.frame_tx_by_connection_id.remove(&connection_id);
+
+        for joins in self.joins_by_topic.values_mut() {
+            joins.retain(|(join_connection_id, _)| *join_connection_id != connection_id);
+        }
+    }
+
+    fn joins(&self, topic: &str) -> Vec<(u64, JoinReference)> {
+        self.joins_by_topic.get(topic).cloned().unwrap_or_default()
+    }
+
+    fn send(&self, connection_id: u64, frame: tungstenite::Message) {
+        if let Some(frame_tx) = self.frame_tx_by_connection_id.get(&connection_id) {
+            frame_tx.unbounded_send(frame).ok();
+        }
+    }
+
+    fn receive(&mut self, connection_id: u64, frame: tungstenite::Message) -> io::Result<()> {
+        let message = decode_client_frame(frame)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+
+        match message {
+            Message::Control(Control {
+                event: Event::Phoenix(PhoenixEvent::Heartbeat),
+                reference: Some(reference),
+                ..
+            }) => {
+                let frame = json!([
+                    null,
+                    reference,
+                    "phoenix",
+                    PhoenixEvent::Reply.to_string(),
+                    { "status": "ok", "response": {} }
+                ]);
+                self.send(connection_id, tungstenite::Message::Text(frame.to_string()));
+            }
+            Message::Push(push) => self.receive_push(connection_id, push),
+            other => log::debug!("fake server ignored {:?}", other),
+        }
+
+        Ok(())
+    }
+
+    fn receive_push(&mut self, connection_id: u64, push: Push) {
+        let Push {
+            topic,
+            event_payload: EventPayload { event, payload },
+            join_reference,
+            reference,
+        } = push;
+        let topic = topic.to_string();
+
+        self.received_tx
+            .unbounded_send(Received {
+                topic: topic.clone(),
+                event: event.clone().into(),
+                payload: payload.into(),
+            })
+            .ok();
+
+        let reply = match event {
+            Event::Phoenix(PhoenixEvent::Join) => {
+                let reply = self
+                    .join_replies_by_topic
+                    .get_mut(&topic)
+                    .and_then(VecDeque::pop_front)
+                    .unwrap_or_else(|| ScriptedReply::Ok(Payload::from(json!({})).into()));
+
+                if let ScriptedReply::Ok(_) = reply {
+                    let joins = self.joins_by_topic.entry(topic.clone()).or_default();
+                    joins.retain(|(join_connection_id, _)| *join_connection_id != connection_id);
+                    joins.push((connection_id, join_reference.clone()));
+                }
+
+                reply
+            }
+            Event::Phoenix(PhoenixEvent::Leave) => {
+                if let Some(joins) = self.joins_by_topic.get_mut(&topic) {
+                    joins.retain(|join| join != &(connection_id, join_reference.clone()));
+                }
+
+                ScriptedReply::Ok(Payload::from(json!({})).into())
+            }
+            event => self
+                .call_replies_by_topic_event
+                .get_mut(&(topic.clone(), event.to_string()))
+                .and_then(VecDeque::pop_front)
+                .unwrap_or(ScriptedReply::NoReply),
+        };
+
+        let (status, response) = match reply {
+            ScriptedReply::Ok(response) => ("ok", response),
+            ScriptedReply::Error(response) => ("error", response),
+            ScriptedReply::NoReply => return,
+        };
+
+        if let Some(reference) = reference {
+            self.send(
+                connection_id,
+                reply_frame(
+                    &join_reference,
+                    &reference.to_string(),
+                    &topic,
+                    status,
+                    response.into(),
+                ),
+            );
+        }
+    }
+}
+
+/// A push from the server in the Phoenix V2 serializer format, which is a broadcast without
+/// `join_reference`.
+fn push_frame(
+    join_reference: Option<&JoinReference>,
+    topic: &str,
+    event: &str,
+    payload: Payload,
+) -> tungstenite::Message {
+    match payload {
+        Payload::Value(value) => tungstenite::Message::Text(
+            json!([join_reference, null, topic, event, value.as_ref()]).to_string(),
+        ),
+        Payload::Binary(bytes) => {
+            let mut buffer = Vec::new();
+
+            match join_reference {
+                Some(join_reference) => {
+                    let join_reference = join_reference.to_string();
+                    buffer.put_u8(0);
+                    buffer.put_u8(join_reference.len() as u8);
+                    buffer.put_u8(topic.len() as u8);
+                    buffer.put_u8(event.len() as u8);
+                    buffer.put_slice(join_reference.as_bytes());
+                }
+                None => {
+                    buffer.put_u8(2);
+                    buffer.put_u8(topic.len() as u8);
+                    buffer.put_u8(event.len() as u8);
+                }
+            }
+
+            buffer.put_slice(topic.as_bytes());
+            buffer.put_slice(event.as_bytes());
+            buffer.put(bytes);
+
+            tungstenite::Message::Binary(buffer)
+        }
+    }
+}
+
+/// A reply from the server in the Phoenix V2 serializer format.
+fn reply_frame(
+    join_reference: &JoinReference,
+    reference: &str,
+    topic: &str,
+    status: &str,
+    response: Payload,
+) -> tungstenite::Message {
+    match response {
+        Payload::Value(value) => tungstenite::Message::Text(
+            json!([
+                join_reference,
+                reference,
+                topic,
+                PhoenixEvent::Reply.to_string(),
+                { "status": status, "response": value.as_ref() }
+            ])
+            .to_string(),
+        ),
+        Payload::Binary(bytes) => {
+            let join_reference = join_reference.to_string();
+            let mut buffer = Vec::new();
+            buffer.put_u8(1);
+            buffer.put_u8(join_reference.len() as u8);
+            buffer.put_u8(reference.len() as u8);
+            buffer.put_u8(topic.len() as u8);
+            buffer.put_u8(status.len() as u8);
+            buffer.put_slice(join_reference.as_bytes());
+            buffer.put_slice(reference.as_bytes());
+            buffer.put_slice(topic.as_bytes());
+            buffer.put_slice(status.as_bytes());
+            buffer.put(bytes);
+
+            tungstenite::Message::Binary(buffer)
+        }
+    }
+}
+
+/// Decodes a frame sent by the client.  JSON is the same format in both directions, but binary
+/// pushes from the client also have a reference, unlike those from the server.
+fn decode_client_frame(frame: tungstenite::Message) -> Result<Message, MessageDecodingError> {
+    let bytes = match frame {
+        tungstenite::Message::Binary(bytes) => bytes,
+        frame => return Message::decode(frame),
+    };
+    if bytes.len() < 5 {
+        return Err(MessageDecodingError::UnexpectedEof);
+    }
+
+    let (header, mut rest) = bytes.split_at(5);
+    let (join_reference_size, reference_size, topic_size, event_size) =
+        (header[1], header[2], header[3], header[4]);
+    let mut take_str = |size: u8| -> Result<String, MessageDecodingError> {
+        if rest.len() < size as usize {
+            return Err(MessageDecodingError::UnexpectedEof);
+        }
+
+        let (taken, remaining) = rest.split_at(size as usize);
+        rest = remaining;
+
+        Ok(str::from_utf8(taken)?.to_string())
+    };
+    let join_reference = take_str(join_reference_size)?;
+    let reference = take_str(reference_size)?;
+    let topic = take_str(topic_size)?;
+    let event = take_str(event_size)?;
+
+    Ok(Message::Push(Push {
+        topic: Topic::from_string(topic),
+        event_payload: EventPayload {
+            event: event.into(),
+            payload: Bytes::copy_from_slice(rest).into(),
+        },
+        join_reference: join_reference.into(),
+        reference: Some(reference).filter(|reference| !reference.is_empty()).map(From::from),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ChannelJoinError, ChannelStatus, ReconnectReason, SocketStatus};
+
+    #[tokio::test]
+    async fn join_is_replied_to_with_ok_by_default() {
+        let server = FakeServer::new();
+        let (socket, channel) = server.joined_channel("room:lobby").await;
+
+        assert_eq!(channel.status(), ChannelStatus::Joined);
+        assert!(server.is_joined("room:lobby"));
+
+        let received = server.next_received().await;
+        assert_eq!(received.topic, "room:lobby");
+        assert_eq!(received.event, crate::Event::Phoenix { phoenix: PhoenixEvent::Join });
+
+        socket.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn scripted_join_error_rejects_join() {
+        let server = FakeServer::new();
+        server.reply_to_join(
+            "room:lobby",
+            ScriptedReply::Error(json_payload(json!({ "reason": "unauthorized" }))),
+        );
+
+        let socket = server.connected_socket().await;
+        let channel = socket
+            .channel(Topic::from_string("room:lobby".to_string()), None)
+            .await
+            .unwrap();
+
+        match channel.join(TIMEOUT).await {
+            Err(ChannelJoinError::Rejected { rejection }) => {
+                assert_eq!(rejection, json_payload(json!({ "reason": "unauthorized" })))
+            }
+            other => panic!("join not rejected: {:?}", other),
+        }
+        assert!(!server.is_joined("room:lobby"));
+
+        socket.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn scripted_call_is_replied_to() {
+        let server = FakeServer::new();
+        server.reply_to_call(
+            "room:lobby",
+            "ping",
+            ScriptedReply::Ok(json_payload(json!({ "pong": 1 }))),
+        );
+        let (socket, channel) = server.joined_channel("room:lobby").await;
+
+        let reply = channel
+            .call(
+                crate::Event::from_string("ping".to_string()),
+                json_payload(json!({})),
+                TIMEOUT,
+            )
+            .await
+            .unwrap();
+        assert_eq!(reply, json_payload(json!({ "pong": 1 })));
+
+        socket.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn binary_call_is_received_and_replied_to() {
+        let server = FakeServer::new();
+        server.reply_to_call(
+            "room:lobby",
+            "upload",
+            ScriptedReply::Ok(crate::Payload::binary_from_bytes(vec![4, 5])),
+        );
+        let (socket, channel) = server.joined_channel("room:lobby").await;
+        server.next_received().await;
+
+        let reply = channel
+            .call(
+                crate::Event::from_string("upload".to_string()),
+                crate::Payload::binary_from_bytes(vec![1, 2, 3]),
+                TIMEOUT,
+            )
+            .await
+            .unwrap();
+        assert_eq!(reply, crate::Payload::binary_from_bytes(vec![4, 5]));
+
+        let received = server.next_received().await;
+        assert_eq!(received.event, crate::Event::from_string("upload".to_string()));
+        assert_eq!(received.payload, crate::Payload::binary_from_bytes(vec![1, 2, 3]));
+
+        socket.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn pushes_and_broadcasts_are_events() {
+        let server = FakeServer::new();
+        let (socket, channel) = server.joined_channel("room:lobby").await;
+        let events = channel.events();
+
+        server.push("room:lobby", "pushed", json_payload(json!({ "n": 1 })));
+        server.broadcast(
+            "room:lobby",
+            "broadcasted",
+            crate::Payload::binary_from_bytes(vec![2]),
+        );
+
+        let pushed = events.event().await.unwrap();
+        assert_eq!(pushed.event, crate::Event::from_string("pushed".to_string()));
+        assert_eq!(pushed.payload, json_payload(json!({ "n": 1 })));
+
+        let broadcasted = events.event().await.unwrap();
+        assert_eq!(
+            broadcasted.event,
+            crate::Event::from_string("broadcasted".to_string())
+        );
+        assert_eq!(broadcasted.payload, crate::Payload::binary_from_bytes(vec![2]));
+
+        socket.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn close_makes_channel_rejoin() {
+        let server = FakeServer::new();
+        let (socket, channel) = server.joined_channel("room:lobby").await;
+        let events = channel.events();
+        server.next_received().await;
+
+        server.close("room:lobby");
+
+        let closed = events.event().await.unwrap();
+        assert_eq!(closed.event, crate::Event::Phoenix { phoenix: PhoenixEvent::Close });
+
+        let rejoin = server.next_received().await;
+        assert_eq!(rejoin.event, crate::Event::Phoenix { phoenix: PhoenixEvent::Join });
+
+        socket.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn disconnect_makes_socket_reconnect() {
+        let server = FakeServer::new();
+        let (socket, _channel) = server.joined_channel("room:lobby").await;
+        server.next_received().await;
+        let statuses = socket.statuses();
+
+        server.disconnect();
+
+        match statuses.status().await.unwrap() {
+            Ok(SocketStatus::WaitingToReconnect { reason, .. }) => {
+                assert_eq!(reason, ReconnectReason::ConnectionLost)
+            }
+            other => panic!("socket not waiting to reconnect: {:?}", other),
+        }
+
+        // Channels rejoin once the socket reconnects
+        let rejoin = server.next_received().await;
+        assert_eq!(rejoin.event, crate::Event::Phoenix { phoenix: PhoenixEvent::Join });
+        assert_eq!(server.connection_count(), 1);
+
+        socket.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn events_stream_ends_after_off() {
+        use futures::StreamExt;
+
+        let server = FakeServer::new();
+        let (socket, channel) = server.joined_channel("room:lobby").await;
+        let events = channel.events();
+        let mut stream = events.clone().into_stream();
+
+        server.broadcast("room:lobby", "new_msg", json_payload(serde_json::json!({})));
+        let event_payload = stream.next().await.unwrap().unwrap();
+        assert_eq!(
+            event_payload.event,
+            crate::Event::from_string("new_msg".to_string())
+        );
+
+        channel.off(events);
+        assert!(stream.next().await.is_none());
+
+        socket.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn statuses_streams_yield_status_changes() {
+        use futures::StreamExt;
+
+        let server = FakeServer::new();
+        let socket = server.socket().unwrap();
+        let mut socket_statuses = socket.statuses().into_stream();
+        socket.connect(TIMEOUT).await.unwrap();
+
+        assert!(matches!(
+            socket_statuses.next().await,
+            Some(Ok(Ok(SocketStatus::Connected)))
+        ));
+
+        let channel = socket
+            .channel(Topic::from_string("room:lobby".to_string()), None)
+            .await
+            .unwrap();
+        let mut channel_statuses = channel.statuses().into_stream();
+        channel.join(TIMEOUT).await.unwrap();
+
+        let mut joined = false;
+        while let Some(status) = channel_statuses.next().await {
+            if matches!(status, Ok(Ok(ChannelStatus::Joined))) {
+                joined = true;
+                break;
+            }
+        }
+        assert!(joined);
+
+        socket.shutdown().await.unwrap();
+    }
+}
@@ -0,0 +1,231 @@
+//! Connections to the server other than the built-in web socket and long poll transports, such as
+//! an in-memory duplex for tests, a Unix domain socket tunnel or web sockets over QUIC.
+//!
+//! A [Connector] opens a new [Transport] for each [Socket::connect](crate::Socket::connect) and
+//! automatic reconnect, and is passed to
+//! [Socket::spawn_with_connector](crate::Socket::spawn_with_connector).  A [Transport] sends and
+//! receives whole [Message]s, so it decides how they are framed and serialized.
+//!
+//! ```
+//! # use futures::future::BoxFuture;
+//! #
+//! # use phoenix_channels_client::transport::{ConnectRequest, Connector, TransportError};
+//! #
+//! /// Opens the same connections as the wrapped [Connector], but logs every URL it connects to.
+//! struct LoggingConnector<C>(C);
+//! impl<C: Connector> Connector for LoggingConnector<C> {
+//!     type Transport = C::Transport;
+//!
+//!     fn connect(
+//!         &self,
+//!         request: ConnectRequest,
+//!     ) -> BoxFuture<'_, Result<Self::Transport, TransportError>> {
+//!         println!("connecting to {}", request.url);
+//!
+//!         self.0.connect(request)
+//!     }
+//! }
+//! ```
+
+use std::io;
+
+use futures::future::BoxFuture;
+use futures::{Sink, Stream};
+use tokio_tungstenite::tungstenite;
+use tokio_tungstenite::tungstenite::http::HeaderMap;
+use url::Url;
+
+pub use crate::rust::join_reference::JoinReference;
+pub use crate::rust::message::{
+    Broadcast, Control, Event, EventPayload, Message, MessageDecodingError, MessageEncodingError,
+    Payload, Push, Reply, ReplyStatus,
+};
+pub use crate::rust::reference::Reference;
+
+/// A connection to the server that [Message]s are sent and received over.
+///
+/// Implemented for any [Sink] and [Stream] of [Message]s with [TransportError]s.
+pub trait Transport:
+    Stream<Item = Result<Message, TransportError>>
+    + Sink<Message, Error = TransportError>
+    + Send
+    + Sync
+    + Unpin
+    + 'static
+{
+}
+impl<T> Transport for T where
+    T: Stream<Item = Result<Message, TransportError>>
+        + Sink<Message, Error = TransportError>
+        + Send
+        + Sync
+        + Unpin
+        + 'static
+{
+}
+
+/// Opens a new [Transport] for each [Socket::connect](crate::Socket::connect) and automatic
+/// reconnect.
+pub trait Connector: Send + Sync + 'static {
+    /// The connection opened by [Connector::connect].
+    type Transport: Transport;
+
+    /// Opens a connection for `request`.  The [Socket](crate::Socket) applies the `timeout` passed
+    /// to [Socket::connect](crate::Socket::connect) or
+    /// [SocketOptions::connect_timeout](crate::SocketOptions::connect_timeout), so the returned
+    /// future doesn't need its own.
+    fn connect(&self, request: ConnectRequest)
+        -> BoxFuture<'_, Result<Self::Transport, TransportError>>;
+}
+
+/// What a [Connector] needs to open a [Transport] for a single connection attempt.
+#[derive(Clone, Debug)]
+pub struct ConnectRequest {
+    /// The `url` passed to [Socket::spawn](crate::Socket::spawn) with the `vsn` param and
+    /// [ConnectParams::query_params](crate::ConnectParams::query_params).
+    pub url: Url,
+    /// [SocketOptions::headers](crate::SocketOptions::headers),
+    /// [SocketOptions::subprotocols](crate::SocketOptions::subprotocols) as
+    /// `Sec-WebSocket-Protocol` and [ConnectParams::headers](crate::ConnectParams::headers).
+    pub headers: HeaderMap,
+    /// [Socket::set_auth_token](crate::Socket::set_auth_token)
+    pub auth_token: Option<String>,
+}
+
+/// Errors sending or receiving over a [Transport] or opening it with a [Connector].
+#[derive(Debug, thiserror::Error)]
+pub enum TransportError {
+    /// The connection was closed, so the [Socket](crate::Socket) waits to reconnect.
+    #[error("connection closed")]
+    ConnectionClosed,
+    /// Sending or receiving failed, so the [Socket](crate::Socket) waits to reconnect.
+    #[error("io error: {0}")]
+    Io(#[from] io::Error),
+    /// Errors from the built-in web socket and long poll transports.
+    #[error("web socket error: {0}")]
+    WebSocket(#[from] tungstenite::Error),
+}
+impl From<TransportError> for tungstenite::Error {
+    fn from(transport_error: TransportError) -> Self {
+        match transport_error {
+            TransportError::ConnectionClosed => tungstenite::Error::ConnectionClosed,
+            TransportError::Io(io_error) => tungstenite::Error::Io(io_error),
+            TransportError::WebSocket(web_socket_error) => web_socket_error,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::pin::Pin;
+    use std::sync::Mutex;
+    use std::task::{Context, Poll};
+    use std::time::Duration;
+
+    use futures::channel::mpsc;
+    use futures::StreamExt;
+    use serde_json::json;
+
+    use super::*;
+    use crate::{ChannelStatus, PhoenixEvent, Socket, SocketOptions, Topic};
+
+    /// The client end of an in-memory connection.
+    struct Duplex {
+        received_rx: mpsc::UnboundedReceiver<Result<Message, TransportError>>,
+        sent_tx: mpsc::UnboundedSender<Message>,
+    }
+    impl Stream for Duplex {
+        type Item = Result<Message, TransportError>;
+
+        fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+            self.received_rx.poll_next_unpin(cx)
+        }
+    }
+    impl Sink<Message> for Duplex {
+        type Error = TransportError;
+
+        fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Pin::new(&mut self.sent_tx)
+                .poll_ready(cx)
+                .map_err(|_| TransportError::ConnectionClosed)
+        }
+
+        fn start_send(mut self: Pin<&mut Self>, message: Message) -> Result<(), Self::Error> {
+            Pin::new(&mut self.sent_tx)
+                .start_send(message)
+                .map_err(|_| TransportError::ConnectionClosed)
+        }
+
+        fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Pin::new(&mut self.sent_tx)
+                .poll_flush(cx)
+                .map_err(|_| TransportError::ConnectionClosed)
+        }
+
+        fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Pin::new(&mut self.sent_tx)
+                .poll_close(cx)
+                .map_err(|_| TransportError::ConnectionClosed)
+        }
+    }
+
+    /// Hands out a single [Duplex].
+    struct DuplexConnector(Mutex<Option<Duplex>>);
+    impl Connector for DuplexConnector {
+        type Transport = Duplex;
+
+        fn connect(&self, _request: ConnectRequest) -> BoxFuture<'_, Result<Duplex, TransportError>> {
+            let duplex = self.0.lock().unwrap().take();
+
+            Box::pin(async move { duplex.ok_or(TransportError::ConnectionClosed) })
+        }
+    }
+
+    #[tokio::test]
+    async fn socket_joins_over_custom_transport() {
+        let (sent_tx, mut sent_rx) = mpsc::unbounded();
+        let (received_tx, received_rx) = mpsc::unbounded();
+        let connector = DuplexConnector(Mutex::new(Some(Duplex {
+            received_rx,
+            sent_tx,
+        })));
+
+        // Reply to joins like a server would
+        tokio::spawn(async move {
+            while let Some(message) = sent_rx.next().await {
+                if let Message::Push(push) = message {
+                    if push.event_payload.event == PhoenixEvent::Join {
+                        received_tx
+                            .unbounded_send(Ok(Message::Reply(Reply {
+                                topic: push.topic,
+                                event: PhoenixEvent::Reply.into(),
+                                payload: json!({}).into(),
+                                join_reference: push.join_reference,
+                                reference: push.reference.unwrap(),
+                                status: ReplyStatus::Ok,
+                            })))
+                            .unwrap();
+                    }
+                }
+            }
+        });
+
+        let socket = Socket::spawn_with_connector(
+            Url::parse("memory://phoenix/socket").unwrap(),
+            SocketOptions::default(),
+            connector,
+        )
+        .unwrap();
+        socket.connect(Duration::from_secs(5)).await.unwrap();
+
+        let channel = socket
+            .channel(Topic::from_string("room:lobby".to_string()), None)
+            .await
+            .unwrap();
+        channel.join(Duration::from_secs(5)).await.unwrap();
+
+        assert_eq!(channel.status(), ChannelStatus::Joined);
+
+        socket.shutdown().await.unwrap();
+    }
+}
@@ -0,0 +1,117 @@
+#![cfg(feature = "derive")]
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use phoenix_channels_client::{
+    ChannelEvents, ChannelEventsError, Event, EventPayload, Payload, PhoenixEvent,
+};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Message {
+    body: String,
+}
+
+#[derive(Debug, PartialEq, ChannelEvents)]
+enum RoomEvent {
+    #[channel_event(rename = "new_msg")]
+    NewMessage(Message),
+    UserTyping,
+    Presence(serde_json::Value),
+}
+
+fn json_payload(json: serde_json::Value) -> Payload {
+    Payload::json_from_serialized(json.to_string()).unwrap()
+}
+
+fn user_event(name: &str) -> Event {
+    Event::User {
+        user: name.to_string(),
+    }
+}
+
+#[test]
+fn newtype_variant_is_encoded_with_renamed_event() {
+    let (event, payload) = RoomEvent::NewMessage(Message {
+        body: "hi".to_string(),
+    })
+    .into_event_payload()
+    .unwrap();
+
+    assert_eq!(event, user_event("new_msg"));
+    assert_eq!(payload, json_payload(json!({ "body": "hi" })));
+}
+
+#[test]
+fn unit_variant_is_encoded_with_snake_case_event_and_empty_payload() {
+    let (event, payload) = RoomEvent::UserTyping.into_event_payload().unwrap();
+
+    assert_eq!(event, user_event("user_typing"));
+    assert_eq!(payload, json_payload(json!({})));
+}
+
+#[test]
+fn event_payload_is_decoded() {
+    assert_eq!(
+        RoomEvent::from_event_payload(EventPayload {
+            event: user_event("new_msg"),
+            payload: json_payload(json!({ "body": "hi" })),
+        })
+        .unwrap(),
+        RoomEvent::NewMessage(Message {
+            body: "hi".to_string()
+        })
+    );
+    assert_eq!(
+        RoomEvent::from_event_payload(EventPayload {
+            event: user_event("user_typing"),
+            payload: json_payload(json!(null)),
+        })
+        .unwrap(),
+        RoomEvent::UserTyping
+    );
+    assert_eq!(
+        RoomEvent::from_event_payload(EventPayload {
+            event: user_event("presence"),
+            payload: json_payload(json!({ "joins": {} })),
+        })
+        .unwrap(),
+        RoomEvent::Presence(json!({ "joins": {} }))
+    );
+}
+
+#[test]
+fn unknown_event_errors() {
+    for event in [
+        user_event("NewMessage"),
+        Event::Phoenix {
+            phoenix: PhoenixEvent::Close,
+        },
+    ] {
+        match RoomEvent::from_event_payload(EventPayload {
+            event: event.clone(),
+            payload: json_payload(json!({})),
+        }) {
+            Err(ChannelEventsError::UnknownEvent { event: unknown }) => assert_eq!(unknown, event),
+            other => panic!("{} decoded as {:?}", event, other),
+        }
+    }
+}
+
+#[test]
+fn mismatched_payload_errors() {
+    for payload in [
+        json_payload(json!({ "text": "hi" })),
+        Payload::binary_from_bytes(vec![0, 1, 2, 3]),
+    ] {
+        match RoomEvent::from_event_payload(EventPayload {
+            event: user_event("new_msg"),
+            payload,
+        }) {
+            Err(ChannelEventsError::Deserialization { event, .. }) => {
+                assert_eq!(event, user_event("new_msg"))
+            }
+            other => panic!("payload decoded as {:?}", other),
+        }
+    }
+}
@@ -3,6 +3,7 @@
 
 #[cfg(feature = "nightly")]
 use std::assert_matches::assert_matches;
+use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::Duration;
 
@@ -17,8 +18,8 @@ use uuid::Uuid;
 // the foreign bindings
 use phoenix_channels_client::{
     CallError, ChannelJoinError, ChannelStatus, ChannelStatusJoinError, ChannelStatuses,
-    ConnectError, PhoenixError, Event, EventPayload, IoError, Payload, Socket, SocketStatus, Topic,
-    WebSocketError, JSON,
+    ConnectError, ConnectParams, PhoenixError, Event, EventPayload, IoError, Payload, Socket,
+    SocketStatus, Topic, WebSocketError, JSON,
 };
 
 #[cfg(not(feature = "nightly"))]
@@ -431,6 +432,70 @@ async fn socket_key_rotation_test() -> Result<(), PhoenixError> {
     Ok(())
 }
 
+#[tokio::test]
+async fn socket_params_provider_key_rotation_test() -> Result<(), PhoenixError> {
+    let _ = env_logger::builder()
+        .parse_default_env()
+        .filter_level(log::LevelFilter::Debug)
+        .is_test(true)
+        .try_init();
+
+    let id = id();
+    let shared_secret_url = shared_secret_url(id.clone());
+    let socket = connected_socket(shared_secret_url).await?;
+    let secret = generate_secret(&socket).await?;
+    let secret_url = secret_url(id, secret);
+    let secret_socket = connected_socket(secret_url).await?;
+
+    // generate a new secret for every reconnect, so deleting the current secret isn't permanent
+    secret_socket.set_async_params_provider(move || {
+        let socket = socket.clone();
+
+        async move {
+            let secret = generate_secret(&socket).await.unwrap();
+
+            ConnectParams {
+                query_params: Some(HashMap::from([("secret".to_string(), secret)])),
+                headers: None,
+            }
+        }
+    });
+
+    let secret_channel = secret_socket
+        .channel(Topic::from_string("channel:secret".to_string()), None)
+        .await?;
+    secret_channel.join(JOIN_TIMEOUT).await?;
+
+    let statuses = secret_socket.statuses();
+
+    match secret_channel
+        .call(
+            Event::from_string("delete_secret".to_string()),
+            Payload::json_from_serialized(json!({}).to_string()).unwrap(),
+            CALL_TIMEOUT,
+        )
+        .await
+    {
+        Ok(payload) => panic!(
+            "Deleting secret succeeded without disconnecting socket and returned payload: {:?}",
+            payload
+        ),
+        Err(CallError::SocketDisconnected) => (),
+        Err(other) => panic!("Error other than SocketDisconnected: {:?}", other),
+    }
+
+    assert_matches!(
+        timeout(CALL_TIMEOUT, statuses.status()).await??,
+        Ok(SocketStatus::WaitingToReconnect { .. })
+    );
+    assert_matches!(
+        timeout(CONNECT_TIMEOUT, statuses.status()).await??,
+        Ok(SocketStatus::Connected)
+    );
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn phoenix_channels_socket_disconnect_reconnect_test() -> Result<(), PhoenixError> {
     phoenix_channels_reconnect_test(Event::from_string("socket_disconnect".to_string())).await
@@ -497,6 +562,7 @@ async fn phoenix_channels_reconnect_test(event: Event) -> Result<(), PhoenixErro
             }
             CallError::SocketDisconnected => panic!("socket disconnected"),
             CallError::Reply { reply } => panic!("Error from server: {:?}", reply),
+            CallError::BinaryPayloadUnsupported => panic!("binary payload unsupported"),
         },
     };
 